@@ -0,0 +1,142 @@
+use poem::{http::StatusCode, Endpoint, Error, Request, Result};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+const DEFAULT_MAX_STREAMS_PER_KEY: usize = 10;
+
+/// Keys accepted on the webhook and pool event routes, from `API_KEYS` (comma separated).
+///
+/// Clients send one as `Authorization: Bearer <key>` or `x-api-key`. Without any keys
+/// configured those routes reject every request.
+#[derive(Clone, Default)]
+pub struct ApiKeys(Arc<HashSet<String>>);
+
+/// The key a request was authenticated with, attached by [`require_api_key`].
+#[derive(Clone)]
+pub struct ApiKey(String);
+
+impl ApiKeys {
+    pub fn from_env() -> Self {
+        let keys = std::env::var("API_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self(Arc::new(keys))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn authenticate(&self, req: &Request) -> Option<ApiKey> {
+        let key = req
+            .header("authorization")
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .or_else(|| req.header("x-api-key"))?
+            .trim();
+        self.0.contains(key).then(|| ApiKey(key.to_string()))
+    }
+}
+
+/// `around` middleware that rejects requests without a valid API key.
+pub async fn require_api_key<E: Endpoint>(ep: Arc<E>, mut req: Request) -> Result<E::Output> {
+    let key = req
+        .data::<ApiKeys>()
+        .and_then(|keys| keys.authenticate(&req));
+    let Some(key) = key else {
+        return Err(Error::from_status(StatusCode::UNAUTHORIZED));
+    };
+    req.set_data(key);
+    ep.call(req).await
+}
+
+/// Open pool event streams (SSE and WebSocket) per API key, capped at
+/// `API_MAX_STREAMS_PER_KEY` (default 10). Every stream holds its own Redis connection.
+#[derive(Clone, Default)]
+pub struct StreamQuotas {
+    max_streams: usize,
+    open: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl StreamQuotas {
+    pub fn new(max_streams: usize) -> Self {
+        Self {
+            max_streams,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_env() -> std::io::Result<Self> {
+        let max_streams = match std::env::var("API_MAX_STREAMS_PER_KEY") {
+            Ok(v) => v
+                .parse()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+            Err(_) => DEFAULT_MAX_STREAMS_PER_KEY,
+        };
+        Ok(Self::new(max_streams))
+    }
+
+    pub fn max_streams(&self) -> usize {
+        self.max_streams
+    }
+
+    /// Reserve a stream slot for `key`, or `None` once it has `max_streams` open. The
+    /// returned permit releases the slot on drop.
+    pub fn acquire(&self, key: &ApiKey) -> Option<StreamPermit> {
+        let mut open = self.open.lock().unwrap();
+        let count = open.entry(key.0.clone()).or_default();
+        if *count >= self.max_streams {
+            return None;
+        }
+        *count += 1;
+        Some(StreamPermit {
+            quotas: self.clone(),
+            key: key.0.clone(),
+        })
+    }
+
+    fn release(&self, key: &str) {
+        let mut open = self.open.lock().unwrap();
+        if let Some(count) = open.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(key);
+            }
+        }
+    }
+}
+
+/// Held for as long as a pool event stream is open.
+pub struct StreamPermit {
+    quotas: StreamQuotas,
+    key: String,
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        self.quotas.release(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_slots_are_per_key_and_released_on_drop() {
+        let quotas = StreamQuotas::new(1);
+        let a = ApiKey("a".to_string());
+        let b = ApiKey("b".to_string());
+
+        let permit = quotas.acquire(&a).unwrap();
+        assert!(quotas.acquire(&a).is_none());
+        let _other = quotas.acquire(&b).unwrap();
+
+        drop(permit);
+        assert!(quotas.acquire(&a).is_some());
+        assert_eq!(quotas.open.lock().unwrap().get("a"), None);
+    }
+}
@@ -1,16 +1,60 @@
-use poem::{get, listener::TcpListener, middleware::Tracing, EndpointExt, Route, Server};
+use poem::{get, listener::TcpListener, middleware::Tracing, patch, EndpointExt, Route, Server};
 
+mod auth;
 mod routes;
-use routes::{get_accounts, get_slots, get_transactions};
+use auth::{require_api_key, ApiKeys, StreamQuotas};
+use routes::{
+    create_webhook, delete_webhook, get_accounts, get_slots, get_transactions,
+    get_webhook_deliveries, get_webhooks, pool_events_sse, pool_events_ws, update_webhook,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
+    let redis_url =
+        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    let redis_client = redis::Client::open(redis_url)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let api_keys = ApiKeys::from_env();
+    if api_keys.is_empty() {
+        eprintln!("API_KEYS is not set; webhook and pool event routes will reject every request");
+    }
+    let stream_quotas = StreamQuotas::from_env()?;
+
     let api_v1 = Route::new()
         .at("/transactions", get(get_transactions))
         .at("/slots", get(get_slots))
-        .at("/accounts", get(get_accounts));
+        .at("/accounts", get(get_accounts))
+        .at(
+            "/pools/:pool_id/events",
+            get(pool_events_sse).around(require_api_key),
+        )
+        .at(
+            "/pools/:pool_id/ws",
+            get(pool_events_ws).around(require_api_key),
+        )
+        .at(
+            "/webhooks",
+            get(get_webhooks)
+                .post(create_webhook)
+                .around(require_api_key),
+        )
+        .at(
+            "/webhooks/:id",
+            patch(update_webhook)
+                .delete(delete_webhook)
+                .around(require_api_key),
+        )
+        .at(
+            "/webhooks/:id/deliveries",
+            get(get_webhook_deliveries).around(require_api_key),
+        );
 
-    let app = Route::new().nest("/api/v1", api_v1).with(Tracing);
+    let app = Route::new()
+        .nest("/api/v1", api_v1)
+        .with(Tracing)
+        .data(redis_client)
+        .data(api_keys)
+        .data(stream_quotas);
 
     Server::new(TcpListener::bind("0.0.0.0:3000"))
         .name("heimdall-api")
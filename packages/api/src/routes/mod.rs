@@ -1,7 +1,11 @@
 pub mod accounts;
+pub mod pool_events;
 pub mod slots;
 pub mod transactions;
+pub mod webhooks;
 
 pub use accounts::*;
+pub use pool_events::*;
 pub use slots::*;
 pub use transactions::*;
+pub use webhooks::*;
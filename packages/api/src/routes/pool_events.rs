@@ -0,0 +1,244 @@
+use futures_util::{stream, SinkExt, Stream, StreamExt};
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{
+        sse::{Event, SSE},
+        websocket::{Message, WebSocket},
+        Data, Path, Query,
+    },
+    IntoResponse, Request,
+};
+use redis::{
+    aio::MultiplexedConnection,
+    streams::{StreamReadOptions, StreamReadReply},
+    AsyncCommands,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::auth::{ApiKey, StreamQuotas};
+
+/// Redis stream laser-ingest publishes every pool event to.
+const POOL_EVENTS_STREAM: &str = "heimdall:pool_events";
+const READ_BLOCK_MS: usize = 5000;
+const READ_COUNT: usize = 100;
+
+/// Query parameters shared by the SSE and WebSocket endpoints.
+#[derive(Debug, Deserialize)]
+pub struct PoolEventsParams {
+    /// Resume after this Redis stream id. SSE clients normally send `Last-Event-ID`
+    /// instead; the header wins when both are present.
+    pub last_event_id: Option<String>,
+}
+
+/// A pool event as delivered to HTTP clients. Mirrors the gRPC `PoolUpdate` message,
+/// plus the stream id clients hand back to resume.
+#[derive(Debug, Serialize)]
+pub struct PoolEventMessage {
+    pub id: String,
+    /// Deterministic event id, the same for every copy of an event; `None` for events
+    /// published before ids were assigned.
+    pub event_id: Option<String>,
+    pub pool_id: String,
+    pub event_type: String,
+    pub payload: Value,
+}
+
+/// `GET /api/v1/pools/:pool_id/events` – Server-Sent Events feed for one pool.
+#[handler]
+pub async fn pool_events_sse(
+    req: &Request,
+    Path(pool_id): Path<String>,
+    Query(params): Query<PoolEventsParams>,
+    redis_client: Data<&redis::Client>,
+    key: Data<&ApiKey>,
+    quotas: Data<&StreamQuotas>,
+) -> poem::Result<SSE> {
+    let last_event_id = req
+        .header("Last-Event-ID")
+        .map(str::to_owned)
+        .or(params.last_event_id);
+    if last_event_id.as_deref().is_some_and(|id| !is_stream_id(id)) {
+        return Err(invalid_event_id());
+    }
+    let Some(permit) = quotas.acquire(&key) else {
+        return Err(stream_limit_reached(quotas.max_streams()));
+    };
+    let conn = connect(&redis_client).await?;
+
+    let events = pool_event_stream(conn, pool_id, last_event_id).map(move |msg| {
+        // Holds the key's stream slot until the response is dropped.
+        let _permit = &permit;
+        let data = serde_json::to_string(&msg).unwrap_or_default();
+        Event::message(data).event_type(msg.event_type).id(msg.id)
+    });
+
+    Ok(SSE::new(events).keep_alive(Duration::from_secs(15)))
+}
+
+/// `GET /api/v1/pools/:pool_id/ws` – WebSocket feed for one pool. Each text frame is a
+/// JSON encoded [`PoolEventMessage`].
+#[handler]
+pub async fn pool_events_ws(
+    Path(pool_id): Path<String>,
+    Query(params): Query<PoolEventsParams>,
+    ws: WebSocket,
+    redis_client: Data<&redis::Client>,
+    key: Data<&ApiKey>,
+    quotas: Data<&StreamQuotas>,
+) -> poem::Result<impl IntoResponse> {
+    if params
+        .last_event_id
+        .as_deref()
+        .is_some_and(|id| !is_stream_id(id))
+    {
+        return Err(invalid_event_id());
+    }
+    let Some(permit) = quotas.acquire(&key) else {
+        return Err(stream_limit_reached(quotas.max_streams()));
+    };
+    let conn = connect(&redis_client).await?;
+
+    Ok(ws.on_upgrade(move |socket| async move {
+        let _permit = permit;
+        let (mut sink, mut incoming) = socket.split();
+        let events = pool_event_stream(conn, pool_id, params.last_event_id);
+        futures_util::pin_mut!(events);
+
+        loop {
+            tokio::select! {
+                msg = events.next() => {
+                    let Some(msg) = msg else {
+                        // Reading the stream failed; close so the client reconnects.
+                        let _ = sink.send(Message::close()).await;
+                        break;
+                    };
+                    let text = serde_json::to_string(&msg).unwrap_or_default();
+                    if sink.send(Message::text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                frame = incoming.next() => {
+                    // We only listen for the client going away; anything else is ignored.
+                    match frame {
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }))
+}
+
+async fn connect(client: &redis::Client) -> poem::Result<MultiplexedConnection> {
+    client
+        .get_multiplexed_async_connection()
+        .await
+        .map_err(|e| {
+            eprintln!("Error connecting to Redis: {}", e);
+            poem::Error::from_status(poem::http::StatusCode::SERVICE_UNAVAILABLE)
+        })
+}
+
+/// Whether `id` is a Redis stream id (`<ms>-<seq>`). XREAD fails on anything else, so a
+/// bad resume id is rejected up front.
+fn is_stream_id(id: &str) -> bool {
+    id.split_once('-')
+        .is_some_and(|(ms, seq)| ms.parse::<u64>().is_ok() && seq.parse::<u64>().is_ok())
+}
+
+fn invalid_event_id() -> poem::Error {
+    poem::Error::from_string(
+        "last event id must be a stream id (<ms>-<seq>)",
+        StatusCode::BAD_REQUEST,
+    )
+}
+
+fn stream_limit_reached(limit: usize) -> poem::Error {
+    poem::Error::from_string(
+        format!("open stream limit of {} reached for this API key", limit),
+        StatusCode::TOO_MANY_REQUESTS,
+    )
+}
+
+/// Tail `heimdall:pool_events` for a single pool.
+///
+/// Only events whose `pool_id` matches are delivered. A new subscriber starts at the tip of
+/// the stream unless it resumes after a previously seen stream id. Every subscriber reads
+/// the stream on its own, unlike the gRPC `StreamPoolUpdates` call, which shares a consumer
+/// group and can't resume. The stream ends if reading from Redis fails.
+pub fn pool_event_stream(
+    conn: MultiplexedConnection,
+    pool_id: String,
+    last_event_id: Option<String>,
+) -> impl Stream<Item = PoolEventMessage> + Send + 'static {
+    let cursor = last_event_id.unwrap_or_else(|| "$".to_string());
+
+    stream::unfold((conn, cursor), move |(mut conn, mut cursor)| {
+        let pool_id = pool_id.clone();
+        async move {
+            loop {
+                let opts = StreamReadOptions::default()
+                    .count(READ_COUNT)
+                    .block(READ_BLOCK_MS);
+                let reply: StreamReadReply = match conn
+                    .xread_options(&[POOL_EVENTS_STREAM], &[cursor.as_str()], &opts)
+                    .await
+                {
+                    Ok(reply) => reply,
+                    Err(e) => {
+                        eprintln!("Error reading pool events: {}", e);
+                        return None;
+                    }
+                };
+
+                let mut batch = Vec::new();
+                for key in reply.keys {
+                    for entry in key.ids {
+                        cursor = entry.id.clone();
+                        if let Some(msg) = decode_entry(&entry.id, &entry.map, &pool_id) {
+                            batch.push(msg);
+                        }
+                    }
+                }
+
+                if !batch.is_empty() {
+                    return Some((stream::iter(batch), (conn, cursor)));
+                }
+            }
+        }
+    })
+    .flatten()
+}
+
+fn decode_entry(
+    id: &str,
+    fields: &std::collections::HashMap<String, redis::Value>,
+    pool_id_filter: &str,
+) -> Option<PoolEventMessage> {
+    let redis::Value::Data(data) = fields.get("data")? else {
+        return None;
+    };
+    let json: Value = serde_json::from_slice(data).ok()?;
+    let pool_id = json.get("pool_id")?.as_str()?;
+    if pool_id != pool_id_filter {
+        return None;
+    }
+
+    Some(PoolEventMessage {
+        id: id.to_string(),
+        event_id: json
+            .get("event_id")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        pool_id: pool_id.to_string(),
+        event_type: json
+            .get("event_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        payload: json.get("payload").cloned().unwrap_or(Value::Null),
+    })
+}
@@ -0,0 +1,152 @@
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Json, Path, Query},
+    Error, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use store::{
+    models::{is_public_address, Webhook, WebhookDelivery},
+    Store,
+};
+use url::{Host, Url};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// Only deliver events for this pool. Omit to receive every pool.
+    pub pool_id: Option<String>,
+    /// Only deliver these event types. Omit or leave empty to receive every type.
+    #[serde(default)]
+    pub event_types: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    /// Signing secret for `X-Heimdall-Signature`. Shown only once.
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhookRequest {
+    pub active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveriesParams {
+    pub limit: Option<i64>,
+}
+
+#[handler]
+pub async fn get_webhooks() -> Result<Json<Vec<Webhook>>> {
+    let mut store = Store::default();
+
+    match store.get_webhooks() {
+        Ok(webhooks) => Ok(Json(webhooks)),
+        Err(e) => {
+            eprintln!("Error fetching webhooks: {}", e);
+            Ok(Json(vec![]))
+        }
+    }
+}
+
+#[handler]
+pub async fn create_webhook(Json(req): Json<CreateWebhookRequest>) -> Result<Json<CreatedWebhook>> {
+    check_url(&req.url).await?;
+
+    let secret = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let mut store = Store::default();
+
+    match store.create_webhook(req.url, secret.clone(), req.pool_id, req.event_types) {
+        Ok(webhook) => Ok(Json(CreatedWebhook { webhook, secret })),
+        Err(e) => {
+            eprintln!("Error creating webhook: {}", e);
+            Err(Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// Only public http(s) endpoints may be registered: the host must not be, or resolve to,
+/// a loopback, private or link-local address. The dispatcher checks again when it connects,
+/// in case DNS changes later. `WEBHOOK_ALLOW_PRIVATE_URLS=true` lifts this for local setups.
+async fn check_url(raw: &str) -> Result<()> {
+    let bad_request = |msg: &str| Error::from_string(msg.to_string(), StatusCode::BAD_REQUEST);
+    let url = Url::parse(raw).map_err(|_| bad_request("url is not a valid URL"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(bad_request("url must be an http(s) URL"));
+    }
+    if std::env::var("WEBHOOK_ALLOW_PRIVATE_URLS").is_ok_and(|v| v == "true") {
+        return Ok(());
+    }
+
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<IpAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![ip.into()],
+        Some(Host::Ipv6(ip)) => vec![ip.into()],
+        Some(Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|_| bad_request("url host does not resolve"))?
+            .map(|addr| addr.ip())
+            .collect(),
+        None => return Err(bad_request("url has no host")),
+    };
+    if addrs.is_empty() || !addrs.into_iter().all(is_public_address) {
+        return Err(bad_request("url must point to a public address"));
+    }
+    Ok(())
+}
+
+#[handler]
+pub async fn update_webhook(
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateWebhookRequest>,
+) -> Result<Json<Webhook>> {
+    let mut store = Store::default();
+
+    match store.set_webhook_active(id, req.active) {
+        Ok(webhook) => Ok(Json(webhook)),
+        Err(diesel::result::Error::NotFound) => Err(Error::from_status(StatusCode::NOT_FOUND)),
+        Err(e) => {
+            eprintln!("Error updating webhook {}: {}", id, e);
+            Err(Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[handler]
+pub async fn delete_webhook(Path(id): Path<i32>) -> Result<StatusCode> {
+    let mut store = Store::default();
+
+    match store.delete_webhook(id) {
+        Ok(0) => Err(Error::from_status(StatusCode::NOT_FOUND)),
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            eprintln!("Error deleting webhook {}: {}", id, e);
+            Err(Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[handler]
+pub async fn get_webhook_deliveries(
+    Path(id): Path<i32>,
+    Query(params): Query<DeliveriesParams>,
+) -> Result<Json<Vec<WebhookDelivery>>> {
+    let mut store = Store::default();
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+
+    match store.get_webhook_deliveries(id, limit) {
+        Ok(deliveries) => Ok(Json(deliveries)),
+        Err(e) => {
+            eprintln!("Error fetching deliveries for webhook {}: {}", id, e);
+            Ok(Json(vec![]))
+        }
+    }
+}
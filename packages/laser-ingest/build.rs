@@ -0,0 +1,276 @@
+//! Generates Borsh types and discriminator constants from the Anchor IDLs in `idls/`.
+//!
+//! Every `idls/<program>.json` becomes a `pub mod <program>` in `$OUT_DIR/idls.rs` with:
+//! * one struct/enum per IDL type (public keys as `[u8; 32]`),
+//! * `DISCRIMINATOR` associated constants on event and account types,
+//! * an `instructions` module holding one discriminator constant per instruction,
+//! * an `errors` module holding one constant per custom error code.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{env, error::Error, fmt::Write as _, fs, path::Path, path::PathBuf};
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "async", "await", "dyn",
+];
+
+fn main() -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-changed=idls");
+
+    let mut paths: Vec<PathBuf> = fs::read_dir("idls")?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut out = String::from("// @generated by build.rs from idls/*.json. Do not edit.\n\n");
+    for path in &paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+        out.push_str(&generate_program(path)?);
+    }
+
+    let out_path = PathBuf::from(env::var("OUT_DIR")?).join("idls.rs");
+    fs::write(out_path, out)?;
+    Ok(())
+}
+
+fn generate_program(path: &Path) -> Result<String, Box<dyn Error>> {
+    let idl: Value = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| format!("parsing {}: {}", path.display(), e))?;
+    let module = to_snake_case(path.file_stem().unwrap().to_str().unwrap());
+
+    let mut out = String::new();
+    writeln!(out, "#[allow(dead_code, clippy::all)]")?;
+    writeln!(out, "pub mod {} {{", module)?;
+    writeln!(out, "    use borsh::BorshDeserialize;\n")?;
+
+    let address = idl["address"]
+        .as_str()
+        .or_else(|| idl["metadata"]["address"].as_str());
+    if let Some(address) = address {
+        writeln!(out, "    pub const PROGRAM_ID: &str = \"{}\";\n", address)?;
+    }
+
+    let types = array(&idl["types"]);
+    let events = array(&idl["events"]);
+    let accounts = array(&idl["accounts"]);
+
+    for ty in types {
+        let name = ty["name"].as_str().ok_or("type without name")?;
+        write_docs(&mut out, &ty["docs"], "    ")?;
+        write_type_def(&mut out, name, &ty["type"])?;
+    }
+    // Legacy IDLs inline event fields and account layouts instead of listing them in `types`.
+    for evt in events {
+        if let Some(fields) = evt.get("fields") {
+            let def = serde_json::json!({ "kind": "struct", "fields": fields });
+            write_type_def(&mut out, evt["name"].as_str().unwrap(), &def)?;
+        }
+    }
+    for acc in accounts {
+        if let Some(def) = acc.get("type") {
+            write_type_def(&mut out, acc["name"].as_str().unwrap(), def)?;
+        }
+    }
+
+    for evt in events {
+        let name = evt["name"].as_str().ok_or("event without name")?;
+        let disc = discriminator(evt, "event", name);
+        writeln!(
+            out,
+            "    impl {} {{\n        pub const DISCRIMINATOR: [u8; 8] = {:?};\n    }}\n",
+            name, disc
+        )?;
+    }
+    for acc in accounts {
+        let name = acc["name"].as_str().ok_or("account without name")?;
+        let disc = discriminator(acc, "account", name);
+        writeln!(
+            out,
+            "    impl {} {{\n        pub const DISCRIMINATOR: [u8; 8] = {:?};\n    }}\n",
+            name, disc
+        )?;
+    }
+
+    writeln!(out, "    pub mod instructions {{")?;
+    for ix in array(&idl["instructions"]) {
+        let name = ix["name"].as_str().ok_or("instruction without name")?;
+        let disc = discriminator(ix, "global", &to_snake_case(name));
+        writeln!(
+            out,
+            "        pub const {}: [u8; 8] = {:?};",
+            to_snake_case(name).to_uppercase(),
+            disc
+        )?;
+    }
+    writeln!(out, "    }}\n")?;
+
+    writeln!(out, "    pub mod errors {{")?;
+    for err in array(&idl["errors"]) {
+        let name = err["name"].as_str().ok_or("error without name")?;
+        let code = err["code"].as_u64().ok_or("error without code")?;
+        writeln!(
+            out,
+            "        pub const {}: u32 = {};",
+            to_snake_case(name).to_uppercase(),
+            code
+        )?;
+    }
+    writeln!(out, "\n        /// Name of a custom error code, as declared in the IDL.")?;
+    writeln!(out, "        pub fn name(code: u32) -> Option<&'static str> {{")?;
+    writeln!(out, "            match code {{")?;
+    for err in array(&idl["errors"]) {
+        writeln!(
+            out,
+            "                {} => Some(\"{}\"),",
+            err["code"].as_u64().unwrap(),
+            err["name"].as_str().unwrap()
+        )?;
+    }
+    writeln!(out, "                _ => None,\n            }}\n        }}")?;
+    writeln!(out, "    }}")?;
+
+    writeln!(out, "}}\n")?;
+    Ok(out)
+}
+
+fn write_type_def(out: &mut String, name: &str, def: &Value) -> Result<(), Box<dyn Error>> {
+    match def["kind"].as_str() {
+        Some("struct") => {
+            writeln!(out, "    #[derive(BorshDeserialize, Debug, Clone)]")?;
+            writeln!(out, "    pub struct {} {{", name)?;
+            for field in array(&def["fields"]) {
+                write_docs(out, &field["docs"], "        ")?;
+                writeln!(
+                    out,
+                    "        pub {}: {},",
+                    field_name(field["name"].as_str().ok_or("field without name")?),
+                    rust_type(&field["type"])?
+                )?;
+            }
+            writeln!(out, "    }}\n")?;
+        }
+        Some("enum") => {
+            writeln!(out, "    #[derive(BorshDeserialize, Debug, Clone)]")?;
+            writeln!(out, "    pub enum {} {{", name)?;
+            for variant in array(&def["variants"]) {
+                let vname = variant["name"].as_str().ok_or("variant without name")?;
+                match variant["fields"].as_array() {
+                    None => writeln!(out, "        {},", vname)?,
+                    Some(fields) if fields.iter().all(|f| f.get("name").is_some()) => {
+                        writeln!(out, "        {} {{", vname)?;
+                        for f in fields {
+                            writeln!(
+                                out,
+                                "            {}: {},",
+                                field_name(f["name"].as_str().unwrap()),
+                                rust_type(&f["type"])?
+                            )?;
+                        }
+                        writeln!(out, "        }},")?;
+                    }
+                    Some(fields) => {
+                        let tys = fields
+                            .iter()
+                            .map(rust_type)
+                            .collect::<Result<Vec<_>, _>>()?;
+                        writeln!(out, "        {}({}),", vname, tys.join(", "))?;
+                    }
+                }
+            }
+            writeln!(out, "    }}\n")?;
+        }
+        Some("type") => {
+            writeln!(out, "    pub type {} = {};\n", name, rust_type(&def["alias"])?)?;
+        }
+        other => return Err(format!("unsupported type kind {:?} for {}", other, name).into()),
+    }
+    Ok(())
+}
+
+fn rust_type(ty: &Value) -> Result<String, Box<dyn Error>> {
+    if let Some(prim) = ty.as_str() {
+        return Ok(match prim {
+            "bool" | "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "f32" | "u64" | "i64"
+            | "f64" | "u128" | "i128" => prim.to_string(),
+            "pubkey" | "publicKey" => "[u8; 32]".to_string(),
+            "string" => "String".to_string(),
+            "bytes" => "Vec<u8>".to_string(),
+            other => return Err(format!("unsupported IDL type `{}`", other).into()),
+        });
+    }
+    if let Some(inner) = ty.get("option") {
+        return Ok(format!("Option<{}>", rust_type(inner)?));
+    }
+    if let Some(inner) = ty.get("vec") {
+        return Ok(format!("Vec<{}>", rust_type(inner)?));
+    }
+    if let Some(arr) = ty.get("array").and_then(Value::as_array) {
+        let len = arr.get(1).and_then(Value::as_u64).ok_or("array without length")?;
+        return Ok(format!("[{}; {}]", rust_type(&arr[0])?, len));
+    }
+    if let Some(defined) = ty.get("defined") {
+        let name = defined
+            .as_str()
+            .or_else(|| defined["name"].as_str())
+            .ok_or("defined type without name")?;
+        return Ok(name.to_string());
+    }
+    Err(format!("unsupported IDL type {}", ty).into())
+}
+
+fn write_docs(out: &mut String, docs: &Value, indent: &str) -> std::fmt::Result {
+    for line in array(docs) {
+        if let Some(line) = line.as_str() {
+            writeln!(out, "{}/// {}", indent, line)?;
+        }
+    }
+    Ok(())
+}
+
+fn discriminator(item: &Value, namespace: &str, name: &str) -> [u8; 8] {
+    if let Some(bytes) = item["discriminator"].as_array() {
+        let mut out = [0u8; 8];
+        for (o, b) in out.iter_mut().zip(bytes) {
+            *o = b.as_u64().unwrap_or_default() as u8;
+        }
+        return out;
+    }
+    let digest = Sha256::digest(format!("{}:{}", namespace, name).as_bytes());
+    let mut out = [0u8; 8];
+    out.copy_from_slice(&digest[..8]);
+    out
+}
+
+fn array(v: &Value) -> &[Value] {
+    v.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn field_name(name: &str) -> String {
+    let snake = to_snake_case(name);
+    if RUST_KEYWORDS.contains(&snake.as_str()) {
+        format!("r#{}", snake)
+    } else {
+        snake
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, ch) in name.chars().enumerate() {
+        if ch.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.push(ch.to_ascii_lowercase());
+        } else if ch == '-' {
+            out.push('_');
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
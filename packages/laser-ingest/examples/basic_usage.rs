@@ -13,8 +13,11 @@ use borsh::{BorshDeserialize};
 use bs58;
 
 // Import the IDL-derived structs and discriminator
-mod idl;
-use idl::{EvtSwap, SwapResult, SWAP_EVENT_DISCRIMINATOR};
+// Types generated by build.rs from idls/dynamic_bonding_curve.json
+mod idls {
+    include!(concat!(env!("OUT_DIR"), "/idls.rs"));
+}
+use idls::dynamic_bonding_curve::{EvtSwap, SwapResult};
 
 const ANCHOR_CPI_LOG_DISCRIMINATOR: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29]; // e445a52e51cb9a1d
 
@@ -41,7 +44,15 @@ fn find_swap_event_in_tx(
     tx: &Transaction,
     meta: &TransactionStatusMeta,
 ) -> Option<SwapResult> {
-    let account_keys = tx.message.as_ref()?.account_keys.as_slice();
+    // v0 transactions can reference the program through an address lookup table.
+    let account_keys: Vec<&Vec<u8>> = tx
+        .message
+        .as_ref()?
+        .account_keys
+        .iter()
+        .chain(meta.loaded_writable_addresses.iter())
+        .chain(meta.loaded_readonly_addresses.iter())
+        .collect();
     let inner_instructions = meta.inner_instructions.as_slice();
     let dbc_program_bytes = bs58::decode(DBC_PROGRAM_ID).into_vec().ok()?;
 
@@ -50,12 +61,12 @@ fn find_swap_event_in_tx(
             let program_id_index = ix.program_id_index as usize;
 
             if let Some(program_id_bytes) = account_keys.get(program_id_index) {
-                if *program_id_bytes == dbc_program_bytes {
+                if **program_id_bytes == dbc_program_bytes {
                     // This is a CPI to our program. Check for Anchor's self-CPI log signature.
                     if ix.data.starts_with(&ANCHOR_CPI_LOG_DISCRIMINATOR) {
                         let event_with_disc = &ix.data[8..];
                         // Now check for our EvtSwap discriminator inside the CPI data.
-                        if event_with_disc.starts_with(&SWAP_EVENT_DISCRIMINATOR) {
+                        if event_with_disc.starts_with(&EvtSwap::DISCRIMINATOR) {
                             let event_data = &event_with_disc[8..];
                             if let Ok(evt) = EvtSwap::try_from_slice(event_data) {
                                 return Some(evt.swap_result);
@@ -0,0 +1,414 @@
+//! Gap backfill after ingest outages.
+//!
+//! The last processed slot and signature of every pool are tracked in memory and flushed to
+//! the `heimdall:ingest:cursors` Redis hash every second, off the dispatch path. A crash
+//! loses at most that last second, which only widens the next backfill. When the worker (re)connects, the first live update
+//! marks where the stream picked up again; each pool's transactions between its cursor and
+//! that slot are fetched over RPC and run through the pool's processor. Their events are
+//! published with `backfilled: true`.
+//!
+//! * `BACKFILL_RPC_URL` – RPC endpoint; gap backfill is off when unset.
+//! * `BACKFILL_MAX_SIGNATURES` – cap on transactions fetched per pool and gap (default 5000).
+
+use anyhow::{Context, Result};
+use helius_laserstream::grpc::SubscribeUpdate;
+use redis::aio::MultiplexedConnection as RedisConn;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::dispatch::Publisher;
+use crate::processors::Processor;
+use crate::rpc::{RpcClient, SignatureInfo, MAX_SIGNATURES_PAGE};
+use crate::tokens::TokenCache;
+use crate::types::{PoolMeta, PoolVariant};
+
+/// Filter name carried by updates replayed from RPC rather than streamed.
+pub const BACKFILL_FILTER: &str = "backfill";
+const CURSORS_KEY: &str = "heimdall:ingest:cursors";
+/// How often advanced cursors are written to Redis.
+pub const CURSOR_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Whether `update` was replayed by a backfill.
+pub fn is_backfill(update: &SubscribeUpdate) -> bool {
+    update.filters.iter().any(|f| f == BACKFILL_FILTER)
+}
+
+/// Last transaction processed for a pool.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub slot: u64,
+    pub signature: String,
+}
+
+/// Per-pool cursors, mirrored to Redis by [`Cursors::flush`] so they survive restarts.
+#[derive(Clone, Debug, Default)]
+pub struct Cursors {
+    inner: Arc<Mutex<CursorState>>,
+}
+
+#[derive(Debug, Default)]
+struct CursorState {
+    cursors: HashMap<String, Cursor>,
+    /// Pools whose cursor advanced since the last flush.
+    dirty: HashSet<String>,
+}
+
+impl Cursors {
+    pub async fn load(conn: &mut RedisConn) -> Result<Self> {
+        let raw: HashMap<String, String> = conn.hgetall(CURSORS_KEY).await?;
+        let cursors = raw
+            .into_iter()
+            .filter_map(|(pool, json)| Some((pool, serde_json::from_str(&json).ok()?)))
+            .collect();
+        Ok(Self {
+            inner: Arc::new(Mutex::new(CursorState {
+                cursors,
+                dirty: HashSet::new(),
+            })),
+        })
+    }
+
+    /// Advance `pool_id`'s cursor in memory; older slots are ignored.
+    pub fn record(&self, pool_id: &str, slot: u64, signature: String) {
+        let mut state = self.inner.lock().unwrap();
+        if state.cursors.get(pool_id).is_some_and(|c| c.slot > slot) {
+            return;
+        }
+        state
+            .cursors
+            .insert(pool_id.to_owned(), Cursor { slot, signature });
+        state.dirty.insert(pool_id.to_owned());
+    }
+
+    /// Write the cursors that advanced since the last flush in one `HSET`. On failure
+    /// they stay dirty and go out with the next flush.
+    pub async fn flush(&self, conn: &mut RedisConn) {
+        let (pools, fields): (Vec<String>, Vec<(String, String)>) = {
+            let mut state = self.inner.lock().unwrap();
+            let pools: Vec<String> = state.dirty.drain().collect();
+            let fields = pools
+                .iter()
+                .map(|pool| {
+                    let json = serde_json::to_string(&state.cursors[pool]).unwrap();
+                    (pool.clone(), json)
+                })
+                .collect();
+            (pools, fields)
+        };
+        if fields.is_empty() {
+            return;
+        }
+        if let Err(e) = conn
+            .hset_multiple::<_, _, _, ()>(CURSORS_KEY, &fields)
+            .await
+        {
+            tracing::warn!(error = %e, pools = pools.len(), "Failed to persist ingest cursors");
+            self.inner.lock().unwrap().dirty.extend(pools);
+        }
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, Cursor> {
+        self.inner.lock().unwrap().cursors.clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackfillConfig {
+    pub rpc_url: String,
+    pub max_signatures: usize,
+}
+
+impl BackfillConfig {
+    /// `None` when `BACKFILL_RPC_URL` is unset.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(rpc_url) = std::env::var("BACKFILL_RPC_URL") else {
+            return Ok(None);
+        };
+        let max_signatures = match std::env::var("BACKFILL_MAX_SIGNATURES") {
+            Ok(v) => v.parse().context("BACKFILL_MAX_SIGNATURES")?,
+            Err(_) => 5000,
+        };
+        Ok(Some(Self {
+            rpc_url,
+            max_signatures,
+        }))
+    }
+}
+
+/// Which of an address's signatures to walk, going back from the newest.
+#[derive(Debug, Clone, Default)]
+pub struct SignatureRange {
+    /// Stop at this signature (exclusive).
+    pub until: Option<String>,
+    /// Oldest slot to include.
+    pub from_slot: u64,
+    /// Oldest block time (unix seconds) to include.
+    pub since: Option<i64>,
+    /// Only include slots strictly below this one.
+    pub before_slot: Option<u64>,
+}
+
+/// Signatures of `address` within `range`, oldest first, at most `max_signatures` of them
+/// (the newest ones when there are more).
+pub async fn signatures(
+    rpc: &RpcClient,
+    address: &str,
+    range: &SignatureRange,
+    max_signatures: usize,
+) -> Result<Vec<SignatureInfo>> {
+    let in_range = |s: &SignatureInfo| {
+        s.slot >= range.from_slot
+            && range.since.is_none_or(|since| s.block_time.is_none_or(|t| t >= since))
+    };
+    let mut signatures = Vec::new();
+    let mut before: Option<String> = None;
+    while signatures.len() < max_signatures {
+        let page = rpc
+            .signatures_for_address(
+                address,
+                before.as_deref(),
+                range.until.as_deref(),
+                (max_signatures - signatures.len()).min(MAX_SIGNATURES_PAGE),
+            )
+            .await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(last.signature.clone());
+        let reached_start = !in_range(last);
+        signatures.extend(
+            page.into_iter()
+                .filter(|s| in_range(s) && range.before_slot.is_none_or(|b| s.slot < b)),
+        );
+        if reached_start {
+            break;
+        }
+    }
+    if signatures.len() >= max_signatures {
+        tracing::warn!(address, max_signatures, "Signature range truncated to the newest");
+    }
+    signatures.reverse();
+    Ok(signatures)
+}
+
+/// Fetch a transaction over RPC as a backfilled update.
+pub async fn fetch_transaction(rpc: &RpcClient, signature: &str) -> Result<Option<SubscribeUpdate>> {
+    let update = rpc.transaction(signature).await?;
+    if update.is_none() {
+        tracing::warn!(signature, "Transaction not found, skipping");
+    }
+    Ok(update.map(|mut update| {
+        update.filters = vec![BACKFILL_FILTER.to_owned()];
+        update
+    }))
+}
+
+/// Transactions of `address` after `cursor` and before `before_slot`, oldest first, marked
+/// as backfilled.
+pub async fn fetch_gap(
+    rpc: &RpcClient,
+    address: &str,
+    cursor: &Cursor,
+    before_slot: u64,
+    max_signatures: usize,
+) -> Result<Vec<SubscribeUpdate>> {
+    let range = SignatureRange {
+        until: Some(cursor.signature.clone()),
+        from_slot: cursor.slot,
+        since: None,
+        before_slot: Some(before_slot),
+    };
+    let mut updates = Vec::new();
+    for sig in signatures(rpc, address, &range, max_signatures).await? {
+        updates.extend(fetch_transaction(rpc, &sig.signature).await?);
+    }
+    Ok(updates)
+}
+
+/// Backfill every pool with a cursor up to `before_slot`, the first slot the live stream
+/// delivered after reconnecting.
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    rpc: RpcClient,
+    max_signatures: usize,
+    pools: Vec<PoolMeta>,
+    cursors: HashMap<String, Cursor>,
+    before_slot: u64,
+    processors: Arc<HashMap<PoolVariant, Box<dyn Processor>>>,
+    tokens: TokenCache,
+    publisher: Publisher,
+) {
+    for pool in &pools {
+        let (Some(cursor), Some(proc)) = (cursors.get(&pool.pool_id), processors.get(&pool.variant))
+        else {
+            continue;
+        };
+        if cursor.slot >= before_slot {
+            continue;
+        }
+        let updates = match fetch_gap(&rpc, &pool.pool_id, cursor, before_slot, max_signatures).await {
+            Ok(updates) => updates,
+            Err(e) => {
+                tracing::error!(error = %e, pool_id = %pool.pool_id, "Gap backfill failed");
+                continue;
+            }
+        };
+        if !updates.is_empty() {
+            tracing::info!(
+                pool_id = %pool.pool_id,
+                from_slot = cursor.slot,
+                before_slot,
+                transactions = updates.len(),
+                "Backfilling gap"
+            );
+        }
+        for update in &updates {
+            tokens.observe(update);
+            publisher.send(proc.handle_update(pool, update)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx_error::TxError;
+    use helius_laserstream::grpc::subscribe_update::UpdateOneof;
+    use poem::{handler, listener::TcpAcceptor, post, web::Json, Route, Server};
+    use serde_json::{json, Value};
+
+    fn sig(n: u8) -> String {
+        bs58::encode([n; 64]).into_string()
+    }
+
+    fn key(n: u8) -> String {
+        bs58::encode([n; 32]).into_string()
+    }
+
+    /// Five transactions of one address in slots 100..=104, newest first. The mock serves
+    /// two signatures per page, honouring `before`/`until` like a real node.
+    #[handler]
+    fn mock_rpc(Json(req): Json<Value>) -> Json<Value> {
+        let params = &req["params"];
+        let result = match req["method"].as_str().unwrap() {
+            "getSignaturesForAddress" => {
+                let config = &params[1];
+                let all: Vec<u8> = (0..5).rev().collect();
+                let start = match config["before"].as_str() {
+                    Some(before) => all.iter().position(|n| sig(*n) == before).unwrap() + 1,
+                    None => 0,
+                };
+                let page: Vec<Value> = all[start..]
+                    .iter()
+                    .take_while(|n| config["until"].as_str() != Some(sig(**n).as_str()))
+                    .take(2)
+                    .map(|n| json!({ "signature": sig(*n), "slot": 100 + *n as u64, "err": null }))
+                    .collect();
+                json!(page)
+            }
+            "getTransaction" => {
+                let n = (0..5).find(|n| sig(*n) == params[0].as_str().unwrap()).unwrap();
+                let err = if n == 3 {
+                    json!({ "InstructionError": [1, { "Custom": 6002 }] })
+                } else {
+                    Value::Null
+                };
+                json!({
+                    "slot": 100 + n as u64,
+                    "version": 0,
+                    "transaction": {
+                        "signatures": [sig(n)],
+                        "message": {
+                            "header": {
+                                "numRequiredSignatures": 1,
+                                "numReadonlySignedAccounts": 0,
+                                "numReadonlyUnsignedAccounts": 1
+                            },
+                            "accountKeys": [key(1), key(2)],
+                            "recentBlockhash": key(9),
+                            "instructions": [
+                                { "programIdIndex": 2, "accounts": [0, 1], "data": "3Bxs4h24hBtQy9rw" }
+                            ],
+                            "addressTableLookups": [
+                                { "accountKey": key(7), "writableIndexes": [], "readonlyIndexes": [0] }
+                            ]
+                        }
+                    },
+                    "meta": {
+                        "err": err,
+                        "fee": 5000,
+                        "preBalances": [10, 0, 1],
+                        "postBalances": [5, 0, 1],
+                        "innerInstructions": [],
+                        "logMessages": [],
+                        "preTokenBalances": [],
+                        "postTokenBalances": [],
+                        "loadedAddresses": { "writable": [], "readonly": [key(3)] }
+                    }
+                })
+            }
+            other => panic!("unexpected method {}", other),
+        };
+        Json(json!({ "jsonrpc": "2.0", "id": req["id"], "result": result }))
+    }
+
+    #[test]
+    fn record_keeps_the_newest_cursor_until_flushed() {
+        let cursors = Cursors::default();
+        cursors.record("pool", 10, sig(1));
+        cursors.record("pool", 9, sig(2));
+        cursors.record("other", 5, sig(3));
+
+        let newest = Cursor {
+            slot: 10,
+            signature: sig(1),
+        };
+        assert_eq!(cursors.snapshot()["pool"], newest);
+        let dirty = &cursors.inner.lock().unwrap().dirty;
+        assert_eq!(dirty, &HashSet::from(["pool".to_owned(), "other".to_owned()]));
+    }
+
+    #[tokio::test]
+    async fn fetch_gap_pages_between_cursor_and_live_slot() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = TcpAcceptor::from_tokio(listener).unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(Route::new().at("/", post(mock_rpc))));
+
+        let rpc = RpcClient::new(format!("http://{}", addr));
+        // Processed up to slot 101; the live stream resumed at slot 104.
+        let cursor = Cursor {
+            slot: 101,
+            signature: sig(1),
+        };
+        let updates = fetch_gap(&rpc, &key(5), &cursor, 104, 100).await.unwrap();
+
+        let txs: Vec<_> = updates
+            .iter()
+            .map(|u| match &u.update_oneof {
+                Some(UpdateOneof::Transaction(tx)) => tx,
+                other => panic!("expected transaction, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(txs.iter().map(|tx| tx.slot).collect::<Vec<_>>(), vec![102, 103]);
+        assert!(updates.iter().all(is_backfill));
+
+        let info = txs[0].transaction.as_ref().unwrap();
+        assert_eq!(info.signature, bs58::decode(sig(2)).into_vec().unwrap());
+        let meta = info.meta.as_ref().unwrap();
+        assert!(meta.err.is_none());
+        assert_eq!(meta.loaded_readonly_addresses, vec![bs58::decode(key(3)).into_vec().unwrap()]);
+        let message = info.transaction.as_ref().unwrap().message.as_ref().unwrap();
+        assert!(message.versioned);
+        assert_eq!(message.instructions[0].program_id_index, 2);
+
+        let failed = txs[1].transaction.as_ref().unwrap().meta.as_ref().unwrap();
+        let err = TxError::decode(&failed.err.as_ref().unwrap().err).unwrap();
+        assert_eq!(err.kind, "InstructionError");
+        assert_eq!(err.instruction_index, Some(1));
+        assert_eq!(err.custom_code, Some(6002));
+    }
+}
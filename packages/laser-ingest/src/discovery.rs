@@ -0,0 +1,327 @@
+//! Automatic pool discovery.
+//!
+//! When enabled, laser-ingest also subscribes to the DBC, DAMM and AMM programs, watches
+//! for pool-creation instructions, derives the pool's config, creator and quote vault from
+//! the instruction accounts and registers the pool in the watchlist. The watchlist file is
+//! rewritten and the grown list is pushed to the ingest worker, which resubscribes.
+
+use anyhow::{Context, Result};
+use helius_laserstream::grpc::{
+    subscribe_update::UpdateOneof, SubscribeRequestFilterTransactions, SubscribeUpdate,
+};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+use crate::idl::{generated::dynamic_bonding_curve, instruction_discriminator};
+use crate::processors::account_keys;
+use crate::types::{PoolMeta, PoolVariant};
+use crate::watchlist;
+
+/// Meteora DAMM v2 (cp-amm).
+const DAMM_PROGRAM_ID: &str = "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG";
+/// Meteora Dynamic AMM (DAMM v1).
+const AMM_PROGRAM_ID: &str = "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB";
+
+/// A pool-creation instruction and the positions of the accounts we care about.
+struct CreationRule {
+    variant: PoolVariant,
+    program_id: &'static str,
+    instruction: &'static str,
+    pool: usize,
+    config: Option<usize>,
+    creator: Option<usize>,
+    quote_vault: Option<usize>,
+    base_mint: Option<usize>,
+    quote_mint: Option<usize>,
+}
+
+const CREATION_RULES: &[CreationRule] = &[
+    CreationRule {
+        variant: PoolVariant::Dbc,
+        program_id: dynamic_bonding_curve::PROGRAM_ID,
+        instruction: "initialize_virtual_pool_with_spl_token",
+        pool: 5,
+        config: Some(0),
+        creator: Some(2),
+        quote_vault: Some(7),
+        base_mint: Some(3),
+        quote_mint: Some(4),
+    },
+    CreationRule {
+        variant: PoolVariant::Dbc,
+        program_id: dynamic_bonding_curve::PROGRAM_ID,
+        instruction: "initialize_virtual_pool_with_token2022",
+        pool: 5,
+        config: Some(0),
+        creator: Some(2),
+        quote_vault: Some(7),
+        base_mint: Some(3),
+        quote_mint: Some(4),
+    },
+    CreationRule {
+        variant: PoolVariant::Damm,
+        program_id: DAMM_PROGRAM_ID,
+        instruction: "initialize_pool",
+        pool: 6,
+        config: Some(4),
+        creator: Some(0),
+        quote_vault: Some(11),
+        base_mint: None,
+        quote_mint: None,
+    },
+    CreationRule {
+        variant: PoolVariant::Damm,
+        program_id: DAMM_PROGRAM_ID,
+        instruction: "initialize_pool_with_dynamic_config",
+        pool: 7,
+        config: Some(5),
+        creator: Some(0),
+        quote_vault: Some(12),
+        base_mint: None,
+        quote_mint: None,
+    },
+    CreationRule {
+        variant: PoolVariant::Damm,
+        program_id: DAMM_PROGRAM_ID,
+        instruction: "initialize_customizable_pool",
+        pool: 5,
+        config: None,
+        creator: Some(0),
+        quote_vault: Some(10),
+        base_mint: None,
+        quote_mint: None,
+    },
+    CreationRule {
+        variant: PoolVariant::Amm,
+        program_id: AMM_PROGRAM_ID,
+        instruction: "initialize_permissionless_constant_product_pool_with_config",
+        pool: 0,
+        config: Some(1),
+        creator: Some(18),
+        quote_vault: None,
+        base_mint: None,
+        quote_mint: None,
+    },
+];
+
+/// Discovery settings.
+///
+/// * `DISCOVERY_ENABLED` – `true` to turn discovery on (default off).
+/// * `DISCOVERY_VARIANTS` – comma separated variants to discover (default `dbc,damm,amm`).
+/// * `DISCOVERY_CONFIGS` – only register pools created with one of these configs.
+/// * `DISCOVERY_CREATORS` – only register pools created by one of these wallets.
+/// * `DISCOVERY_FLUSH_SECS` – how often newly found pools are written out (default 5).
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    pub enabled: bool,
+    pub variants: Vec<PoolVariant>,
+    pub configs: HashSet<String>,
+    pub creators: HashSet<String>,
+    pub flush_interval: Duration,
+}
+
+impl DiscoveryConfig {
+    pub fn from_env() -> Result<Self> {
+        let enabled = std::env::var("DISCOVERY_ENABLED")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        let variants = match std::env::var("DISCOVERY_VARIANTS") {
+            Ok(raw) => list(&raw)
+                .map(|v| {
+                    serde_json::from_value(serde_json::Value::String(v.clone()))
+                        .with_context(|| format!("DISCOVERY_VARIANTS: unknown variant {}", v))
+                })
+                .collect::<Result<_>>()?,
+            Err(_) => vec![PoolVariant::Dbc, PoolVariant::Damm, PoolVariant::Amm],
+        };
+        let flush_secs = match std::env::var("DISCOVERY_FLUSH_SECS") {
+            Ok(v) => v.parse().context("DISCOVERY_FLUSH_SECS")?,
+            Err(_) => 5,
+        };
+        Ok(Self {
+            enabled,
+            variants,
+            configs: std::env::var("DISCOVERY_CONFIGS")
+                .map(|raw| list(&raw).collect())
+                .unwrap_or_default(),
+            creators: std::env::var("DISCOVERY_CREATORS")
+                .map(|raw| list(&raw).collect())
+                .unwrap_or_default(),
+            flush_interval: Duration::from_secs(flush_secs),
+        })
+    }
+}
+
+fn list(raw: &str) -> impl Iterator<Item = String> + '_ {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_owned)
+}
+
+/// Detects newly created pools in the update stream.
+pub struct Discovery {
+    config: DiscoveryConfig,
+    /// `(program id bytes, instruction discriminator)` -> rule
+    rules: Vec<(Vec<u8>, [u8; 8], &'static CreationRule)>,
+}
+
+impl Discovery {
+    pub fn new(config: DiscoveryConfig) -> Self {
+        let rules = CREATION_RULES
+            .iter()
+            .filter(|r| config.variants.contains(&r.variant))
+            .map(|r| {
+                (
+                    bs58::decode(r.program_id).into_vec().expect("valid program id"),
+                    instruction_discriminator(r.instruction),
+                    r,
+                )
+            })
+            .collect();
+        Self { config, rules }
+    }
+
+    /// Program-level transaction filters, one per discovered program.
+    pub fn filters(&self) -> HashMap<String, SubscribeRequestFilterTransactions> {
+        let mut programs: Vec<&str> = self.rules.iter().map(|(_, _, r)| r.program_id).collect();
+        programs.sort_unstable();
+        programs.dedup();
+        programs
+            .into_iter()
+            .map(|program_id| {
+                (
+                    format!("discovery_{}", program_id),
+                    SubscribeRequestFilterTransactions {
+                        account_include: vec![program_id.to_owned()],
+                        failed: Some(false),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Pools created by this update that pass the config/creator filters.
+    pub fn detect(&self, update: &SubscribeUpdate) -> Vec<PoolMeta> {
+        let mut found = Vec::new();
+        let Some(UpdateOneof::Transaction(tx_update)) = &update.update_oneof else {
+            return found;
+        };
+        let Some(tx_info) = &tx_update.transaction else {
+            return found;
+        };
+        let (Some(tx), Some(meta)) = (&tx_info.transaction, &tx_info.meta) else {
+            return found;
+        };
+        if meta.err.is_some() {
+            return found;
+        }
+        let Some(message) = &tx.message else {
+            return found;
+        };
+
+        let keys = account_keys(message, meta);
+
+        let top_level = message
+            .instructions
+            .iter()
+            .map(|ix| (ix.program_id_index, &ix.accounts, &ix.data));
+        let inner = meta.inner_instructions.iter().flat_map(|list| {
+            list.instructions
+                .iter()
+                .map(|ix| (ix.program_id_index, &ix.accounts, &ix.data))
+        });
+
+        for (program_idx, accounts, data) in top_level.chain(inner) {
+            let Some(program) = keys.get(program_idx as usize) else {
+                continue;
+            };
+            let Some((_, _, rule)) = self
+                .rules
+                .iter()
+                .find(|(id, disc, _)| *id == **program && data.starts_with(disc))
+            else {
+                continue;
+            };
+            let key = |i: usize| {
+                let idx = *accounts.get(i)? as usize;
+                keys.get(idx).map(|k| bs58::encode(k).into_string())
+            };
+            let Some(pool_id) = key(rule.pool) else {
+                continue;
+            };
+            let config = rule.config.and_then(key);
+            let creator = rule.creator.and_then(key);
+
+            if !self.config.configs.is_empty()
+                && !config.as_ref().is_some_and(|c| self.config.configs.contains(c))
+            {
+                continue;
+            }
+            if !self.config.creators.is_empty()
+                && !creator.as_ref().is_some_and(|c| self.config.creators.contains(c))
+            {
+                continue;
+            }
+
+            tracing::info!(pool_id, variant = ?rule.variant, ?config, ?creator, "Discovered new pool");
+            found.push(PoolMeta {
+                pool_id,
+                variant: rule.variant.clone(),
+                quote_vault: rule.quote_vault.and_then(key),
+                config_pda: config,
+                base_mint: rule.base_mint.and_then(key),
+                quote_mint: rule.quote_mint.and_then(key),
+                program_id: None,
+                idl_path: None,
+            });
+        }
+        found
+    }
+}
+
+/// Collects discovered pools, persists them to the watchlist file and publishes the grown
+/// watchlist. Pools are flushed in batches so a burst of launches causes one resubscribe.
+pub async fn run_registrar(
+    path: String,
+    mut found_rx: mpsc::Receiver<PoolMeta>,
+    pool_tx: watch::Sender<Vec<PoolMeta>>,
+    flush_interval: Duration,
+) {
+    let mut pending: Vec<PoolMeta> = Vec::new();
+    let mut ticker = tokio::time::interval(flush_interval);
+    loop {
+        tokio::select! {
+            maybe_pool = found_rx.recv() => match maybe_pool {
+                Some(pool) => {
+                    if !pending.iter().any(|p| p.pool_id == pool.pool_id) {
+                        pending.push(pool);
+                    }
+                }
+                None => break,
+            },
+            _ = ticker.tick() => {
+                if pending.is_empty() {
+                    continue;
+                }
+                let mut pools = pool_tx.borrow().clone();
+                let known: HashSet<String> = pools.iter().map(|p| p.pool_id.clone()).collect();
+                let new: Vec<PoolMeta> = pending
+                    .drain(..)
+                    .filter(|p| !known.contains(&p.pool_id))
+                    .collect();
+                if new.is_empty() {
+                    continue;
+                }
+                pools.extend(new.iter().cloned());
+                if let Err(e) = watchlist::save_to_json(&path, &pools) {
+                    tracing::error!(error = %e, path, "Failed to persist discovered pools");
+                }
+                tracing::info!(added = new.len(), total = pools.len(), "Registered discovered pools");
+                pool_tx.send_replace(pools);
+            }
+        }
+    }
+}
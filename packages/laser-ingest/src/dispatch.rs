@@ -0,0 +1,158 @@
+//! Routing of upstream updates to the processors of the watched pools they touch, and
+//! publishing of the events they return.
+//!
+//! Events are queued to a background task that publishes them to the `EventSink` in the
+//! order they were produced. Each batch holds whatever was queued while the previous one was
+//! in flight, up to `SINK_BATCH_SIZE` (default 500), so publishing keeps up under load
+//! without delaying events when it's quiet. A full queue blocks the dispatcher.
+
+use anyhow::{Context, Result};
+use helius_laserstream::grpc::{subscribe_update::UpdateOneof, SubscribeUpdate};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::processors::{self, events::StreamedEvent, Processor};
+use crate::sinks::{self, EventSink, SinkEvent};
+use crate::types::{PoolMeta, PoolVariant};
+
+/// Events queued for publishing before the dispatcher waits.
+const QUEUE_CAPACITY: usize = 10_000;
+/// Attempts at publishing a batch before it is dropped.
+const PUBLISH_ATTEMPTS: u32 = 5;
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Handle to the publishing task. Clones share the queue; the task publishes what is left
+/// and exits once every clone is dropped, returning how many events it had to drop.
+#[derive(Clone)]
+pub struct Publisher {
+    tx: mpsc::Sender<StreamedEvent>,
+}
+
+impl Publisher {
+    /// Publish to `stream` through the sink `EVENT_SINK` selects.
+    pub fn from_env(stream: &str) -> Result<(Self, JoinHandle<usize>)> {
+        let batch_size = match std::env::var("SINK_BATCH_SIZE") {
+            Ok(v) => v.parse().context("SINK_BATCH_SIZE")?,
+            Err(_) => 500,
+        };
+        let sink = sinks::from_env(stream)?;
+        tracing::info!(sink = sink.name(), stream, batch_size, "Publishing pool events");
+        Ok(Self::spawn(sink, batch_size))
+    }
+
+    pub fn spawn(sink: Box<dyn EventSink>, batch_size: usize) -> (Self, JoinHandle<usize>) {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        (Self { tx }, tokio::spawn(publish(sink, rx, batch_size.max(1))))
+    }
+
+    pub async fn send(&self, events: Vec<StreamedEvent>) {
+        for event in events {
+            if self.tx.send(event).await.is_err() {
+                tracing::error!("Event publisher stopped, dropping pool event");
+            }
+        }
+    }
+}
+
+async fn publish(
+    mut sink: Box<dyn EventSink>,
+    mut rx: mpsc::Receiver<StreamedEvent>,
+    batch_size: usize,
+) -> usize {
+    let mut dropped = 0;
+    let mut batch = Vec::with_capacity(batch_size);
+    while let Some(event) = rx.recv().await {
+        let mut next = Some(event);
+        while let Some(event) = next {
+            tracing::info!(event = ?event, "Publishing pool event");
+            match SinkEvent::try_from(&event) {
+                Ok(sink_event) => batch.push(sink_event),
+                Err(e) => {
+                    tracing::error!(error = %e, event = ?event, "Failed to serialize pool event, dropping it");
+                    dropped += 1;
+                }
+            }
+            next = if batch.len() < batch_size {
+                rx.try_recv().ok()
+            } else {
+                None
+            };
+        }
+
+        if batch.is_empty() {
+            continue;
+        }
+        let mut backoff = RETRY_BACKOFF;
+        for attempt in 1..=PUBLISH_ATTEMPTS {
+            match sink.publish(&batch).await {
+                Ok(()) => break,
+                Err(e) if attempt == PUBLISH_ATTEMPTS => {
+                    tracing::error!(error = %e, sink = sink.name(), events = batch.len(), "Publishing failed, dropping events");
+                    dropped += batch.len();
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, sink = sink.name(), ?backoff, "Publishing failed, retrying");
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+        batch.clear();
+    }
+    dropped
+}
+
+/// Run `update` through the processor of every watched pool it touches and queue the
+/// events, returning those pools. A transaction may touch several (aggregator routes,
+/// bundles); every one of them gets to process it.
+pub async fn dispatch<'a>(
+    update: &SubscribeUpdate,
+    pools: &'a [PoolMeta],
+    processors: &HashMap<PoolVariant, Box<dyn Processor>>,
+    publisher: &Publisher,
+) -> Vec<&'a PoolMeta> {
+    let matched = match_update_to_pools(update, pools);
+    for pool in &matched {
+        if let Some(proc) = processors.get(&pool.variant) {
+            publisher.send(proc.handle_update(pool, update)).await;
+        }
+    }
+    matched
+}
+
+fn match_update_to_pools<'a>(update: &SubscribeUpdate, pools: &'a [PoolMeta]) -> Vec<&'a PoolMeta> {
+    if let Some(oneof) = &update.update_oneof {
+        match oneof {
+            UpdateOneof::Account(acc_upd) => {
+                if let Some(acc) = &acc_upd.account {
+                    let key_str = bs58::encode(&acc.pubkey).into_string();
+                    pools.iter().filter(|p| {
+                        p.pool_id == key_str
+                            || p.quote_vault.as_ref() == Some(&key_str)
+                            || p.config_pda.as_ref() == Some(&key_str)
+                    }).collect()
+                } else { Vec::new() }
+            }
+            UpdateOneof::Transaction(tx_upd) => {
+                if let Some(tx_info) = &tx_upd.transaction {
+                    if let (Some(tx), Some(meta)) = (&tx_info.transaction, &tx_info.meta) {
+                        let Some(message) = &tx.message else { return Vec::new() };
+                        // Include lookup-table addresses: a v0 transaction may reference the
+                        // pool or program only through an ALT.
+                        let accounts: HashSet<_> = processors::account_keys(message, meta)
+                            .into_iter()
+                            .map(|k| bs58::encode(k).into_string())
+                            .collect();
+                        pools.iter().filter(|p| {
+                            accounts.contains(&p.pool_id)
+                                || p.program_id.as_ref().is_some_and(|id| accounts.contains(id))
+                        }).collect()
+                    } else { Vec::new() }
+                } else { Vec::new() }
+            }
+            _ => Vec::new(),
+        }
+    } else { Vec::new() }
+}
@@ -0,0 +1,143 @@
+//! Connection health of the LaserStream subscription, served over HTTP.
+//!
+//! * `HEALTH_ADDR` – listen address of the health endpoint (default `0.0.0.0:8081`).
+//! * `HEALTH_MAX_SILENCE_SECS` – `/health` reports unhealthy when no message arrived for
+//!   this long (default 60).
+//!
+//! `GET /health` returns the snapshot as JSON, with `200` when connected and receiving
+//! messages or on leader-election standby, and `503` otherwise.
+
+use anyhow::{Context, Result};
+use helius_laserstream::grpc::{subscribe_update::UpdateOneof, SubscribeUpdate};
+use poem::{
+    get, handler, http::StatusCode, listener::TcpListener, web::Data, EndpointExt, IntoResponse,
+    Response, Route, Server,
+};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    #[default]
+    Connecting,
+    Connected,
+    Reconnecting,
+    /// Waiting for the leader lease; not subscribed.
+    Standby,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct HealthSnapshot {
+    pub state: ConnectionState,
+    /// Unix time (ms) of the last update received.
+    pub last_message_at: Option<u64>,
+    /// Highest slot seen on any update.
+    pub last_slot: Option<u64>,
+    /// Stream errors and reconnects since start.
+    pub reconnects: u64,
+    pub last_error: Option<String>,
+}
+
+/// Shared, cheaply clonable health tracker.
+#[derive(Clone, Debug, Default)]
+pub struct Health {
+    inner: Arc<Mutex<HealthSnapshot>>,
+}
+
+impl Health {
+    pub fn connecting(&self) {
+        self.inner.lock().unwrap().state = ConnectionState::Connecting;
+    }
+
+    pub fn standby(&self) {
+        self.inner.lock().unwrap().state = ConnectionState::Standby;
+    }
+
+    /// Record a received update.
+    pub fn message(&self, update: &SubscribeUpdate) {
+        let mut h = self.inner.lock().unwrap();
+        h.state = ConnectionState::Connected;
+        h.last_message_at = Some(now_ms());
+        if let Some(slot) = update_slot(update) {
+            h.last_slot = Some(h.last_slot.map_or(slot, |s| s.max(slot)));
+        }
+    }
+
+    /// Record a stream error or end of stream; the subscription is being re-established.
+    pub fn reconnecting(&self, error: impl ToString) {
+        let mut h = self.inner.lock().unwrap();
+        h.state = ConnectionState::Reconnecting;
+        h.reconnects += 1;
+        h.last_error = Some(error.to_string());
+    }
+
+    pub fn last_slot(&self) -> Option<u64> {
+        self.inner.lock().unwrap().last_slot
+    }
+
+    pub fn snapshot(&self) -> HealthSnapshot {
+        self.inner.lock().unwrap().clone()
+    }
+
+    fn is_healthy(&self, max_silence: Duration) -> bool {
+        let h = self.inner.lock().unwrap();
+        h.state == ConnectionState::Standby
+            || h.state == ConnectionState::Connected
+            && h.last_message_at
+                .is_some_and(|at| now_ms().saturating_sub(at) <= max_silence.as_millis() as u64)
+    }
+}
+
+pub fn update_slot(update: &SubscribeUpdate) -> Option<u64> {
+    match update.update_oneof.as_ref()? {
+        UpdateOneof::Slot(s) => Some(s.slot),
+        UpdateOneof::Transaction(t) => Some(t.slot),
+        UpdateOneof::Account(a) => Some(a.slot),
+        UpdateOneof::Block(b) => Some(b.slot),
+        UpdateOneof::BlockMeta(b) => Some(b.slot),
+        _ => None,
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[handler]
+fn health_handler(
+    Data(health): Data<&Health>,
+    Data(max_silence): Data<&Duration>,
+) -> Response {
+    let status = if health.is_healthy(*max_silence) {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    poem::web::Json(health.snapshot())
+        .with_status(status)
+        .into_response()
+}
+
+/// Serve `/health` using the `HEALTH_*` environment settings.
+pub async fn serve(health: Health) -> Result<()> {
+    let addr = std::env::var("HEALTH_ADDR").unwrap_or_else(|_| "0.0.0.0:8081".to_string());
+    let max_silence = match std::env::var("HEALTH_MAX_SILENCE_SECS") {
+        Ok(v) => Duration::from_secs(v.parse().context("HEALTH_MAX_SILENCE_SECS")?),
+        Err(_) => Duration::from_secs(60),
+    };
+    let app = Route::new()
+        .at("/health", get(health_handler))
+        .data(health)
+        .data(max_silence);
+    tracing::info!(addr, "Serving health endpoint");
+    Server::new(TcpListener::bind(addr))
+        .name("laser-ingest-health")
+        .run(app)
+        .await?;
+    Ok(())
+}
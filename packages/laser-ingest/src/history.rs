@@ -0,0 +1,181 @@
+//! `laser-ingest backfill` – historical backfill of a single watched pool.
+//!
+//! ```text
+//! laser-ingest backfill --pool <id> (--from-slot <slot> | --since <unix seconds | 6h | 7d>)
+//!                       [--stream <redis stream>] [--limit <max transactions>]
+//! ```
+//!
+//! Pages through the pool's signatures over RPC (`BACKFILL_RPC_URL`), runs every
+//! transaction through the pool's `Processor`, oldest first, and publishes the events to
+//! `heimdall:pool_events:backfill` (or `--stream`) instead of the live stream, through the
+//! sink `EVENT_SINK` selects. Once all events are published, the processed signatures are
+//! remembered per pool in `heimdall:backfill:seen:<pool>`, so re-running an overlapping
+//! range publishes nothing twice. If publishing drops events, nothing is marked and a
+//! re-run retries the whole range.
+
+use anyhow::{anyhow, bail, Context, Result};
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::backfill::{fetch_transaction, signatures, SignatureRange};
+use crate::dispatch::Publisher;
+use crate::processors::Processor;
+use crate::rpc::RpcClient;
+use crate::tokens::TokenCache;
+use crate::types::PoolVariant;
+use crate::watchlist;
+
+const DEFAULT_STREAM: &str = "heimdall:pool_events:backfill";
+const DEFAULT_LIMIT: usize = 100_000;
+/// Signatures added to the seen set per `SADD`.
+const SEEN_CHUNK: usize = 1_000;
+
+#[derive(Debug, Clone)]
+pub struct HistoryArgs {
+    pub pool: String,
+    pub from_slot: Option<u64>,
+    /// Unix seconds.
+    pub since: Option<i64>,
+    pub stream: String,
+    pub limit: usize,
+}
+
+impl HistoryArgs {
+    /// Parse the arguments following `backfill`.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut pool = None;
+        let mut from_slot = None;
+        let mut since = None;
+        let mut stream = DEFAULT_STREAM.to_owned();
+        let mut limit = DEFAULT_LIMIT;
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or_else(|| anyhow!("{} needs a value", flag));
+            match flag.as_str() {
+                "--pool" => pool = Some(value()?),
+                "--from-slot" => from_slot = Some(value()?.parse().context("--from-slot")?),
+                "--since" => since = Some(parse_since(&value()?)?),
+                "--stream" => stream = value()?,
+                "--limit" => limit = value()?.parse().context("--limit")?,
+                other => bail!("unknown argument {}", other),
+            }
+        }
+        if from_slot.is_none() && since.is_none() {
+            bail!("one of --from-slot or --since is required");
+        }
+        Ok(Self {
+            pool: pool.ok_or_else(|| anyhow!("--pool is required"))?,
+            from_slot,
+            since,
+            stream,
+            limit,
+        })
+    }
+}
+
+/// Unix seconds, or a duration before now such as `90m`, `6h` or `7d`.
+fn parse_since(raw: &str) -> Result<i64> {
+    if let Ok(ts) = raw.parse() {
+        return Ok(ts);
+    }
+    let split = raw.char_indices().last().map_or(0, |(i, _)| i);
+    let (n, unit) = raw.split_at(split);
+    let n: i64 = n
+        .parse()
+        .with_context(|| format!("invalid --since {}", raw))?;
+    let secs = match unit {
+        "s" => n,
+        "m" => n * 60,
+        "h" => n * 3600,
+        "d" => n * 86_400,
+        _ => bail!("invalid --since {}", raw),
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    Ok(now - secs)
+}
+
+pub async fn run(
+    args: HistoryArgs,
+    processors: HashMap<PoolVariant, Box<dyn Processor>>,
+    tokens: TokenCache,
+    redis_client: redis::Client,
+) -> Result<()> {
+    let path = std::env::var("WATCHLIST_JSON").unwrap_or_else(|_| "watchlist.json".to_string());
+    let pool = watchlist::load_from_json(&path)?
+        .into_iter()
+        .find(|p| p.pool_id == args.pool)
+        .ok_or_else(|| anyhow!("pool {} is not in the watchlist {}", args.pool, path))?;
+    let proc = processors
+        .get(&pool.variant)
+        .ok_or_else(|| anyhow!("no processor for {:?}", pool.variant))?;
+    let rpc_url = std::env::var("BACKFILL_RPC_URL").context("BACKFILL_RPC_URL is required")?;
+    let rpc = RpcClient::new(rpc_url);
+    let mut conn = redis_client.get_multiplexed_async_connection().await?;
+    let (publisher, published) = Publisher::from_env(&args.stream)?;
+
+    let range = SignatureRange {
+        from_slot: args.from_slot.unwrap_or_default(),
+        since: args.since,
+        ..Default::default()
+    };
+    let sigs = signatures(&rpc, &pool.pool_id, &range, args.limit).await?;
+    tracing::info!(pool_id = %pool.pool_id, transactions = sigs.len(), stream = %args.stream, "Backfilling pool history");
+
+    let seen_key = format!("heimdall:backfill:seen:{}", pool.pool_id);
+    let mut processed = Vec::new();
+    let mut skipped = 0usize;
+    for sig in &sigs {
+        if conn.sismember(&seen_key, &sig.signature).await? {
+            skipped += 1;
+            continue;
+        }
+        if let Some(update) = fetch_transaction(&rpc, &sig.signature).await? {
+            tokens.observe(&update);
+            publisher.send(proc.handle_update(&pool, &update)).await;
+        }
+        processed.push(sig.signature.as_str());
+        if processed.len() % 500 == 0 {
+            tracing::info!(
+                processed = processed.len(),
+                total = sigs.len(),
+                "Backfill progress"
+            );
+        }
+    }
+    drop(publisher);
+    let dropped = published.await?;
+    if dropped > 0 {
+        bail!(
+            "{} events could not be published; no signatures were marked seen, re-run to retry",
+            dropped
+        );
+    }
+    for chunk in processed.chunks(SEEN_CHUNK) {
+        conn.sadd::<_, _, ()>(&seen_key, chunk).await?;
+    }
+    tracing::info!(pool_id = %pool.pool_id, processed = processed.len(), skipped, "Backfill complete");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_since_as_timestamp_or_duration() {
+        assert_eq!(parse_since("1700000000").unwrap(), 1_700_000_000);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let six_hours_ago = parse_since("6h").unwrap();
+        assert!((now - 6 * 3600 - six_hours_ago).abs() <= 1);
+    }
+
+    #[test]
+    fn rejects_bad_since_without_panicking() {
+        for raw in ["", "h", "6w", "6é", "é"] {
+            assert!(parse_since(raw).is_err(), "{}", raw);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Borsh decoding driven by IDL type descriptions.
+//!
+//! Integers up to 64 bits become JSON numbers; 128-bit integers are emitted as decimal
+//! strings so consumers don't silently lose precision. Public keys are base58 and raw
+//! `bytes` are base64.
+
+use anyhow::{anyhow, bail, Result};
+use base64::Engine as _;
+use serde_json::{Map, Value};
+
+use super::{IdlDecoder, IdlEnumFields, IdlField, IdlType, IdlTypeDefTy};
+
+pub(super) fn decode_typedef(
+    decoder: &IdlDecoder,
+    ty: &IdlTypeDefTy,
+    buf: &mut &[u8],
+) -> Result<Value> {
+    match ty {
+        IdlTypeDefTy::Struct { fields } => decode_fields(decoder, fields, buf),
+        IdlTypeDefTy::Enum { variants } => {
+            let idx = take::<1>(buf)?[0] as usize;
+            let variant = variants
+                .get(idx)
+                .ok_or_else(|| anyhow!("enum variant {} out of range", idx))?;
+            match &variant.fields {
+                None => Ok(Value::String(variant.name.clone())),
+                Some(fields) => {
+                    let inner = match fields {
+                        IdlEnumFields::Named(named) => decode_fields(decoder, named, buf)?,
+                        IdlEnumFields::Tuple(types) => Value::Array(
+                            types
+                                .iter()
+                                .map(|t| decode_type(decoder, t, buf))
+                                .collect::<Result<_>>()?,
+                        ),
+                    };
+                    let mut map = Map::new();
+                    map.insert(variant.name.clone(), inner);
+                    Ok(Value::Object(map))
+                }
+            }
+        }
+        IdlTypeDefTy::Type { alias } => decode_type(decoder, alias, buf),
+    }
+}
+
+pub(super) fn decode_fields(
+    decoder: &IdlDecoder,
+    fields: &[IdlField],
+    buf: &mut &[u8],
+) -> Result<Value> {
+    let mut map = Map::new();
+    for field in fields {
+        let value = decode_type(decoder, &field.ty, buf)
+            .map_err(|e| anyhow!("field `{}`: {}", field.name, e))?;
+        map.insert(field.name.clone(), value);
+    }
+    Ok(Value::Object(map))
+}
+
+fn decode_type(decoder: &IdlDecoder, ty: &IdlType, buf: &mut &[u8]) -> Result<Value> {
+    Ok(match ty {
+        IdlType::Primitive(name) => decode_primitive(name, buf)?,
+        IdlType::Option { option: inner } => match take::<1>(buf)?[0] {
+            0 => Value::Null,
+            1 => decode_type(decoder, inner, buf)?,
+            tag => bail!("invalid option tag {}", tag),
+        },
+        IdlType::COption { coption: inner } => {
+            // COption uses a 4-byte tag and always reserves space for the value.
+            let tag = u32::from_le_bytes(take::<4>(buf)?);
+            let value = decode_type(decoder, inner, buf)?;
+            if tag == 0 {
+                Value::Null
+            } else {
+                value
+            }
+        }
+        IdlType::Vec { vec: inner } => {
+            let len = u32::from_le_bytes(take::<4>(buf)?) as usize;
+            if len > buf.len() {
+                bail!("vec length {} exceeds remaining data", len);
+            }
+            if is_u8(inner) {
+                Value::Array(take_slice(buf, len)?.iter().map(|b| Value::from(*b)).collect())
+            } else {
+                Value::Array(
+                    (0..len)
+                        .map(|_| decode_type(decoder, inner, buf))
+                        .collect::<Result<_>>()?,
+                )
+            }
+        }
+        IdlType::Array { array: (inner, len) } => Value::Array(
+            (0..*len)
+                .map(|_| decode_type(decoder, inner, buf))
+                .collect::<Result<_>>()?,
+        ),
+        IdlType::Defined { defined } => {
+            let def = decoder
+                .type_def(defined.name())
+                .ok_or_else(|| anyhow!("unknown type `{}`", defined.name()))?;
+            decode_typedef(decoder, def, buf)?
+        }
+    })
+}
+
+fn decode_primitive(name: &str, buf: &mut &[u8]) -> Result<Value> {
+    Ok(match name {
+        "bool" => Value::Bool(take::<1>(buf)?[0] != 0),
+        "u8" => Value::from(take::<1>(buf)?[0]),
+        "i8" => Value::from(take::<1>(buf)?[0] as i8),
+        "u16" => Value::from(u16::from_le_bytes(take(buf)?)),
+        "i16" => Value::from(i16::from_le_bytes(take(buf)?)),
+        "u32" => Value::from(u32::from_le_bytes(take(buf)?)),
+        "i32" => Value::from(i32::from_le_bytes(take(buf)?)),
+        "f32" => Value::from(f32::from_le_bytes(take(buf)?)),
+        "u64" => Value::from(u64::from_le_bytes(take(buf)?)),
+        "i64" => Value::from(i64::from_le_bytes(take(buf)?)),
+        "f64" => Value::from(f64::from_le_bytes(take(buf)?)),
+        "u128" => Value::String(u128::from_le_bytes(take(buf)?).to_string()),
+        "i128" => Value::String(i128::from_le_bytes(take(buf)?).to_string()),
+        "pubkey" | "publicKey" => Value::String(bs58::encode(take::<32>(buf)?).into_string()),
+        "string" => {
+            let len = u32::from_le_bytes(take::<4>(buf)?) as usize;
+            Value::String(String::from_utf8_lossy(take_slice(buf, len)?).into_owned())
+        }
+        "bytes" => {
+            let len = u32::from_le_bytes(take::<4>(buf)?) as usize;
+            Value::String(base64::engine::general_purpose::STANDARD.encode(take_slice(buf, len)?))
+        }
+        other => bail!("unsupported IDL type `{}`", other),
+    })
+}
+
+fn is_u8(ty: &IdlType) -> bool {
+    matches!(ty, IdlType::Primitive(name) if name == "u8")
+}
+
+fn take<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N]> {
+    let slice = take_slice(buf, N)?;
+    Ok(slice.try_into().expect("slice has length N"))
+}
+
+fn take_slice<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if buf.len() < len {
+        bail!("unexpected end of data (wanted {}, have {})", len, buf.len());
+    }
+    let (head, tail) = buf.split_at(len);
+    *buf = tail;
+    Ok(head)
+}
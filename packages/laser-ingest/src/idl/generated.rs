@@ -0,0 +1,6 @@
+//! Borsh types generated by `build.rs` from the IDLs in `idls/`, one module per program.
+//!
+//! Processors should prefer these over hand-written structs so layouts can't drift from
+//! the on-chain program.
+
+include!(concat!(env!("OUT_DIR"), "/idls.rs"));
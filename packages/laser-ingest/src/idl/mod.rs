@@ -0,0 +1,568 @@
+//! Runtime Anchor IDL support.
+//!
+//! Loads an Anchor IDL JSON file (both the 0.30+ format with explicit discriminators and
+//! the legacy format without them) and decodes events and instructions of that program
+//! into `serde_json::Value`s, so a new program can be watched without writing any Borsh
+//! structs by hand. Only the parts of the IDL needed for that are modelled.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, fs, path::Path};
+
+mod decode;
+pub mod generated;
+
+/// Prefix of every Anchor `emit_cpi!` self-invocation (`sha256("anchor:event")[..8]`, LE).
+pub const ANCHOR_CPI_LOG_DISCRIMINATOR: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+
+pub type Discriminator = [u8; 8];
+
+// ------------------------------------------------------------------
+// IDL JSON model
+// ------------------------------------------------------------------
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Idl {
+    /// Program id (new format). Legacy IDLs keep it in `metadata.address`.
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<IdlMetadata>,
+    #[serde(default)]
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default)]
+    pub events: Vec<IdlEvent>,
+    #[serde(default)]
+    pub types: Vec<IdlTypeDef>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlMetadata {
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    #[serde(default)]
+    pub discriminator: Option<Discriminator>,
+    #[serde(default)]
+    pub accounts: Vec<IdlInstructionAccount>,
+    #[serde(default)]
+    pub args: Vec<IdlField>,
+}
+
+/// An instruction account. Composite (nested) account groups are flattened in order.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstructionAccount {
+    pub name: String,
+    #[serde(default)]
+    pub accounts: Vec<IdlInstructionAccount>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlEvent {
+    pub name: String,
+    #[serde(default)]
+    pub discriminator: Option<Discriminator>,
+    /// Legacy IDLs inline the event fields here.
+    #[serde(default)]
+    pub fields: Option<Vec<IdlField>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlTypeDef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefTy,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlTypeDefTy {
+    Struct {
+        #[serde(default)]
+        fields: Vec<IdlField>,
+    },
+    Enum {
+        variants: Vec<IdlEnumVariant>,
+    },
+    Type {
+        alias: IdlType,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Option<IdlEnumFields>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlEnumFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlType {
+    /// `u8`, `u64`, `bool`, `pubkey`/`publicKey`, `string`, `bytes`, ...
+    Primitive(String),
+    Option { option: Box<IdlType> },
+    COption { coption: Box<IdlType> },
+    Vec { vec: Box<IdlType> },
+    Array { array: (Box<IdlType>, usize) },
+    Defined { defined: IdlDefined },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlDefined {
+    /// Legacy: `{ "defined": "Name" }`
+    Legacy(String),
+    /// 0.30+: `{ "defined": { "name": "Name" } }`
+    Named { name: String },
+}
+
+impl IdlDefined {
+    pub fn name(&self) -> &str {
+        match self {
+            IdlDefined::Legacy(name) => name,
+            IdlDefined::Named { name } => name,
+        }
+    }
+}
+
+impl IdlInstructionAccount {
+    fn flatten_into(&self, out: &mut Vec<IdlInstructionAccount>) {
+        if self.accounts.is_empty() {
+            out.push(self.clone());
+        } else {
+            for acc in &self.accounts {
+                acc.flatten_into(out);
+            }
+        }
+    }
+}
+
+// ------------------------------------------------------------------
+// Discriminators
+// ------------------------------------------------------------------
+
+fn sighash(namespace: &str, name: &str) -> Discriminator {
+    let digest = Sha256::digest(format!("{}:{}", namespace, name).as_bytes());
+    let mut out = [0u8; 8];
+    out.copy_from_slice(&digest[..8]);
+    out
+}
+
+/// `sha256("event:<Name>")[..8]`
+pub fn event_discriminator(name: &str) -> Discriminator {
+    sighash("event", name)
+}
+
+/// `sha256("global:<snake_case_name>")[..8]`
+pub fn instruction_discriminator(name: &str) -> Discriminator {
+    sighash("global", &to_snake_case(name))
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, ch) in name.chars().enumerate() {
+        if ch.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.push(ch.to_ascii_lowercase());
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+// ------------------------------------------------------------------
+// Decoder
+// ------------------------------------------------------------------
+
+/// A decoded event.
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    pub name: String,
+    pub data: Value,
+}
+
+/// A decoded instruction with its accounts resolved to IDL names.
+#[derive(Debug, Clone)]
+pub struct DecodedInstruction {
+    pub name: String,
+    pub args: Value,
+    /// `(idl account name, base58 pubkey)` in instruction order.
+    pub accounts: Vec<(String, String)>,
+}
+
+/// Decoder for a single program, built from its IDL.
+pub struct IdlDecoder {
+    program_id: Option<String>,
+    types: HashMap<String, IdlTypeDefTy>,
+    events: HashMap<Discriminator, (String, IdlTypeDefTy)>,
+    instructions: HashMap<Discriminator, IdlInstruction>,
+}
+
+impl IdlDecoder {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("reading IDL {}", path.display()))?;
+        Self::from_json(&data).with_context(|| format!("parsing IDL {}", path.display()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let idl: Idl = serde_json::from_str(json)?;
+        Ok(Self::new(idl))
+    }
+
+    pub fn new(idl: Idl) -> Self {
+        let types: HashMap<String, IdlTypeDefTy> = idl
+            .types
+            .iter()
+            .map(|t| (t.name.clone(), t.ty.clone()))
+            .collect();
+
+        let mut events = HashMap::new();
+        for evt in &idl.events {
+            let ty = match &evt.fields {
+                Some(fields) => IdlTypeDefTy::Struct {
+                    fields: fields.clone(),
+                },
+                None => match types.get(&evt.name) {
+                    Some(ty) => ty.clone(),
+                    None => {
+                        tracing::warn!(event = %evt.name, "IDL event has no type definition");
+                        continue;
+                    }
+                },
+            };
+            let disc = evt
+                .discriminator
+                .unwrap_or_else(|| event_discriminator(&evt.name));
+            events.insert(disc, (evt.name.clone(), ty));
+        }
+
+        let mut instructions = HashMap::new();
+        for ix in &idl.instructions {
+            let disc = ix
+                .discriminator
+                .unwrap_or_else(|| instruction_discriminator(&ix.name));
+            let mut flat = Vec::new();
+            for acc in &ix.accounts {
+                acc.flatten_into(&mut flat);
+            }
+            instructions.insert(
+                disc,
+                IdlInstruction {
+                    accounts: flat,
+                    ..ix.clone()
+                },
+            );
+        }
+
+        let program_id = idl
+            .address
+            .clone()
+            .or_else(|| idl.metadata.as_ref().and_then(|m| m.address.clone()));
+
+        Self {
+            program_id,
+            types,
+            events,
+            instructions,
+        }
+    }
+
+    /// Program id recorded in the IDL, if any.
+    pub fn program_id(&self) -> Option<&str> {
+        self.program_id.as_deref()
+    }
+
+    /// Decode an event payload (`<8 byte event discriminator><borsh data>`).
+    pub fn decode_event(&self, data: &[u8]) -> Option<Decoded> {
+        let (disc, mut rest) = split_discriminator(data)?;
+        let (name, ty) = self.events.get(&disc)?;
+        match decode::decode_typedef(self, ty, &mut rest) {
+            Ok(data) => Some(Decoded {
+                name: name.clone(),
+                data,
+            }),
+            Err(e) => {
+                tracing::warn!(event = %name, error = %e, "Failed to decode IDL event");
+                None
+            }
+        }
+    }
+
+    /// Decode the data of an `emit_cpi!` self-invocation, i.e. an inner instruction whose
+    /// data starts with [`ANCHOR_CPI_LOG_DISCRIMINATOR`].
+    pub fn decode_cpi_event(&self, ix_data: &[u8]) -> Option<Decoded> {
+        let rest = ix_data.strip_prefix(&ANCHOR_CPI_LOG_DISCRIMINATOR)?;
+        self.decode_event(rest)
+    }
+
+    /// Decode an instruction. `account_keys` are the instruction's accounts, already
+    /// resolved to pubkeys, in the order they appear in the instruction.
+    pub fn decode_instruction(
+        &self,
+        data: &[u8],
+        account_keys: &[Vec<u8>],
+    ) -> Option<DecodedInstruction> {
+        let (disc, mut rest) = split_discriminator(data)?;
+        let ix = self.instructions.get(&disc)?;
+        let args = match decode::decode_fields(self, &ix.args, &mut rest) {
+            Ok(args) => args,
+            Err(e) => {
+                tracing::warn!(instruction = %ix.name, error = %e, "Failed to decode IDL instruction");
+                return None;
+            }
+        };
+        let accounts = ix
+            .accounts
+            .iter()
+            .zip(account_keys)
+            .map(|(acc, key)| (acc.name.clone(), bs58::encode(key).into_string()))
+            .collect();
+        Some(DecodedInstruction {
+            name: ix.name.clone(),
+            args,
+            accounts,
+        })
+    }
+
+    fn type_def(&self, name: &str) -> Option<&IdlTypeDefTy> {
+        self.types.get(name)
+    }
+}
+
+fn split_discriminator(data: &[u8]) -> Option<(Discriminator, &[u8])> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (disc, rest) = data.split_at(8);
+    Some((disc.try_into().ok()?, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Legacy format: no discriminators, event fields inline, `defined` as a string.
+    const LEGACY_IDL: &str = r#"{
+        "metadata": { "address": "Prog111111111111111111111111111111111111111" },
+        "instructions": [{
+            "name": "swapExactIn",
+            "accounts": [
+                { "name": "user", "isMut": true, "isSigner": true },
+                { "name": "pool", "accounts": [
+                    { "name": "state", "isMut": true, "isSigner": false },
+                    { "name": "vault", "isMut": true, "isSigner": false }
+                ]}
+            ],
+            "args": [
+                { "name": "params", "type": { "defined": "SwapParams" } },
+                { "name": "memo", "type": { "option": "string" } }
+            ]
+        }],
+        "events": [{
+            "name": "Traded",
+            "fields": [
+                { "name": "side", "type": { "defined": "Side" } },
+                { "name": "amounts", "type": { "vec": "u64" } },
+                { "name": "price", "type": "u128" }
+            ]
+        }],
+        "types": [
+            { "name": "SwapParams", "type": { "kind": "struct", "fields": [
+                { "name": "amountIn", "type": "u64" },
+                { "name": "minOut", "type": "u64" }
+            ]}},
+            { "name": "Side", "type": { "kind": "enum", "variants": [
+                { "name": "Buy" },
+                { "name": "Sell", "fields": [{ "name": "partial", "type": "bool" }] }
+            ]}}
+        ]
+    }"#;
+
+    /// 0.30+ format: explicit discriminators, `defined: { name }`, types shared with events.
+    const IDL: &str = r#"{
+        "address": "Prog222222222222222222222222222222222222222",
+        "instructions": [{
+            "name": "set_authority",
+            "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+            "accounts": [{ "name": "authority", "signer": true }],
+            "args": [
+                { "name": "new_authority", "type": { "coption": "pubkey" } },
+                { "name": "seeds", "type": { "array": ["u8", 2] } },
+                { "name": "data", "type": "bytes" }
+            ]
+        }],
+        "events": [{ "name": "AuthoritySet", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+        "types": [{ "name": "AuthoritySet", "type": { "kind": "struct", "fields": [
+            { "name": "authority", "type": { "coption": "pubkey" } },
+            { "name": "flags", "type": { "vec": "u8" } }
+        ]}}]
+    }"#;
+
+    fn u32_le(v: u32) -> Vec<u8> {
+        v.to_le_bytes().to_vec()
+    }
+
+    #[test]
+    fn discriminators() {
+        assert_eq!(to_snake_case("swapExactIn"), "swap_exact_in");
+        assert_eq!(to_snake_case("initialize"), "initialize");
+        assert_eq!(
+            instruction_discriminator("initialize"),
+            [175, 175, 109, 31, 13, 152, 155, 237]
+        );
+        assert_eq!(
+            instruction_discriminator("swapExactIn"),
+            [104, 104, 131, 86, 161, 189, 180, 216]
+        );
+        assert_eq!(
+            event_discriminator("Traded"),
+            [225, 202, 73, 175, 147, 43, 160, 150]
+        );
+        let mut anchor_event = sighash("anchor", "event");
+        anchor_event.reverse();
+        assert_eq!(anchor_event, ANCHOR_CPI_LOG_DISCRIMINATOR);
+    }
+
+    #[test]
+    fn legacy_instruction_with_nested_accounts() {
+        let decoder = IdlDecoder::from_json(LEGACY_IDL).unwrap();
+        assert_eq!(
+            decoder.program_id(),
+            Some("Prog111111111111111111111111111111111111111")
+        );
+
+        let mut data = instruction_discriminator("swapExactIn").to_vec();
+        data.extend(1_000u64.to_le_bytes());
+        data.extend(990u64.to_le_bytes());
+        data.push(1);
+        data.extend(u32_le(2));
+        data.extend(b"hi");
+        let keys = vec![vec![1; 32], vec![2; 32], vec![3; 32]];
+
+        let ix = decoder.decode_instruction(&data, &keys).unwrap();
+        assert_eq!(ix.name, "swapExactIn");
+        assert_eq!(
+            ix.args,
+            json!({ "params": { "amountIn": 1000, "minOut": 990 }, "memo": "hi" })
+        );
+        let names: Vec<_> = ix.accounts.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["user", "state", "vault"]);
+        assert_eq!(ix.accounts[2].1, bs58::encode([3; 32]).into_string());
+
+        // `None` option, and unknown or truncated data.
+        let mut no_memo = data[..24].to_vec();
+        no_memo.push(0);
+        assert_eq!(
+            decoder.decode_instruction(&no_memo, &keys).unwrap().args["memo"],
+            Value::Null
+        );
+        assert!(decoder.decode_instruction(&data[..20], &keys).is_none());
+        assert!(decoder.decode_instruction(&[0; 16], &keys).is_none());
+        assert!(decoder.decode_instruction(&data[..4], &keys).is_none());
+    }
+
+    #[test]
+    fn legacy_event_with_enum_vec_and_u128() {
+        let decoder = IdlDecoder::from_json(LEGACY_IDL).unwrap();
+        let mut data = event_discriminator("Traded").to_vec();
+        data.extend([1, 1]); // Sell { partial: true }
+        data.extend(u32_le(2));
+        data.extend(5u64.to_le_bytes());
+        data.extend(7u64.to_le_bytes());
+        data.extend(u128::MAX.to_le_bytes());
+
+        let evt = decoder.decode_event(&data).unwrap();
+        assert_eq!(evt.name, "Traded");
+        assert_eq!(
+            evt.data,
+            json!({
+                "side": { "Sell": { "partial": true } },
+                "amounts": [5, 7],
+                "price": u128::MAX.to_string(),
+            })
+        );
+
+        // Unit variant, and an out-of-range one.
+        data[8] = 0;
+        data.remove(9);
+        assert_eq!(decoder.decode_event(&data).unwrap().data["side"], "Buy");
+        data[8] = 2;
+        assert!(decoder.decode_event(&data).is_none());
+    }
+
+    #[test]
+    fn coption_always_reserves_its_value() {
+        let decoder = IdlDecoder::from_json(IDL).unwrap();
+        let some = {
+            let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+            data.extend(u32_le(1));
+            data.extend([4; 32]);
+            data.extend([7, 8]);
+            data.extend(u32_le(3));
+            data.extend([0xff, 0, 1]);
+            data
+        };
+        let ix = decoder.decode_instruction(&some, &[vec![4; 32]]).unwrap();
+        assert_eq!(ix.name, "set_authority");
+        assert_eq!(
+            ix.args,
+            json!({
+                "new_authority": bs58::encode([4; 32]).into_string(),
+                "seeds": [7, 8],
+                "data": "/wAB",
+            })
+        );
+        assert_eq!(ix.accounts[0].0, "authority");
+
+        // A `None` still occupies 4 + 32 bytes; the following fields must line up.
+        let mut none = some.clone();
+        none[8..12].copy_from_slice(&u32_le(0));
+        let args = decoder.decode_instruction(&none, &[]).unwrap().args;
+        assert_eq!(args["new_authority"], Value::Null);
+        assert_eq!(args["seeds"], json!([7, 8]));
+    }
+
+    #[test]
+    fn cpi_event_with_explicit_discriminator() {
+        let decoder = IdlDecoder::from_json(IDL).unwrap();
+        let mut data = ANCHOR_CPI_LOG_DISCRIMINATOR.to_vec();
+        data.extend([9; 8]);
+        data.extend(u32_le(0));
+        data.extend([0; 32]);
+        data.extend(u32_le(2));
+        data.extend([1, 2]);
+
+        let evt = decoder.decode_cpi_event(&data).unwrap();
+        assert_eq!(evt.name, "AuthoritySet");
+        assert_eq!(evt.data, json!({ "authority": null, "flags": [1, 2] }));
+        // Without the CPI prefix it isn't an `emit_cpi!` event.
+        assert!(decoder.decode_cpi_event(&data[8..]).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+//! Active/standby leader election over a Redis lease.
+//!
+//! Only the replica holding the lease subscribes to LaserStream; the others wait on standby
+//! and poll the lease. The leader renews it every third of the TTL and drops its subscription
+//! as soon as a renewal fails, so a standby takes over at most `TTL + TTL / 3` after the
+//! leader stops.
+//!
+//! * `LEADER_ELECTION` – enable (`true`/`1`); off by default, every replica subscribes.
+//! * `LEADER_LEASE_KEY` – Redis key of the lease (default `heimdall:ingest:leader`).
+//! * `LEADER_LEASE_TTL_SECS` – lease TTL (default 15).
+//! * `LEADER_ID` – this replica's lease value (default `$HOSTNAME-<pid>`).
+//!
+//! On every renewal the leader also stores its last seen slot in `<lease key>:last_slot`,
+//! where a new leader picks it up to resume its subscription.
+
+use anyhow::{Context, Result};
+use redis::aio::MultiplexedConnection as RedisConn;
+use redis::AsyncCommands;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Take the lease if it is free, or extend it if `ARGV[1]` already holds it. Returns 1 when
+/// the caller holds the lease afterwards.
+static ACQUIRE_OR_RENEW: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+        local holder = redis.call('GET', KEYS[1])
+        if holder == false then
+            redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+            return 1
+        elseif holder == ARGV[1] then
+            redis.call('PEXPIRE', KEYS[1], ARGV[2])
+            return 1
+        end
+        return 0
+        ",
+    )
+});
+
+#[derive(Debug, Clone)]
+pub struct Leader {
+    key: String,
+    /// `<key>:last_slot`, so deployments sharing a Redis with separate leases don't
+    /// resume from each other's slot.
+    last_slot_key: String,
+    id: String,
+    ttl: Duration,
+}
+
+impl Leader {
+    /// `None` unless `LEADER_ELECTION` is enabled.
+    pub fn from_env() -> Result<Option<Self>> {
+        let enabled = std::env::var("LEADER_ELECTION")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+        let ttl = match std::env::var("LEADER_LEASE_TTL_SECS") {
+            Ok(v) => Duration::from_secs(v.parse().context("LEADER_LEASE_TTL_SECS")?),
+            Err(_) => Duration::from_secs(15),
+        };
+        anyhow::ensure!(
+            ttl >= Duration::from_secs(3),
+            "LEADER_LEASE_TTL_SECS must be at least 3"
+        );
+        let id = std::env::var("LEADER_ID").unwrap_or_else(|_| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "laser-ingest".to_string());
+            format!("{}-{}", host, std::process::id())
+        });
+        let key = std::env::var("LEADER_LEASE_KEY")
+            .unwrap_or_else(|_| "heimdall:ingest:leader".to_string());
+        Ok(Some(Self::new(key, id, ttl)))
+    }
+
+    fn new(key: String, id: String, ttl: Duration) -> Self {
+        Self {
+            last_slot_key: format!("{}:last_slot", key),
+            key,
+            id,
+            ttl,
+        }
+    }
+
+    /// How often the leader renews and standbys poll.
+    pub fn renew_interval(&self) -> Duration {
+        self.ttl / 3
+    }
+
+    async fn try_acquire(&self, conn: &mut RedisConn) -> redis::RedisResult<bool> {
+        ACQUIRE_OR_RENEW
+            .key(&self.key)
+            .arg(&self.id)
+            .arg(self.ttl.as_millis() as u64)
+            .invoke_async(conn)
+            .await
+    }
+
+    /// Wait on standby until this replica holds the lease. Returns the slot the previous
+    /// leader last recorded.
+    pub async fn acquire(&self, client: &redis::Client) -> Option<u64> {
+        tracing::info!(id = %self.id, key = %self.key, "Standing by for leader lease");
+        loop {
+            let attempt = async {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                if !self.try_acquire(&mut conn).await? {
+                    return Ok(None);
+                }
+                let slot: Option<u64> = conn.get(&self.last_slot_key).await?;
+                Ok::<_, redis::RedisError>(Some(slot))
+            };
+            match attempt.await {
+                Ok(Some(last_slot)) => {
+                    tracing::info!(id = %self.id, ?last_slot, "Acquired leader lease");
+                    return last_slot;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!(error = %e, "Failed to acquire leader lease"),
+            }
+            tokio::time::sleep(self.renew_interval()).await;
+        }
+    }
+
+    /// Extend the lease and record `last_slot`. `false` once the lease is lost, in which
+    /// case the caller must stop ingesting.
+    pub async fn renew(&self, conn: &mut RedisConn, last_slot: Option<u64>) -> bool {
+        match self.try_acquire(conn).await {
+            Ok(true) => {}
+            Ok(false) => return false,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to renew leader lease");
+                return false;
+            }
+        }
+        if let Some(slot) = last_slot {
+            if let Err(e) = conn.set::<_, _, ()>(&self.last_slot_key, slot).await {
+                tracing::warn!(error = %e, "Failed to record last slot");
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeRedis;
+
+    const TTL: Duration = Duration::from_secs(15);
+
+    #[tokio::test]
+    async fn acquires_free_lease_and_resumes_from_recorded_slot() {
+        let redis = FakeRedis::start().await;
+        let mut conn = redis
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        conn.set::<_, _, ()>("lease:last_slot", 42).await.unwrap();
+
+        let leader = Leader::new("lease".into(), "a".into(), TTL);
+        assert_eq!(leader.acquire(&redis.client).await, Some(42));
+
+        let holder: String = conn.get("lease").await.unwrap();
+        assert_eq!(holder, "a");
+    }
+
+    #[tokio::test]
+    async fn renewal_records_last_slot_under_the_lease_key() {
+        let redis = FakeRedis::start().await;
+        let mut conn = redis
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        let leader = Leader::new("lease".into(), "a".into(), TTL);
+        assert!(leader.try_acquire(&mut conn).await.unwrap());
+
+        assert!(leader.renew(&mut conn, Some(100)).await);
+        let slot: u64 = conn.get("lease:last_slot").await.unwrap();
+        assert_eq!(slot, 100);
+    }
+
+    #[tokio::test]
+    async fn standby_cannot_take_a_held_lease() {
+        let redis = FakeRedis::start().await;
+        let mut conn = redis
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        let leader = Leader::new("lease".into(), "a".into(), TTL);
+        let standby = Leader::new("lease".into(), "b".into(), TTL);
+
+        assert!(leader.try_acquire(&mut conn).await.unwrap());
+        assert!(!standby.try_acquire(&mut conn).await.unwrap());
+        assert!(leader.try_acquire(&mut conn).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn renewal_fails_once_the_lease_is_lost() {
+        let redis = FakeRedis::start().await;
+        let mut conn = redis
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        let leader = Leader::new("lease".into(), "a".into(), TTL);
+        assert!(leader.renew(&mut conn, Some(100)).await);
+
+        // The lease expired and another replica took it.
+        conn.set::<_, _, ()>("lease", "b").await.unwrap();
+        assert!(!leader.renew(&mut conn, Some(200)).await);
+
+        let slot: u64 = conn.get("lease:last_slot").await.unwrap();
+        assert_eq!(
+            slot, 100,
+            "a replica that lost the lease must not record its slot"
+        );
+    }
+}
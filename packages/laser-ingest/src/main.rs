@@ -2,20 +2,56 @@ use anyhow::Result;
 use futures_util::StreamExt;
 use processors::{default_registry, Processor};
 use redis::aio::MultiplexedConnection as RedisConn;
-use std::collections::{HashMap, HashSet};
-use tokio::sync::watch;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
 use bs58;
 
+mod backfill;
+mod discovery;
+mod dispatch;
+mod health;
+mod history;
+mod idl;
+mod leader;
+mod price;
 mod processors;
+mod recorder;
+mod rpc;
+mod sinks;
+mod sources;
+mod tokens;
+mod tx_error;
 mod types;
 mod watchlist;
 
+#[cfg(test)]
+mod testing;
+
 use types::PoolMeta;
 
+/// First delay before reconnecting after the stream ended; doubles up to `MAX_BACKOFF`.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Slots to rewind when resuming with `from_slot`, so updates of forked-off slots at
+/// `processed` commitment are replayed from the canonical chain.
+const FROM_SLOT_REWIND: u64 = 31;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenvy::dotenv().ok();
-    tracing_subscriber::fmt::init();
+    if std::env::var("EVENT_SINK").as_deref() == Ok("stdout") {
+        // stdout carries the events.
+        tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+    } else {
+        tracing_subscriber::fmt::init();
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("backfill") {
+        let args = history::HistoryArgs::parse(std::env::args().skip(2))?;
+        return backfill_history(args).await;
+    }
 
     // --------------------------------------------------
     // Load watchlist from local JSON (for dev / isolated mode)
@@ -24,21 +60,105 @@ async fn main() -> Result<()> {
     let pools = watchlist::load_from_json(&path)?;
     tracing::info!(?pools, "Loaded watchlist");
 
-    // Channel if we later want dynamic reloads (not used in this isolated example)
-    let (tx, rx) = watch::channel(pools);
+    // Watchlist updates (e.g. from discovery) trigger a resubscribe
+    let (pool_tx, pool_rx) = watch::channel(pools);
+
+    let discovery_config = discovery::DiscoveryConfig::from_env()?;
+    let discovery = if discovery_config.enabled {
+        tracing::info!(config = ?discovery_config, "Pool discovery enabled");
+        let (found_tx, found_rx) = mpsc::channel(1024);
+        tokio::spawn(discovery::run_registrar(
+            path.clone(),
+            found_rx,
+            pool_tx.clone(),
+            discovery_config.flush_interval,
+        ));
+        Some((discovery::Discovery::new(discovery_config), found_tx))
+    } else {
+        None
+    };
 
-    ingest_worker(rx).await
+    let result = ingest_worker(pool_rx, discovery).await;
+    drop(pool_tx);
+    result
 }
 
-async fn ingest_worker(mut pool_rx: watch::Receiver<Vec<PoolMeta>>) -> Result<()> {
-    let processors_registry = default_registry();
+/// `laser-ingest backfill ...`: replay one pool's history into the backfill stream.
+async fn backfill_history(args: history::HistoryArgs) -> Result<()> {
+    let tokens = tokens::TokenCache::from_env()?;
+    let publish_failed_swaps = std::env::var("PUBLISH_FAILED_SWAPS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    // No current SOL/USD price: it would misprice historical swaps.
+    let registry = default_registry(
+        tokens.clone(),
+        price::SolUsdPrice::default(),
+        publish_failed_swaps,
+    );
+    let redis_url =
+        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    history::run(args, registry, tokens, redis::Client::open(redis_url)?).await
+}
+
+async fn ingest_worker(
+    mut pool_rx: watch::Receiver<Vec<PoolMeta>>,
+    discovery: Option<(discovery::Discovery, mpsc::Sender<PoolMeta>)>,
+) -> Result<()> {
+    let tokens = tokens::TokenCache::from_env()?;
+    let sol_usd = price::SolUsdPrice::from_env()?;
+    let publish_failed_swaps = std::env::var("PUBLISH_FAILED_SWAPS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    let processors_registry = Arc::new(default_registry(
+        tokens.clone(),
+        sol_usd,
+        publish_failed_swaps,
+    ));
 
     // Redis connection (shared across restarts)
     let redis_url =
         std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
     let redis_client = redis::Client::open(redis_url)?;
 
+    let backfill_config = backfill::BackfillConfig::from_env()?;
+    let cursors = async {
+        let mut conn = redis_client.get_multiplexed_async_connection().await?;
+        backfill::Cursors::load(&mut conn).await
+    }
+    .await
+    .unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Failed to load ingest cursors, gaps before now won't be backfilled");
+        backfill::Cursors::default()
+    });
+
+    let health = health::Health::default();
+    let health_server = health.clone();
+    tokio::spawn(async move {
+        if let Err(e) = health::serve(health_server).await {
+            tracing::error!(error = %e, "Health endpoint failed");
+        }
+    });
+    // Resume from the last seen slot after a reconnect; LaserStream supports `from_slot`,
+    // other Yellowstone providers may not.
+    let resume_from_slot = std::env::var("RESUME_FROM_SLOT")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(true);
+    let source = sources::from_env()?;
+    let leader = leader::Leader::from_env()?;
+    let mut recorder = recorder::Recorder::from_env()?;
+    let (publisher, _) = dispatch::Publisher::from_env(processors::events::POOL_EVENTS_STREAM)?;
+    let mut backoff = INITIAL_BACKOFF;
+
     loop {
+        // Standbys wait here; a new leader resumes from its predecessor's last slot.
+        let leader_slot = match &leader {
+            Some(leader) => {
+                health.standby();
+                leader.acquire(&redis_client).await
+            }
+            None => None,
+        };
+
         let current_pools = pool_rx.borrow().clone();
         tracing::info!(pools = current_pools.len(), "Building SubscribeRequest for pools");
 
@@ -54,7 +174,11 @@ async fn ingest_worker(mut pool_rx: watch::Receiver<Vec<PoolMeta>>) -> Result<()
             }
         }
 
-        let request = helius_laserstream::grpc::SubscribeRequest {
+        if let Some((discovery, _)) = &discovery {
+            tx_filters.extend(discovery.filters());
+        }
+
+        let mut request = helius_laserstream::grpc::SubscribeRequest {
             accounts,
             transactions: tx_filters,
             blocks: HashMap::new(),
@@ -63,68 +187,121 @@ async fn ingest_worker(mut pool_rx: watch::Receiver<Vec<PoolMeta>>) -> Result<()
             commitment: Some(helius_laserstream::grpc::CommitmentLevel::Processed as i32),
             ..Default::default()
         };
-
-        // Helius config
-        let api_key = std::env::var("HELIUS_API_KEY").unwrap_or_else(|_| "demo".to_string());
-        let endpoint_url = std::env::var("HELIUS_ENDPOINT")
-            .unwrap_or_else(|_| "https://laserstream-devnet.helius-rpc.com".to_string());
-        let config = helius_laserstream::LaserstreamConfig {
-            api_key,
-            endpoint: endpoint_url.parse()?,
-            ..Default::default()
-        };
+        if resume_from_slot {
+            if let Some(slot) = health.last_slot().max(leader_slot) {
+                request.from_slot = Some(slot.saturating_sub(FROM_SLOT_REWIND));
+                tracing::info!(from_slot = ?request.from_slot, "Resuming subscription");
+            }
+        }
 
         // Connect & subscribe
-        tracing::info!("Connecting to Helius LaserStream...");
-        let stream = helius_laserstream::subscribe(config, request);
+        tracing::info!(source = source.name(), "Connecting to upstream...");
+        health.connecting();
+        let mut redis_conn: RedisConn = match redis_client.get_multiplexed_async_connection().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::error!(error = %e, ?backoff, "Redis connection failed, retrying");
+                health.reconnecting(&e);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                continue;
+            }
+        };
+        let stream = source.subscribe(request);
         futures_util::pin_mut!(stream);
-
-        let mut redis_conn: RedisConn = redis_client.get_multiplexed_async_connection().await?;
+        // The first update of a new connection bounds the gap to backfill.
+        let mut backfill_pending = backfill_config.is_some();
+        let mut cursor_flush = tokio::time::interval(backfill::CURSOR_FLUSH_INTERVAL);
+        let mut lease_renewal = tokio::time::interval(
+            leader
+                .as_ref()
+                .map_or(Duration::from_secs(3600), |l| l.renew_interval()),
+        );
 
         // Main loop
         loop {
             tokio::select! {
-                maybe_msg = stream.next() => {
-                    if let Some(Ok(msg)) = maybe_msg {
-                        // Determine which pool this update relates to (naive implementation)
-                        if let Some(pool) = match_update_to_pool(&msg, &current_pools) {
-                            if let Some(proc) = processors_registry.get(&pool.variant) {
-                                proc.handle_update(pool, &msg, &mut redis_conn).await;
+                maybe_msg = stream.next() => match maybe_msg {
+                    Some(Ok(msg)) => {
+                        health.message(&msg);
+                        if let Some(recorder) = &mut recorder {
+                            recorder.record(&msg);
+                        }
+                        backoff = INITIAL_BACKOFF;
+                        tokens.observe(&msg);
+                        if let (true, Some(config), Some(live_slot)) =
+                            (backfill_pending, &backfill_config, health::update_slot(&msg))
+                        {
+                            backfill_pending = false;
+                            tokio::spawn(backfill::run(
+                                rpc::RpcClient::new(&config.rpc_url),
+                                config.max_signatures,
+                                current_pools.clone(),
+                                cursors.snapshot(),
+                                live_slot,
+                                processors_registry.clone(),
+                                tokens.clone(),
+                                publisher.clone(),
+                            ));
+                        }
+                        if let Some((discovery, found_tx)) = &discovery {
+                            for pool in discovery.detect(&msg) {
+                                if found_tx.try_send(pool).is_err() {
+                                    tracing::warn!("Discovery queue full, dropping discovered pool");
+                                }
                             }
                         }
+
+                        let tx_position = match &msg.update_oneof {
+                            Some(helius_laserstream::grpc::subscribe_update::UpdateOneof::Transaction(tx)) => {
+                                tx.transaction.as_ref().map(|info| {
+                                    (tx.slot, bs58::encode(&info.signature).into_string())
+                                })
+                            }
+                            _ => None,
+                        };
+                        let pools =
+                            dispatch::dispatch(&msg, &current_pools, &processors_registry, &publisher)
+                                .await;
+                        if let Some((slot, signature)) = &tx_position {
+                            for pool in pools {
+                                cursors.record(&pool.pool_id, *slot, signature.clone());
+                            }
+                        }
+                    }
+                    // The client reconnects by itself after an error (resuming from its own
+                    // tracked slot); record it and keep reading.
+                    Some(Err(e)) => {
+                        tracing::warn!(error = %e, "Upstream error");
+                        health.reconnecting(&e);
+                    }
+                    // The client gave up reconnecting: back off and subscribe again.
+                    None => {
+                        tracing::error!(?backoff, "Upstream ended, resubscribing");
+                        cursors.flush(&mut redis_conn).await;
+                        health.reconnecting("stream ended");
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                        break;
                     }
                 },
+                _ = lease_renewal.tick(), if leader.is_some() => {
+                    let leader = leader.as_ref().unwrap();
+                    if !leader.renew(&mut redis_conn, health.last_slot()).await {
+                        tracing::warn!("Lost leader lease, dropping subscription");
+                        break;
+                    }
+                }
+                _ = cursor_flush.tick() => {
+                    cursors.flush(&mut redis_conn).await;
+                }
                 _ = pool_rx.changed() => {
                     // Rebuild filters on next loop iteration
                     tracing::info!("Watchlist changed, reconnecting...");
+                    cursors.flush(&mut redis_conn).await;
                     break;
                 }
             }
         }
     }
 }
-
-fn match_update_to_pool<'a>(
-    update: &helius_laserstream::grpc::SubscribeUpdate,
-    pools: &'a [PoolMeta],
-) -> Option<&'a PoolMeta> {
-    if let Some(oneof) = &update.update_oneof {
-        match oneof {
-            helius_laserstream::grpc::subscribe_update::UpdateOneof::Account(acc_upd) => {
-                if let Some(acc) = &acc_upd.account {
-                    let key_str = bs58::encode(&acc.pubkey).into_string();
-                    pools.iter().find(|p| p.quote_vault.as_ref().map(|v| v == &key_str).unwrap_or(false) || p.pool_id == key_str)
-                } else { None }
-            }
-            helius_laserstream::grpc::subscribe_update::UpdateOneof::Transaction(tx_upd) => {
-                if let Some(tx_info) = &tx_upd.transaction {
-                    if let Some(tx) = &tx_info.transaction {
-                        let accounts: HashSet<_> = tx.message.as_ref().unwrap().account_keys.iter().map(|k| bs58::encode(k).into_string()).collect();
-                        pools.iter().find(|p| accounts.contains(&p.pool_id))
-                    } else { None }
-                } else { None }
-            }
-            _ => None,
-        }
-    } else { None }
-} 
\ No newline at end of file
@@ -0,0 +1,98 @@
+//! SOL/USD price used to attach USD values to swaps.
+//!
+//! * `SOL_USD_PRICE` – fixed price (tests, dev).
+//! * `SOL_USD_PRICE_URL` – JSON endpoint polled every `SOL_USD_PRICE_INTERVAL_SECS`
+//!   (default 30); the price is read at `SOL_USD_PRICE_POINTER` (a JSON pointer, default
+//!   `/solana/usd`, which matches CoinGecko's `simple/price?ids=solana&vs_currencies=usd`).
+//! * `SOL_USD_PRICE_MAX_AGE_SECS` – a polled price older than this is not used (default 300).
+//!
+//! With neither set, swaps are published without a USD value.
+
+use anyhow::{Context, Result};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, Default)]
+pub struct SolUsdPrice {
+    inner: Arc<RwLock<Option<(f64, Instant)>>>,
+    max_age: Option<Duration>,
+}
+
+impl SolUsdPrice {
+    /// Build from the environment, spawning the poller if a URL is configured.
+    pub fn from_env() -> Result<Self> {
+        if let Ok(v) = std::env::var("SOL_USD_PRICE") {
+            let price: f64 = v.parse().context("SOL_USD_PRICE")?;
+            return Ok(Self {
+                inner: Arc::new(RwLock::new(Some((price, Instant::now())))),
+                max_age: None,
+            });
+        }
+
+        let Ok(url) = std::env::var("SOL_USD_PRICE_URL") else {
+            return Ok(Self::default());
+        };
+        let pointer =
+            std::env::var("SOL_USD_PRICE_POINTER").unwrap_or_else(|_| "/solana/usd".to_string());
+        let interval = Duration::from_secs(env_u64("SOL_USD_PRICE_INTERVAL_SECS")?.unwrap_or(30));
+        let max_age = Duration::from_secs(env_u64("SOL_USD_PRICE_MAX_AGE_SECS")?.unwrap_or(300));
+
+        let price = Self {
+            inner: Arc::default(),
+            max_age: Some(max_age),
+        };
+        tokio::spawn(poll(price.clone(), url, pointer, interval));
+        Ok(price)
+    }
+
+    /// Latest price, unless it has gone stale.
+    pub fn get(&self) -> Option<f64> {
+        let (price, at) = (*self.inner.read().unwrap())?;
+        match self.max_age {
+            Some(max_age) if at.elapsed() > max_age => None,
+            _ => Some(price),
+        }
+    }
+
+    fn set(&self, price: f64) {
+        *self.inner.write().unwrap() = Some((price, Instant::now()));
+    }
+}
+
+async fn poll(price: SolUsdPrice, url: String, pointer: String, interval: Duration) {
+    let client = reqwest::Client::new();
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match fetch(&client, &url, &pointer).await {
+            Ok(value) => price.set(value),
+            Err(e) => tracing::warn!(error = %e, "Failed to refresh SOL/USD price"),
+        }
+    }
+}
+
+async fn fetch(client: &reqwest::Client, url: &str, pointer: &str) -> Result<f64> {
+    let body: serde_json::Value = client
+        .get(url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let value = body
+        .pointer(pointer)
+        .with_context(|| format!("no value at {}", pointer))?;
+    // Some APIs return prices as strings.
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+        .with_context(|| format!("value at {} is not a number", pointer))
+}
+
+fn env_u64(name: &str) -> Result<Option<u64>> {
+    match std::env::var(name) {
+        Ok(v) => Ok(Some(v.parse().with_context(|| name.to_string())?)),
+        Err(_) => Ok(None),
+    }
+}
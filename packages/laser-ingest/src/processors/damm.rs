@@ -1,14 +1,11 @@
-use async_trait::async_trait;
 use helius_laserstream::grpc::{SubscribeUpdate, SubscribeRequestFilterTransactions};
-use redis::aio::MultiplexedConnection as RedisConn;
 use std::collections::HashMap;
 
-use super::{FilterContribution, Processor};
+use super::{events::StreamedEvent, FilterContribution, Processor};
 use crate::types::PoolMeta;
 
 pub struct DammProcessor;
 
-#[async_trait]
 impl Processor for DammProcessor {
     fn build_filters(&self, pool: &PoolMeta) -> FilterContribution {
         // For DAMM pools we may watch the damm pool_id only.
@@ -23,7 +20,8 @@ impl Processor for DammProcessor {
         FilterContribution { accounts: HashMap::new(), transactions: txs }
     }
 
-    async fn handle_update(&self, _pool: &PoolMeta, _update: &SubscribeUpdate, _conn: &mut RedisConn) {
+    fn handle_update(&self, _pool: &PoolMeta, _update: &SubscribeUpdate) -> Vec<StreamedEvent> {
         // TODO: implement DAMM parsing
+        Vec::new()
     }
 } 
\ No newline at end of file
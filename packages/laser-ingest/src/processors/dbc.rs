@@ -1,85 +1,851 @@
-use super::{FilterContribution, Processor};
+use super::{account_keys, FilterContribution, Processor};
 use crate::{
+    backfill::is_backfill,
+    idl::{
+        generated::dynamic_bonding_curve::{
+            errors, instructions, EvtClaimCreatorTradingFee, EvtClaimProtocolFee, EvtClaimTradingFee,
+            EvtCreateConfig, EvtCreateDammV2MigrationMetadata, EvtCreateMeteoraMigrationMetadata,
+            EvtCreatorWithdrawSurplus, EvtCurveComplete, EvtInitializePool,
+            EvtPartnerWithdrawSurplus, EvtProtocolWithdrawSurplus, EvtSwap, EvtUpdatePoolCreator,
+            EvtWithdrawLeftover, EvtWithdrawMigrationFee, InitializePoolParameters, PoolConfig,
+            SwapParameters, VirtualPool,
+            PROGRAM_ID as DBC_PROGRAM_ID,
+        },
+        IdlDecoder, ANCHOR_CPI_LOG_DISCRIMINATOR,
+    },
     processors::events::{
-        DbcBalanceUpdatePayload, DbcSwapPayload, PoolEvent, StreamedEvent,
+        AccountVersion, DbcBalanceUpdatePayload, DbcConfigCreatedPayload, DbcConfigState,
+        DbcCurvePoint, DbcFeeConfig, DbcPoolStatePayload,
+        DbcCurveCompletePayload, DbcFeeClaimedPayload, DbcFeeClaimer,
+        DbcLeftoverWithdrawnPayload, DbcMigratedPayload, DbcMigrationFeeWithdrawnPayload,
+        DbcMigrationMetadataPayload, DbcMigrationTarget, DbcPoolCreatorUpdatedPayload,
+        DbcPoolInitializedPayload, DbcSurplusWithdrawnPayload, DbcSwapPayload, EventPosition,
+        FailedSwapAttemptPayload, NamedAccount, PoolEvent, PoolInstructionPayload, SwapSide,
+        StreamedEvent,
     },
+    price::SolUsdPrice,
+    tokens::{TokenCache, TokenInfo, WSOL_MINT},
+    tx_error::TxError,
     types::PoolMeta,
 };
-use async_trait::async_trait;
 use borsh::BorshDeserialize;
 use helius_laserstream::grpc::{
-    subscribe_update::UpdateOneof, SubscribeUpdate, Transaction, TransactionStatusMeta,
+    subscribe_update::UpdateOneof, SubscribeUpdate, TokenBalance, Transaction,
+    TransactionStatusMeta,
     SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions,
 };
-use redis::aio::MultiplexedConnection as RedisConn;
 use std::collections::HashMap;
+use std::sync::Mutex;
 
-// TODO: fetch from config
-const DBC_PROGRAM_ID: &str = "dbcij3LWUppWqq96dh6gJWwBifmcGfLSB5D4DuSMaqN";
-const ANCHOR_CPI_LOG_DISCRIMINATOR: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
-
-// generated by IDL build step of DBC program (copied from examples)
-mod idl {
-    use super::*;
-    include!(concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/../laser-ingest/examples/idl.rs"
-    ));
+/// Processor for Meteora Dynamic Bonding Curve pools.
+///
+/// Keeps the last decoded pool and config account per address so every `DbcPoolState`
+/// carries both, whichever of the two accounts changed, and the base/quote mints of each
+/// pool so amounts can be converted with the right decimals.
+///
+/// Failed transactions are not subscribed to unless `publish_failed_swaps` is set, in which
+/// case their swap instructions are published as `FailedSwapAttempt`s.
+pub struct DbcProcessor {
+    tokens: TokenCache,
+    sol_usd: SolUsdPrice,
+    publish_failed_swaps: bool,
+    /// Runtime decoder for instruction arguments and account names.
+    idl: IdlDecoder,
+    pools: Mutex<HashMap<String, VirtualPool>>,
+    configs: Mutex<HashMap<String, DbcConfigState>>,
+    mints: Mutex<HashMap<String, PoolMints>>,
 }
-use idl::{EvtSwap, SWAP_EVENT_DISCRIMINATOR};
 
-pub struct DbcProcessor;
+#[derive(Clone, Debug, Default)]
+struct PoolMints {
+    base: Option<String>,
+    quote: Option<String>,
+}
 
 impl DbcProcessor {
-    fn find_swap_event(
+    pub fn new(tokens: TokenCache, sol_usd: SolUsdPrice, publish_failed_swaps: bool) -> Self {
+        Self {
+            tokens,
+            sol_usd,
+            publish_failed_swaps,
+            idl: IdlDecoder::from_json(DBC_IDL).expect("bundled DBC IDL is valid"),
+            pools: Mutex::default(),
+            configs: Mutex::default(),
+            mints: Mutex::default(),
+        }
+    }
+
+    /// Base and quote mint of `pool`, from swap/creation instructions or account state.
+    fn pool_mints(&self, pool: &str) -> PoolMints {
+        let mut mints = self.mints.lock().unwrap().get(pool).cloned().unwrap_or_default();
+        if mints.base.is_none() || mints.quote.is_none() {
+            if let Some(state) = self.pools.lock().unwrap().get(pool) {
+                mints.base.get_or_insert_with(|| pubkey(state.base_mint));
+                if mints.quote.is_none() {
+                    mints.quote = self
+                        .configs
+                        .lock()
+                        .unwrap()
+                        .get(&pubkey(state.config))
+                        .map(|c| c.quote_mint.clone());
+                }
+            }
+        }
+        mints
+    }
+
+    fn ui_amount(&self, mint: Option<&String>, raw: u64) -> Option<f64> {
+        self.tokens.ui_amount(mint?, raw)
+    }
+
+    /// Learn pool mints (and token names) from DBC swap and pool-creation instructions.
+    fn learn_from_instruction(&self, data: &[u8], accounts: &[u8], account_keys: &[&Vec<u8>]) {
+        let key = |i: usize| {
+            let idx = *accounts.get(i)? as usize;
+            account_keys.get(idx).map(|k| bs58::encode(k).into_string())
+        };
+        // (pool, base_mint, quote_mint) account positions
+        let (pool_idx, base_idx, quote_idx) = if data.starts_with(&instructions::SWAP) {
+            (2, 7, 8)
+        } else if data.starts_with(&instructions::INITIALIZE_VIRTUAL_POOL_WITH_SPL_TOKEN)
+            || data.starts_with(&instructions::INITIALIZE_VIRTUAL_POOL_WITH_TOKEN2022)
+        {
+            if let (Some(base_mint), Some(params)) =
+                (key(3), parse::<InitializePoolParameters>(&data[8..]))
+            {
+                self.tokens.learn(TokenInfo {
+                    mint: base_mint,
+                    decimals: None,
+                    symbol: Some(params.symbol).filter(|s| !s.is_empty()),
+                    name: Some(params.name).filter(|s| !s.is_empty()),
+                });
+            }
+            (5, 3, 4)
+        } else {
+            return;
+        };
+        if let (Some(pool), Some(base), Some(quote)) = (key(pool_idx), key(base_idx), key(quote_idx)) {
+            self.mints.lock().unwrap().insert(
+                pool,
+                PoolMints {
+                    base: Some(base),
+                    quote: Some(quote),
+                },
+            );
+        }
+    }
+
+    /// Decode a DBC instruction into a `PoolInstruction`, owned by its virtual pool (or, for
+    /// config creation, its config).
+    fn decode_instruction(
+        &self,
+        signature: &str,
+        position: EventPosition,
+        data: &[u8],
+        accounts: &[u8],
+        account_keys: &[&Vec<u8>],
+    ) -> Option<(String, PoolEvent)> {
+        let resolved: Vec<Vec<u8>> = accounts
+            .iter()
+            .filter_map(|&i| account_keys.get(i as usize).map(|k| (*k).clone()))
+            .collect();
+        let ix = self.idl.decode_instruction(data, &resolved)?;
+        // Migrations name the virtual pool `virtual_pool` and the new DAMM pool `pool`.
+        let owner = ["virtual_pool", "pool", "config"].iter().find_map(|wanted| {
+            ix.accounts
+                .iter()
+                .find(|(name, _)| name == wanted)
+                .map(|(_, pubkey)| pubkey.clone())
+        })?;
+        Some((
+            owner,
+            PoolEvent::PoolInstruction(PoolInstructionPayload {
+                signature: signature.to_owned(),
+                position,
+                name: ix.name,
+                args: ix.args,
+                accounts: ix
+                    .accounts
+                    .into_iter()
+                    .map(|(name, pubkey)| NamedAccount { name, pubkey })
+                    .collect(),
+            }),
+        ))
+    }
+
+    /// Decode every DBC instruction, event and migration in the transaction, in execution
+    /// order, each paired with the pool (or, for config creation, the config) it belongs to.
+    fn find_events(
+        &self,
+        signature: &str,
         tx: &Transaction,
         meta: &TransactionStatusMeta,
-    ) -> Option<EvtSwap> {
-        let account_keys = tx.message.as_ref()?.account_keys.as_slice();
-        let inner_instructions = meta.inner_instructions.as_slice();
-        let dbc_program_bytes = bs58::decode(DBC_PROGRAM_ID).into_vec().ok()?;
-
-        for inner_ix_list in inner_instructions {
-            for ix in &inner_ix_list.instructions {
-                let program_id_index = ix.program_id_index as usize;
-                if let Some(program_id_bytes) = account_keys.get(program_id_index) {
-                    if *program_id_bytes == dbc_program_bytes {
-                        if ix.data.starts_with(&ANCHOR_CPI_LOG_DISCRIMINATOR) {
-                            let event_with_disc = &ix.data[8..];
-                            if event_with_disc.starts_with(&SWAP_EVENT_DISCRIMINATOR) {
-                                let event_data = &event_with_disc[8..];
-                                if let Ok(evt) = EvtSwap::try_from_slice(event_data) {
-                                    return Some(evt);
-                                }
+    ) -> Vec<(String, PoolEvent)> {
+        let mut events = Vec::new();
+        let Some(message) = tx.message.as_ref() else {
+            return events;
+        };
+        let Ok(dbc_program_bytes) = bs58::decode(DBC_PROGRAM_ID).into_vec() else {
+            return events;
+        };
+        let account_keys = account_keys(message, meta);
+        let is_dbc = |program_id_index: u32| {
+            account_keys
+                .get(program_id_index as usize)
+                .is_some_and(|k| **k == dbc_program_bytes)
+        };
+        // The swap's `payer` signs for the input token account; an `EvtSwap` is attributed
+        // to the last swap instruction before it, falling back to the fee payer.
+        let fee_payer = account_keys.first().map(|k| bs58::encode(k).into_string());
+        let mut swap_payer: Option<String> = None;
+        let payer_of = |data: &[u8], accounts: &[u8]| {
+            if !data.starts_with(&instructions::SWAP) {
+                return None;
+            }
+            accounts
+                .get(SWAP_PAYER_ACCOUNT)
+                .and_then(|&idx| account_keys.get(idx as usize))
+                .map(|k| bs58::encode(k).into_string())
+        };
+
+        // Walk instructions in execution order: each top-level instruction, then its CPIs.
+        // Migrations are plain instructions (usually top-level); the program emits no event.
+        for (index, ix) in message.instructions.iter().enumerate() {
+            let index = index as u32;
+            if is_dbc(ix.program_id_index) {
+                self.learn_from_instruction(&ix.data, &ix.accounts, &account_keys);
+                if let Some(payer) = payer_of(&ix.data, &ix.accounts) {
+                    swap_payer = Some(payer);
+                }
+                let position = EventPosition {
+                    instruction_index: index,
+                    inner_index: None,
+                };
+                events.extend(self.decode_instruction(
+                    signature,
+                    position,
+                    &ix.data,
+                    &ix.accounts,
+                    &account_keys,
+                ));
+                events.extend(decode_migration(
+                    signature,
+                    position,
+                    &ix.data,
+                    &ix.accounts,
+                    &account_keys,
+                ));
+            }
+
+            let inner = meta.inner_instructions.iter().filter(|list| list.index == index);
+            for inner_ix_list in inner {
+                for (inner_index, ix) in inner_ix_list.instructions.iter().enumerate() {
+                    if !is_dbc(ix.program_id_index) {
+                        continue;
+                    }
+                    let position = EventPosition {
+                        instruction_index: index,
+                        inner_index: Some(inner_index as u32),
+                    };
+                    if let Some(event_with_disc) =
+                        ix.data.strip_prefix(&ANCHOR_CPI_LOG_DISCRIMINATOR)
+                    {
+                        let Some((owner, mut event)) =
+                            self.decode_event(signature, position, event_with_disc)
+                        else {
+                            continue;
+                        };
+                        if let PoolEvent::DbcSwap(swap) = &mut event {
+                            if let Some(trader) = swap_payer.as_ref().or(fee_payer.as_ref()) {
+                                attribute_trader(swap, trader, meta, &account_keys);
                             }
                         }
+                        events.push((owner, event));
+                    } else {
+                        self.learn_from_instruction(&ix.data, &ix.accounts, &account_keys);
+                        if let Some(payer) = payer_of(&ix.data, &ix.accounts) {
+                            swap_payer = Some(payer);
+                        }
+                        events.extend(self.decode_instruction(
+                            signature,
+                            position,
+                            &ix.data,
+                            &ix.accounts,
+                            &account_keys,
+                        ));
+                        events.extend(decode_migration(
+                            signature,
+                            position,
+                            &ix.data,
+                            &ix.accounts,
+                            &account_keys,
+                        ));
                     }
                 }
             }
         }
-        None
+        events
+    }
+
+    /// Every DBC swap instruction in a failed transaction, with the decoded error. Nothing
+    /// the program emitted is kept when a transaction fails, so only the instructions are left.
+    fn find_failed_swaps(
+        &self,
+        signature: &str,
+        tx: &Transaction,
+        meta: &TransactionStatusMeta,
+        err: &TxError,
+    ) -> Vec<(String, PoolEvent)> {
+        let mut attempts = Vec::new();
+        let Some(message) = tx.message.as_ref() else {
+            return attempts;
+        };
+        let Ok(dbc_program_bytes) = bs58::decode(DBC_PROGRAM_ID).into_vec() else {
+            return attempts;
+        };
+        let account_keys = account_keys(message, meta);
+        // Custom codes are program specific; only name the ones the DBC program raised.
+        let dbc_failed = format!("Program {} failed: custom program error", DBC_PROGRAM_ID);
+        let error_name = err
+            .custom_code
+            .filter(|_| meta.log_messages.iter().any(|l| l.starts_with(&dbc_failed)))
+            .and_then(errors::name)
+            .map(str::to_owned);
+
+        let top_level = message
+            .instructions
+            .iter()
+            .enumerate()
+            .map(|(index, ix)| (index as u32, None, ix.program_id_index, &ix.accounts, &ix.data));
+        let inner = meta.inner_instructions.iter().flat_map(|list| {
+            list.instructions.iter().enumerate().map(|(inner_index, ix)| {
+                (
+                    list.index,
+                    Some(inner_index as u32),
+                    ix.program_id_index,
+                    &ix.accounts,
+                    &ix.data,
+                )
+            })
+        });
+        for (instruction_index, inner_index, program_idx, accounts, data) in top_level.chain(inner)
+        {
+            let is_dbc = account_keys
+                .get(program_idx as usize)
+                .is_some_and(|k| **k == dbc_program_bytes);
+            if !is_dbc || !data.starts_with(&instructions::SWAP) {
+                continue;
+            }
+            let Some(params) = parse::<SwapParameters>(&data[8..]) else {
+                continue;
+            };
+            let key = |i: usize| {
+                let idx = *accounts.get(i)? as usize;
+                account_keys.get(idx).map(|k| bs58::encode(k).into_string())
+            };
+            let Some(pool) = key(2) else {
+                continue;
+            };
+            // Direction follows from which mint the input token account holds.
+            let input_mint = accounts.get(3).and_then(|&idx| {
+                meta.pre_token_balances
+                    .iter()
+                    .find(|b| b.account_index == idx as u32)
+                    .map(|b| b.mint.clone())
+            });
+            let side = match (input_mint, key(7), key(8)) {
+                (Some(input), Some(base), _) if input == base => Some(SwapSide::Sell),
+                (Some(input), _, Some(quote)) if input == quote => Some(SwapSide::Buy),
+                _ => None,
+            };
+            attempts.push((
+                pool.clone(),
+                PoolEvent::FailedSwapAttempt(FailedSwapAttemptPayload {
+                    signature: signature.to_owned(),
+                    position: EventPosition {
+                        instruction_index,
+                        inner_index,
+                    },
+                    pool,
+                    trader: key(SWAP_PAYER_ACCOUNT),
+                    side,
+                    amount_in: params.amount_in,
+                    minimum_amount_out: params.minimum_amount_out,
+                    error: err.clone(),
+                    error_name: error_name.clone(),
+                }),
+            ));
+        }
+        attempts
+    }
+
+    /// Decode a DBC `emit_cpi!` payload (`<event discriminator><borsh data>`).
+    fn decode_event(
+        &self,
+        signature: &str,
+        position: EventPosition,
+        data: &[u8],
+    ) -> Option<(String, PoolEvent)> {
+        if data.len() < 8 {
+            return None;
+        }
+        let (disc, body) = data.split_at(8);
+        let signature = signature.to_owned();
+
+        let decoded = if disc == EvtSwap::DISCRIMINATOR {
+            let evt: EvtSwap = parse(body)?;
+            let pool = pubkey(evt.pool);
+            let mints = self.pool_mints(&pool);
+            let side = if evt.trade_direction == TRADE_DIRECTION_BASE_TO_QUOTE {
+                SwapSide::Sell
+            } else {
+                SwapSide::Buy
+            };
+            let (input_mint, output_mint) = match side {
+                SwapSide::Sell => (mints.base.as_ref(), mints.quote.as_ref()),
+                SwapSide::Buy => (mints.quote.as_ref(), mints.base.as_ref()),
+            };
+            let input_amount = evt.swap_result.actual_input_amount;
+            let output_amount = evt.swap_result.output_amount;
+            let input_amount_ui = self.ui_amount(input_mint, input_amount);
+            let output_amount_ui = self.ui_amount(output_mint, output_amount);
+            let (base_ui, quote_ui) = match side {
+                SwapSide::Sell => (input_amount_ui, output_amount_ui),
+                SwapSide::Buy => (output_amount_ui, input_amount_ui),
+            };
+            let price = match (base_ui, quote_ui) {
+                (Some(base), Some(quote)) if base > 0.0 => Some(quote / base),
+                _ => None,
+            };
+            let spot_price = match (
+                mints.base.as_deref().and_then(|m| self.tokens.decimals(m)),
+                mints.quote.as_deref().and_then(|m| self.tokens.decimals(m)),
+            ) {
+                (Some(base_decimals), Some(quote_decimals)) => Some(spot_price_from_sqrt(
+                    evt.swap_result.next_sqrt_price,
+                    base_decimals,
+                    quote_decimals,
+                )),
+                _ => None,
+            };
+            let usd_value = match (mints.quote.as_deref(), quote_ui, self.sol_usd.get()) {
+                (Some(WSOL_MINT), Some(quote), Some(sol_usd)) => Some(quote * sol_usd),
+                _ => None,
+            };
+            (
+                pool,
+                PoolEvent::DbcSwap(DbcSwapPayload {
+                    signature,
+                    position,
+                    side,
+                    base_mint: mints.base.clone(),
+                    quote_mint: mints.quote.clone(),
+                    input_amount,
+                    output_amount,
+                    input_amount_ui,
+                    output_amount_ui,
+                    price,
+                    next_sqrt_price: evt.swap_result.next_sqrt_price,
+                    spot_price,
+                    trading_fee: evt.swap_result.trading_fee,
+                    protocol_fee: evt.swap_result.protocol_fee,
+                    referral_fee: evt.swap_result.referral_fee,
+                    block_time: evt.current_timestamp,
+                    usd_value,
+                    trader: None,
+                    trader_base_delta: None,
+                    trader_quote_delta: None,
+                }),
+            )
+        } else if disc == EvtInitializePool::DISCRIMINATOR {
+            let evt: EvtInitializePool = parse(body)?;
+            (
+                pubkey(evt.pool),
+                PoolEvent::DbcPoolInitialized(DbcPoolInitializedPayload {
+                    signature,
+                    position,
+                    pool: pubkey(evt.pool),
+                    config: pubkey(evt.config),
+                    creator: pubkey(evt.creator),
+                    base_mint: pubkey(evt.base_mint),
+                    pool_type: evt.pool_type,
+                    activation_point: evt.activation_point,
+                }),
+            )
+        } else if disc == EvtCreateConfig::DISCRIMINATOR {
+            let evt: EvtCreateConfig = parse(body)?;
+            (
+                pubkey(evt.config),
+                PoolEvent::DbcConfigCreated(DbcConfigCreatedPayload {
+                    signature,
+                    position,
+                    config: pubkey(evt.config),
+                    quote_mint: pubkey(evt.quote_mint),
+                    fee_claimer: pubkey(evt.fee_claimer),
+                    owner: pubkey(evt.owner),
+                    collect_fee_mode: evt.collect_fee_mode,
+                    migration_option: evt.migration_option,
+                    activation_type: evt.activation_type,
+                    token_decimal: evt.token_decimal,
+                    token_type: evt.token_type,
+                    swap_base_amount: evt.swap_base_amount,
+                    migration_quote_threshold: evt.migration_quote_threshold,
+                    migration_base_amount: evt.migration_base_amount,
+                    sqrt_start_price: evt.sqrt_start_price,
+                }),
+            )
+        } else if disc == EvtCurveComplete::DISCRIMINATOR {
+            let evt: EvtCurveComplete = parse(body)?;
+            let mints = self.pool_mints(&pubkey(evt.pool));
+            (
+                pubkey(evt.pool),
+                PoolEvent::DbcCurveComplete(DbcCurveCompletePayload {
+                    signature,
+                    position,
+                    pool: pubkey(evt.pool),
+                    config: pubkey(evt.config),
+                    base_reserve: evt.base_reserve,
+                    quote_reserve: evt.quote_reserve,
+                    base_reserve_ui: self.ui_amount(mints.base.as_ref(), evt.base_reserve),
+                    quote_reserve_ui: self.ui_amount(mints.quote.as_ref(), evt.quote_reserve),
+                }),
+            )
+        } else if disc == EvtCreateDammV2MigrationMetadata::DISCRIMINATOR
+            || disc == EvtCreateMeteoraMigrationMetadata::DISCRIMINATOR
+        {
+            // Both events carry only the virtual pool.
+            let evt: EvtCreateDammV2MigrationMetadata = parse(body)?;
+            let target = if disc == EvtCreateDammV2MigrationMetadata::DISCRIMINATOR {
+                DbcMigrationTarget::DammV2
+            } else {
+                DbcMigrationTarget::Damm
+            };
+            (
+                pubkey(evt.virtual_pool),
+                PoolEvent::DbcMigrationMetadata(DbcMigrationMetadataPayload {
+                    signature,
+                    position,
+                    pool: pubkey(evt.virtual_pool),
+                    target,
+                }),
+            )
+        } else if disc == EvtClaimProtocolFee::DISCRIMINATOR
+            || disc == EvtClaimTradingFee::DISCRIMINATOR
+            || disc == EvtClaimCreatorTradingFee::DISCRIMINATOR
+        {
+            // All three fee claim events share one layout.
+            let evt: EvtClaimTradingFee = parse(body)?;
+            let claimer = if disc == EvtClaimProtocolFee::DISCRIMINATOR {
+                DbcFeeClaimer::Protocol
+            } else if disc == EvtClaimTradingFee::DISCRIMINATOR {
+                DbcFeeClaimer::Partner
+            } else {
+                DbcFeeClaimer::Creator
+            };
+            let mints = self.pool_mints(&pubkey(evt.pool));
+            (
+                pubkey(evt.pool),
+                PoolEvent::DbcFeeClaimed(DbcFeeClaimedPayload {
+                    signature,
+                    position,
+                    pool: pubkey(evt.pool),
+                    claimer,
+                    token_base_amount: evt.token_base_amount,
+                    token_quote_amount: evt.token_quote_amount,
+                    token_base_amount_ui: self.ui_amount(mints.base.as_ref(), evt.token_base_amount),
+                    token_quote_amount_ui: self
+                        .ui_amount(mints.quote.as_ref(), evt.token_quote_amount),
+                }),
+            )
+        } else if disc == EvtPartnerWithdrawSurplus::DISCRIMINATOR
+            || disc == EvtCreatorWithdrawSurplus::DISCRIMINATOR
+            || disc == EvtProtocolWithdrawSurplus::DISCRIMINATOR
+        {
+            let evt: EvtPartnerWithdrawSurplus = parse(body)?;
+            let claimer = if disc == EvtPartnerWithdrawSurplus::DISCRIMINATOR {
+                DbcFeeClaimer::Partner
+            } else if disc == EvtCreatorWithdrawSurplus::DISCRIMINATOR {
+                DbcFeeClaimer::Creator
+            } else {
+                DbcFeeClaimer::Protocol
+            };
+            (
+                pubkey(evt.pool),
+                PoolEvent::DbcSurplusWithdrawn(DbcSurplusWithdrawnPayload {
+                    signature,
+                    position,
+                    pool: pubkey(evt.pool),
+                    claimer,
+                    surplus_amount: evt.surplus_amount,
+                }),
+            )
+        } else if disc == EvtWithdrawLeftover::DISCRIMINATOR {
+            let evt: EvtWithdrawLeftover = parse(body)?;
+            (
+                pubkey(evt.pool),
+                PoolEvent::DbcLeftoverWithdrawn(DbcLeftoverWithdrawnPayload {
+                    signature,
+                    position,
+                    pool: pubkey(evt.pool),
+                    leftover_receiver: pubkey(evt.leftover_receiver),
+                    leftover_amount: evt.leftover_amount,
+                }),
+            )
+        } else if disc == EvtWithdrawMigrationFee::DISCRIMINATOR {
+            let evt: EvtWithdrawMigrationFee = parse(body)?;
+            (
+                pubkey(evt.pool),
+                PoolEvent::DbcMigrationFeeWithdrawn(DbcMigrationFeeWithdrawnPayload {
+                    signature,
+                    position,
+                    pool: pubkey(evt.pool),
+                    fee: evt.fee,
+                    flag: evt.flag,
+                }),
+            )
+        } else if disc == EvtUpdatePoolCreator::DISCRIMINATOR {
+            let evt: EvtUpdatePoolCreator = parse(body)?;
+            (
+                pubkey(evt.pool),
+                PoolEvent::DbcPoolCreatorUpdated(DbcPoolCreatorUpdatedPayload {
+                    signature,
+                    position,
+                    pool: pubkey(evt.pool),
+                    creator: pubkey(evt.creator),
+                    new_creator: pubkey(evt.new_creator),
+                }),
+            )
+        } else {
+            return None;
+        };
+        Some(decoded)
     }
+
+    /// Current state of `pool_address`, if its account has been received.
+    fn pool_state(
+        &self,
+        pool_address: &str,
+        version: AccountVersion,
+    ) -> Option<DbcPoolStatePayload> {
+        let pools = self.pools.lock().unwrap();
+        let state = pools.get(pool_address)?;
+        let config_address = pubkey(state.config);
+        let config_state = self.configs.lock().unwrap().get(&config_address).cloned();
+        let base_mint = pubkey(state.base_mint);
+        let quote_mint = config_state.as_ref().map(|c| c.quote_mint.clone());
+        let curve_progress = config_state
+            .as_ref()
+            .filter(|c| c.migration_quote_threshold > 0)
+            .map(|c| state.quote_reserve as f64 / c.migration_quote_threshold as f64);
+
+        Some(DbcPoolStatePayload {
+            version,
+            pool: pool_address.to_owned(),
+            config: config_address,
+            creator: pubkey(state.creator),
+            base_vault: pubkey(state.base_vault),
+            quote_vault: pubkey(state.quote_vault),
+            base_reserve: state.base_reserve,
+            quote_reserve: state.quote_reserve,
+            base_reserve_ui: self.tokens.ui_amount(&base_mint, state.base_reserve),
+            quote_reserve_ui: self.ui_amount(quote_mint.as_ref(), state.quote_reserve),
+            base_token: self.tokens.get(&base_mint),
+            quote_token: quote_mint.as_deref().and_then(|m| self.tokens.get(m)),
+            base_mint,
+            sqrt_price: state.sqrt_price,
+            activation_point: state.activation_point,
+            is_migrated: state.is_migrated != 0,
+            migration_progress: state.migration_progress,
+            curve_progress,
+            finish_curve_timestamp: state.finish_curve_timestamp,
+            partner_base_fee: state.partner_base_fee,
+            partner_quote_fee: state.partner_quote_fee,
+            creator_base_fee: state.creator_base_fee,
+            creator_quote_fee: state.creator_quote_fee,
+            protocol_base_fee: state.protocol_base_fee,
+            protocol_quote_fee: state.protocol_quote_fee,
+            config_state,
+        })
+    }
+
+    fn pool_state_event(&self, pool: &PoolMeta, version: AccountVersion) -> Option<StreamedEvent> {
+        let state = self.pool_state(&pool.pool_id, version)?;
+        Some(StreamedEvent {
+            pool_id: pool.pool_id.clone(),
+            variant: "dbc",
+            backfilled: false,
+            event: PoolEvent::DbcPoolState(Box::new(state)),
+        })
+    }
+}
+
+fn config_state(config: &PoolConfig) -> DbcConfigState {
+    let fees = &config.pool_fees;
+    DbcConfigState {
+        quote_mint: pubkey(config.quote_mint),
+        token_decimal: config.token_decimal,
+        collect_fee_mode: config.collect_fee_mode,
+        migration_option: config.migration_option,
+        activation_type: config.activation_type,
+        swap_base_amount: config.swap_base_amount,
+        migration_quote_threshold: config.migration_quote_threshold,
+        migration_base_threshold: config.migration_base_threshold,
+        migration_sqrt_price: config.migration_sqrt_price,
+        sqrt_start_price: config.sqrt_start_price,
+        fees: DbcFeeConfig {
+            cliff_fee_numerator: fees.base_fee.cliff_fee_numerator,
+            base_fee_mode: fees.base_fee.base_fee_mode,
+            first_factor: fees.base_fee.first_factor,
+            second_factor: fees.base_fee.second_factor,
+            third_factor: fees.base_fee.third_factor,
+            dynamic_fee_enabled: fees.dynamic_fee.initialized != 0,
+            protocol_fee_percent: fees.protocol_fee_percent,
+            referral_fee_percent: fees.referral_fee_percent,
+            creator_trading_fee_percentage: config.creator_trading_fee_percentage,
+        },
+        curve: config
+            .curve
+            .iter()
+            .take_while(|p| p.sqrt_price != 0)
+            .map(|p| DbcCurvePoint {
+                sqrt_price: p.sqrt_price,
+                liquidity: p.liquidity,
+            })
+            .collect(),
+    }
+}
+
+/// Decode an Anchor account (`<account discriminator><data>`).
+fn parse_account<T: BorshDeserialize>(data: &[u8], discriminator: [u8; 8]) -> Option<T> {
+    parse(data.strip_prefix(&discriminator)?)
 }
 
-async fn publish_event(
-    event: StreamedEvent<'_>,
-    conn: &mut RedisConn,
-) -> redis::RedisResult<()> {
-    let json = serde_json::to_string(&event).unwrap();
-    tracing::info!(event = ?event, "Publishing pool event to Redis");
-    redis::cmd("XADD")
-        .arg("heimdall:pool_events")
-        .arg("*")
-        .arg("data")
-        .arg(json)
-        .query_async(conn)
-        .await
+fn pubkey(bytes: [u8; 32]) -> String {
+    bs58::encode(bytes).into_string()
+}
+
+fn parse<T: BorshDeserialize>(mut data: &[u8]) -> Option<T> {
+    T::deserialize(&mut data).ok()
+}
+
+/// Quote-per-base price in UI units from a Q64.64 `sqrt_price`.
+fn spot_price_from_sqrt(sqrt_price: u128, base_decimals: u8, quote_decimals: u8) -> f64 {
+    let sqrt = sqrt_price as f64 / 2f64.powi(64);
+    sqrt * sqrt * 10f64.powi(base_decimals as i32 - quote_decimals as i32)
+}
+
+const DBC_IDL: &str = include_str!("../../idls/dynamic_bonding_curve.json");
+
+/// Position of `payer` in the `swap` instruction's accounts.
+const SWAP_PAYER_ACCOUNT: usize = 9;
+
+/// Fill in the trader and their net base/quote change from the transaction's balances.
+fn attribute_trader(
+    swap: &mut DbcSwapPayload,
+    trader: &str,
+    meta: &TransactionStatusMeta,
+    account_keys: &[&Vec<u8>],
+) {
+    swap.trader_base_delta = swap
+        .base_mint
+        .as_deref()
+        .and_then(|mint| trader_delta(trader, mint, meta, account_keys));
+    swap.trader_quote_delta = swap
+        .quote_mint
+        .as_deref()
+        .and_then(|mint| trader_delta(trader, mint, meta, account_keys));
+    swap.trader = Some(trader.to_owned());
+}
+
+/// Net change in `owner`'s holdings of `mint` over the transaction, in base units.
+///
+/// Summed over every token account of `owner` in the pre/post token balances. A wrapped SOL
+/// account opened and closed within the transaction leaves no token balances, so for WSOL
+/// the owner's lamport change (excluding the transaction fee) is used instead.
+fn trader_delta(
+    owner: &str,
+    mint: &str,
+    meta: &TransactionStatusMeta,
+    account_keys: &[&Vec<u8>],
+) -> Option<i128> {
+    let sum = |balances: &[TokenBalance]| -> Option<i128> {
+        let mut total = None;
+        for b in balances.iter().filter(|b| b.owner == owner && b.mint == mint) {
+            let amount: i128 = b.ui_token_amount.as_ref()?.amount.parse().ok()?;
+            *total.get_or_insert(0) += amount;
+        }
+        total
+    };
+    match (sum(&meta.pre_token_balances), sum(&meta.post_token_balances)) {
+        (None, None) if mint == WSOL_MINT => {
+            let idx = account_keys
+                .iter()
+                .position(|k| bs58::encode(k).into_string() == owner)?;
+            let pre = *meta.pre_balances.get(idx)? as i128;
+            let post = *meta.post_balances.get(idx)? as i128;
+            let fee = if idx == 0 { meta.fee as i128 } else { 0 };
+            Some(post - pre + fee)
+        }
+        (None, None) => None,
+        (pre, post) => Some(post.unwrap_or(0) - pre.unwrap_or(0)),
+    }
+}
+
+/// `TradeDirection::BaseToQuote`; `1` is `QuoteToBase`.
+const TRADE_DIRECTION_BASE_TO_QUOTE: u8 = 0;
+
+/// Both migration instructions take the virtual pool as account 0 and the new DAMM
+/// pool as account 4.
+fn decode_migration(
+    signature: &str,
+    position: EventPosition,
+    data: &[u8],
+    accounts: &[u8],
+    account_keys: &[&Vec<u8>],
+) -> Option<(String, PoolEvent)> {
+    let target = if data.starts_with(&instructions::MIGRATION_DAMM_V2) {
+        DbcMigrationTarget::DammV2
+    } else if data.starts_with(&instructions::MIGRATE_METEORA_DAMM) {
+        DbcMigrationTarget::Damm
+    } else {
+        return None;
+    };
+    let key = |i: usize| {
+        let idx = *accounts.get(i)? as usize;
+        account_keys.get(idx).map(|k| bs58::encode(k).into_string())
+    };
+    let pool = key(0)?;
+    Some((
+        pool.clone(),
+        PoolEvent::DbcMigrated(DbcMigratedPayload {
+            signature: signature.to_owned(),
+            position,
+            pool,
+            target,
+            damm_pool: key(4)?,
+        }),
+    ))
 }
 
-#[async_trait]
 impl Processor for DbcProcessor {
     fn build_filters(&self, pool: &PoolMeta) -> FilterContribution {
         let mut accounts = HashMap::new();
+        accounts.insert(
+            format!("{}_pool_state", pool.pool_id),
+            SubscribeRequestFilterAccounts {
+                account: vec![pool.pool_id.clone()],
+                ..Default::default()
+            },
+        );
+        if let Some(config) = &pool.config_pda {
+            accounts.insert(
+                format!("{}_config", pool.pool_id),
+                SubscribeRequestFilterAccounts {
+                    account: vec![config.clone()],
+                    ..Default::default()
+                },
+            );
+        }
         if let Some(vault) = &pool.quote_vault {
             accounts.insert(
                 format!("{}_quote_vault", pool.pool_id),
@@ -89,6 +855,8 @@ impl Processor for DbcProcessor {
                 },
             );
         }
+        let mints: Vec<&String> = pool.base_mint.iter().chain(&pool.quote_mint).collect();
+        accounts.extend(self.tokens.account_filters(&pool.pool_id, &mints));
 
         let mut txs = HashMap::new();
         txs.insert(
@@ -98,6 +866,8 @@ impl Processor for DbcProcessor {
                     DBC_PROGRAM_ID.to_owned(),
                     pool.pool_id.clone(), // ensure pool-specific txs come through
                 ],
+                // `None` subscribes to both successful and failed transactions.
+                failed: if self.publish_failed_swaps { None } else { Some(false) },
                 ..Default::default()
             },
         );
@@ -107,74 +877,220 @@ impl Processor for DbcProcessor {
         }
     }
 
-    async fn handle_update(
-        &self,
-        pool: &PoolMeta,
-        update: &SubscribeUpdate,
-        conn: &mut RedisConn,
-    ) {
-        if let Some(oneof) = &update.update_oneof {
-            match oneof {
-                UpdateOneof::Transaction(tx_update) => {
-                    if let Some(tx_info) = &tx_update.transaction {
-                        if let (Some(tx), Some(meta)) = (&tx_info.transaction, &tx_info.meta) {
-                            if let Some(evt) = Self::find_swap_event(tx, meta) {
-                                // ensure pool matches
-                                let evt_pool = bs58::encode(evt.pool).into_string();
-                                if evt_pool != pool.pool_id {
-                                    return; // not our pool
-                                }
-
-                                let payload = DbcSwapPayload {
-                                    signature: bs58::encode(&tx_info.signature).into_string(),
-                                    input_amount: evt.swap_result.actual_input_amount,
-                                    output_amount: evt.swap_result.output_amount as u64,
-                                    next_sqrt_price: evt.swap_result.next_sqrt_price,
-                                };
-                                let _ = publish_event(
-                                    StreamedEvent {
-                                        pool_id: &pool.pool_id,
-                                        variant: "dbc",
-                                        event: PoolEvent::DbcSwap(payload),
-                                    },
-                                    conn,
-                                )
-                                .await;
-                            }
-                        }
-                    }
-                }
-                UpdateOneof::Account(acc_update) => {
-                    if let Some(acc) = &acc_update.account {
-                        // quote vault balance update
-                        if let Some(vault) = &pool.quote_vault {
-                            let pubkey_str = bs58::encode(&acc.pubkey).into_string();
-                            if &pubkey_str == vault {
-                                if acc.data.len() >= 72 {
-                                    let bal_raw =
-                                        u64::from_le_bytes(acc.data[64..72].try_into().unwrap());
-                                    let bal_dec = bal_raw as f64 / 10u64.pow(9) as f64; // WSOL 9 decimals
-
-                                    let payload = DbcBalanceUpdatePayload {
-                                        quote_vault_address: vault.clone(),
-                                        new_balance: bal_dec,
-                                    };
-                                    let _ = publish_event(
-                                        StreamedEvent {
-                                            pool_id: &pool.pool_id,
-                                            variant: "dbc",
-                                            event: PoolEvent::DbcBalanceUpdate(payload),
-                                        },
-                                        conn,
-                                    )
-                                    .await;
-                                }
-                            }
-                        }
+    fn handle_update(&self, pool: &PoolMeta, update: &SubscribeUpdate) -> Vec<StreamedEvent> {
+        match &update.update_oneof {
+            Some(UpdateOneof::Transaction(tx_update)) => {
+                let Some(tx_info) = &tx_update.transaction else {
+                    return Vec::new();
+                };
+                let (Some(tx), Some(meta)) = (&tx_info.transaction, &tx_info.meta) else {
+                    return Vec::new();
+                };
+                let signature = bs58::encode(&tx_info.signature).into_string();
+                let events = match &meta.err {
+                    None => self.find_events(&signature, tx, meta),
+                    Some(err) if self.publish_failed_swaps => {
+                        let Some(err) = TxError::decode(&err.err) else {
+                            return Vec::new();
+                        };
+                        self.find_failed_swaps(&signature, tx, meta, &err)
                     }
+                    // A failed transaction changes nothing; any CPI events it
+                    // recorded before failing were rolled back.
+                    Some(_) => return Vec::new(),
+                };
+                events
+                    .into_iter()
+                    // A routed transaction can carry events for several pools; each
+                    // watched pool publishes only its own, when the update is routed to it.
+                    .filter(|(owner, _)| {
+                        *owner == pool.pool_id || pool.config_pda.as_ref() == Some(owner)
+                    })
+                    .map(|(_, event)| StreamedEvent {
+                        pool_id: pool.pool_id.clone(),
+                        variant: "dbc",
+                        backfilled: is_backfill(update),
+                        event,
+                    })
+                    .collect()
+            }
+            Some(UpdateOneof::Account(acc_update)) => {
+                let Some(acc) = &acc_update.account else {
+                    return Vec::new();
+                };
+                let pubkey_str = bs58::encode(&acc.pubkey).into_string();
+                let version = AccountVersion {
+                    slot: acc_update.slot,
+                    write_version: acc.write_version,
+                };
+                if pubkey_str == pool.pool_id {
+                    // virtual pool account
+                    let Some(state) =
+                        parse_account::<VirtualPool>(&acc.data, VirtualPool::DISCRIMINATOR)
+                    else {
+                        tracing::warn!(pool_id = %pool.pool_id, "Failed to decode DBC pool account");
+                        return Vec::new();
+                    };
+                    self.pools.lock().unwrap().insert(pubkey_str, state);
+                    self.pool_state_event(pool, version).into_iter().collect()
+                } else if pool.config_pda.as_ref() == Some(&pubkey_str) {
+                    // pool config account
+                    let Some(config) =
+                        parse_account::<PoolConfig>(&acc.data, PoolConfig::DISCRIMINATOR)
+                    else {
+                        tracing::warn!(pool_id = %pool.pool_id, "Failed to decode DBC config account");
+                        return Vec::new();
+                    };
+                    self.configs
+                        .lock()
+                        .unwrap()
+                        .insert(pubkey_str, config_state(&config));
+                    self.pool_state_event(pool, version).into_iter().collect()
+                } else if pool.quote_vault.as_ref() == Some(&pubkey_str) && acc.data.len() >= 72 {
+                    // quote vault balance update (SPL token account: mint 0..32,
+                    // amount 64..72)
+                    let mint = bs58::encode(&acc.data[0..32]).into_string();
+                    let raw_balance = u64::from_le_bytes(acc.data[64..72].try_into().unwrap());
+
+                    let payload = DbcBalanceUpdatePayload {
+                        version,
+                        quote_vault_address: pubkey_str,
+                        new_balance: self.tokens.ui_amount(&mint, raw_balance),
+                        raw_balance,
+                        mint,
+                    };
+                    vec![StreamedEvent {
+                        pool_id: pool.pool_id.clone(),
+                        variant: "dbc",
+                        backfilled: false,
+                        event: PoolEvent::DbcBalanceUpdate(payload),
+                    }]
+                } else {
+                    Vec::new()
                 }
-                _ => {}
             }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatch::{dispatch, Publisher};
+    use crate::sinks::memory::MemorySink;
+    use crate::sources::{replay::ReplaySource, UpstreamSource};
+    use crate::types::PoolVariant;
+    use futures_util::StreamExt;
+    use helius_laserstream::grpc::SubscribeRequest;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/dbc");
+
+    fn key(n: u8) -> String {
+        bs58::encode([n; 32]).into_string()
+    }
+
+    /// The pool fixture `name` is about: the watch-list entry in `<name>.pool.json` for a
+    /// recorded capture, else the placeholder pool of the hand-built fixtures (see
+    /// `fixtures/dbc/README.md`).
+    fn pool(name: &str) -> PoolMeta {
+        let path = format!("{}/{}.pool.json", FIXTURES, name);
+        if let Ok(json) = std::fs::read_to_string(&path) {
+            return serde_json::from_str(&json).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        }
+        PoolMeta {
+            pool_id: key(4),
+            variant: PoolVariant::Dbc,
+            quote_vault: Some(key(6)),
+            config_pda: Some(key(8)),
+            base_mint: None,
+            quote_mint: None,
+            program_id: None,
+            idl_path: None,
+        }
+    }
+
+    /// Replay `<name>.bin` through the dispatcher, as the worker would with
+    /// `UPSTREAM_SOURCE=replay`, into an in-memory sink and compare the published events with
+    /// `<name>.golden.jsonl`.
+    /// `UPDATE_GOLDEN=1` rewrites the golden file instead.
+    /// Returns the published events' JSON.
+    async fn assert_golden(name: &str) -> Vec<String> {
+        let tokens = TokenCache::from_env().unwrap();
+        let processors = HashMap::from([(
+            PoolVariant::Dbc,
+            Box::new(DbcProcessor::new(tokens.clone(), SolUsdPrice::default(), true))
+                as Box<dyn Processor>,
+        )]);
+        let pools = vec![pool(name)];
+        let filters = processors[&PoolVariant::Dbc].build_filters(&pools[0]);
+        let request = SubscribeRequest {
+            accounts: filters.accounts,
+            transactions: filters.transactions,
+            ..Default::default()
+        };
+        let sink = MemorySink::default();
+        let (publisher, published) = Publisher::spawn(Box::new(sink.clone()), 500);
+
+        let updates = ReplaySource::once(format!("{}/{}.bin", FIXTURES, name)).subscribe(request);
+        futures_util::pin_mut!(updates);
+        while let Some(update) = updates.next().await {
+            let update = update.unwrap();
+            tokens.observe(&update);
+            dispatch(&update, &pools, &processors, &publisher).await;
+        }
+        drop(publisher);
+        published.await.unwrap();
+
+        let events: Vec<String> = sink.events().iter().map(|event| event.data.clone()).collect();
+        let actual: String = events.iter().map(|data| format!("{}\n", data)).collect();
+        let golden = format!("{}/{}.golden.jsonl", FIXTURES, name);
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(&golden, actual).unwrap();
+        } else {
+            assert_eq!(actual, std::fs::read_to_string(&golden).unwrap(), "{}", golden);
         }
+        events
+    }
+
+    #[tokio::test]
+    async fn pool_and_vault_accounts() {
+        assert_golden("pool_state").await;
+    }
+
+    #[tokio::test]
+    async fn pool_and_config_accounts() {
+        let events = assert_golden("config").await;
+        // Curve values past u64::MAX must read back exactly from the published JSON.
+        let event: serde_json::Value = serde_json::from_str(events.last().unwrap()).unwrap();
+        let config = &event["payload"]["config_state"];
+        let u128_at = |value: &serde_json::Value| value.as_str().unwrap().parse::<u128>().unwrap();
+        let curve: Vec<(u128, u128)> = config["curve"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|point| (u128_at(&point["sqrt_price"]), u128_at(&point["liquidity"])))
+            .collect();
+        let e64 = 1u128 << 64;
+        assert_eq!(
+            curve,
+            [
+                (3 * e64 + 17, 5 * e64 + 123_456_789),
+                (7 * e64 + 1, (1 << 100) + 42),
+                (11 * e64, (1 << 127) + 1),
+            ]
+        );
+        assert_eq!(u128_at(&config["sqrt_start_price"]), 2 * e64 + 5);
+        assert_eq!(u128_at(&config["migration_sqrt_price"]), 11 * e64);
+    }
+
+    #[tokio::test]
+    async fn buy_and_sell() {
+        assert_golden("swaps").await;
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn failed_swap() {
+        assert_golden("failed_swap").await;
+    }
+}
@@ -1,17 +1,363 @@
 use serde::Serialize;
+use serde_json::Value;
+
+use crate::tokens::TokenInfo;
+use crate::tx_error::TxError;
+
+/// u128 values as decimal strings, like the IDL decoder: JSON numbers can't hold them.
+fn u128_string<S: serde::Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// Where in a transaction an event was found.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct EventPosition {
+    /// Index of the top-level instruction.
+    pub instruction_index: u32,
+    /// Index within that instruction's inner (CPI) instructions, `None` for the top-level
+    /// instruction itself.
+    pub inner_index: Option<u32>,
+}
+
+/// The account write an account snapshot was decoded from.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct AccountVersion {
+    pub slot: u64,
+    /// Orders writes to the account within `slot`.
+    pub write_version: u64,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapSide {
+    /// Quote in, base out.
+    Buy,
+    /// Base in, quote out.
+    Sell,
+}
 
 #[derive(Serialize, Debug)]
 pub struct DbcSwapPayload {
     pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub side: SwapSide,
+    /// `None` until the pool's mints have been learned.
+    pub base_mint: Option<String>,
+    pub quote_mint: Option<String>,
     pub input_amount: u64,
     pub output_amount: u64,
+    /// Amounts scaled by mint decimals; `None` while the mint's decimals are unknown.
+    pub input_amount_ui: Option<f64>,
+    pub output_amount_ui: Option<f64>,
+    /// Average execution price, quote per base in UI units.
+    pub price: Option<f64>,
+    #[serde(serialize_with = "u128_string")]
     pub next_sqrt_price: u128,
+    /// Spot price after the swap, quote per base in UI units, from `next_sqrt_price`.
+    pub spot_price: Option<f64>,
+    pub trading_fee: u64,
+    pub protocol_fee: u64,
+    pub referral_fee: u64,
+    /// On-chain unix timestamp of the swap.
+    pub block_time: u64,
+    /// Quote side of the swap in USD; only for SOL-quoted pools with a SOL/USD price.
+    pub usd_value: Option<f64>,
+    /// Swap signer (the instruction's `payer`, else the fee payer).
+    pub trader: Option<String>,
+    /// Net change in the trader's base/quote holdings over the whole transaction, in base
+    /// units; negative when the trader paid.
+    pub trader_base_delta: Option<i128>,
+    pub trader_quote_delta: Option<i128>,
 }
 
 #[derive(Serialize, Debug)]
 pub struct DbcBalanceUpdatePayload {
+    #[serde(flatten)]
+    pub version: AccountVersion,
     pub quote_vault_address: String,
-    pub new_balance: f64,
+    pub mint: String,
+    pub raw_balance: u64,
+    /// `raw_balance` scaled by the mint's decimals; `None` while they are unknown.
+    pub new_balance: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DbcPoolInitializedPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub config: String,
+    pub creator: String,
+    pub base_mint: String,
+    pub pool_type: u8,
+    pub activation_point: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DbcConfigCreatedPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub config: String,
+    pub quote_mint: String,
+    pub fee_claimer: String,
+    pub owner: String,
+    pub collect_fee_mode: u8,
+    pub migration_option: u8,
+    pub activation_type: u8,
+    pub token_decimal: u8,
+    pub token_type: u8,
+    pub swap_base_amount: u64,
+    pub migration_quote_threshold: u64,
+    pub migration_base_amount: u64,
+    #[serde(serialize_with = "u128_string")]
+    pub sqrt_start_price: u128,
+}
+
+/// The bonding curve reached its migration threshold; the pool can now be migrated.
+#[derive(Serialize, Debug)]
+pub struct DbcCurveCompletePayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub config: String,
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+    pub base_reserve_ui: Option<f64>,
+    pub quote_reserve_ui: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DbcMigrationTarget {
+    /// Meteora DAMM v1
+    Damm,
+    DammV2,
+}
+
+/// Migration metadata account was created, ahead of the actual migration.
+#[derive(Serialize, Debug)]
+pub struct DbcMigrationMetadataPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub target: DbcMigrationTarget,
+}
+
+/// Liquidity was moved out of the bonding curve into a DAMM pool. Consumers should stop
+/// tracking `pool` as `dbc` and follow `damm_pool` instead.
+#[derive(Serialize, Debug)]
+pub struct DbcMigratedPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub target: DbcMigrationTarget,
+    pub damm_pool: String,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DbcFeeClaimer {
+    Protocol,
+    Partner,
+    Creator,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DbcFeeClaimedPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub claimer: DbcFeeClaimer,
+    pub token_base_amount: u64,
+    pub token_quote_amount: u64,
+    pub token_base_amount_ui: Option<f64>,
+    pub token_quote_amount_ui: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DbcSurplusWithdrawnPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub claimer: DbcFeeClaimer,
+    pub surplus_amount: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DbcLeftoverWithdrawnPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub leftover_receiver: String,
+    pub leftover_amount: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DbcMigrationFeeWithdrawnPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub fee: u64,
+    pub flag: u8,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DbcPoolCreatorUpdatedPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub creator: String,
+    pub new_creator: String,
+}
+
+/// Authoritative pool state, decoded from the DBC virtual pool account and its config.
+#[derive(Serialize, Debug)]
+pub struct DbcPoolStatePayload {
+    /// The pool or config account write that produced this snapshot.
+    #[serde(flatten)]
+    pub version: AccountVersion,
+    pub pool: String,
+    pub config: String,
+    pub creator: String,
+    pub base_mint: String,
+    pub base_vault: String,
+    pub quote_vault: String,
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+    pub base_reserve_ui: Option<f64>,
+    pub quote_reserve_ui: Option<f64>,
+    pub base_token: Option<TokenInfo>,
+    /// `None` until the config account (which holds the quote mint) has been received.
+    pub quote_token: Option<TokenInfo>,
+    #[serde(serialize_with = "u128_string")]
+    pub sqrt_price: u128,
+    pub activation_point: u64,
+    pub is_migrated: bool,
+    /// Raw `migration_progress` stage of the virtual pool.
+    pub migration_progress: u8,
+    /// `quote_reserve / migration_quote_threshold`, once the config is known.
+    pub curve_progress: Option<f64>,
+    pub finish_curve_timestamp: u64,
+    pub partner_base_fee: u64,
+    pub partner_quote_fee: u64,
+    pub creator_base_fee: u64,
+    pub creator_quote_fee: u64,
+    pub protocol_base_fee: u64,
+    pub protocol_quote_fee: u64,
+    /// `None` until the config account has been received.
+    pub config_state: Option<DbcConfigState>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DbcConfigState {
+    pub quote_mint: String,
+    pub token_decimal: u8,
+    pub collect_fee_mode: u8,
+    pub migration_option: u8,
+    pub activation_type: u8,
+    pub swap_base_amount: u64,
+    pub migration_quote_threshold: u64,
+    pub migration_base_threshold: u64,
+    #[serde(serialize_with = "u128_string")]
+    pub migration_sqrt_price: u128,
+    #[serde(serialize_with = "u128_string")]
+    pub sqrt_start_price: u128,
+    pub fees: DbcFeeConfig,
+    /// Liquidity distribution, unused trailing points omitted.
+    pub curve: Vec<DbcCurvePoint>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DbcFeeConfig {
+    pub cliff_fee_numerator: u64,
+    pub base_fee_mode: u8,
+    pub first_factor: u16,
+    pub second_factor: u64,
+    pub third_factor: u64,
+    pub dynamic_fee_enabled: bool,
+    pub protocol_fee_percent: u8,
+    pub referral_fee_percent: u8,
+    pub creator_trading_fee_percentage: u8,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DbcCurvePoint {
+    #[serde(serialize_with = "u128_string")]
+    pub sqrt_price: u128,
+    #[serde(serialize_with = "u128_string")]
+    pub liquidity: u128,
+}
+
+/// An event emitted by an IDL-decoded program.
+#[derive(Serialize, Debug)]
+pub struct ProgramEventPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub program_id: String,
+    pub name: String,
+    pub data: Value,
+}
+
+/// An instruction invoked on an IDL-decoded program.
+#[derive(Serialize, Debug)]
+pub struct ProgramInstructionPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub program_id: String,
+    pub name: String,
+    pub args: Value,
+    /// IDL account name -> pubkey, in instruction order.
+    pub accounts: Vec<NamedAccount>,
+    /// Whether this was a CPI rather than a top-level instruction.
+    pub inner: bool,
+}
+
+/// A DBC instruction as the user submitted it: IDL name, decoded arguments and named
+/// accounts. Swaps carry `amount_in`/`minimum_amount_out` for requested-vs-realized slippage.
+#[derive(Serialize, Debug)]
+pub struct PoolInstructionPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub name: String,
+    pub args: Value,
+    pub accounts: Vec<NamedAccount>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct NamedAccount {
+    pub name: String,
+    pub pubkey: String,
+}
+
+/// A DBC swap in a failed transaction, with what the user asked for and why it failed.
+#[derive(Serialize, Debug)]
+pub struct FailedSwapAttemptPayload {
+    pub signature: String,
+    #[serde(flatten)]
+    pub position: EventPosition,
+    pub pool: String,
+    pub trader: Option<String>,
+    /// From the input token account's mint; `None` if it had no balance before the tx.
+    pub side: Option<SwapSide>,
+    pub amount_in: u64,
+    pub minimum_amount_out: u64,
+    pub error: TxError,
+    /// IDL name of `error.custom_code` when the DBC program raised it, e.g. `ExceededSlippage`.
+    pub error_name: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -20,13 +366,149 @@ pub struct DbcBalanceUpdatePayload {
 pub enum PoolEvent {
     DbcSwap(DbcSwapPayload),
     DbcBalanceUpdate(DbcBalanceUpdatePayload),
+    DbcPoolInitialized(DbcPoolInitializedPayload),
+    DbcConfigCreated(DbcConfigCreatedPayload),
+    DbcCurveComplete(DbcCurveCompletePayload),
+    DbcMigrationMetadata(DbcMigrationMetadataPayload),
+    DbcMigrated(DbcMigratedPayload),
+    DbcFeeClaimed(DbcFeeClaimedPayload),
+    DbcSurplusWithdrawn(DbcSurplusWithdrawnPayload),
+    DbcLeftoverWithdrawn(DbcLeftoverWithdrawnPayload),
+    DbcMigrationFeeWithdrawn(DbcMigrationFeeWithdrawnPayload),
+    DbcPoolCreatorUpdated(DbcPoolCreatorUpdatedPayload),
+    DbcPoolState(Box<DbcPoolStatePayload>),
+    FailedSwapAttempt(FailedSwapAttemptPayload),
+    PoolInstruction(PoolInstructionPayload),
+    ProgramEvent(ProgramEventPayload),
+    ProgramInstruction(ProgramInstructionPayload),
     // Future variants like AmmSwap, etc. would go here
 }
 
+impl PoolEvent {
+    /// Signature and position of events decoded from a transaction; `None` for account
+    /// snapshots.
+    fn tx_position(&self) -> Option<(&str, EventPosition)> {
+        let (signature, position) = match self {
+            PoolEvent::DbcSwap(p) => (&p.signature, p.position),
+            PoolEvent::DbcPoolInitialized(p) => (&p.signature, p.position),
+            PoolEvent::DbcConfigCreated(p) => (&p.signature, p.position),
+            PoolEvent::DbcCurveComplete(p) => (&p.signature, p.position),
+            PoolEvent::DbcMigrationMetadata(p) => (&p.signature, p.position),
+            PoolEvent::DbcMigrated(p) => (&p.signature, p.position),
+            PoolEvent::DbcFeeClaimed(p) => (&p.signature, p.position),
+            PoolEvent::DbcSurplusWithdrawn(p) => (&p.signature, p.position),
+            PoolEvent::DbcLeftoverWithdrawn(p) => (&p.signature, p.position),
+            PoolEvent::DbcMigrationFeeWithdrawn(p) => (&p.signature, p.position),
+            PoolEvent::DbcPoolCreatorUpdated(p) => (&p.signature, p.position),
+            PoolEvent::FailedSwapAttempt(p) => (&p.signature, p.position),
+            PoolEvent::PoolInstruction(p) => (&p.signature, p.position),
+            PoolEvent::ProgramEvent(p) => (&p.signature, p.position),
+            PoolEvent::ProgramInstruction(p) => (&p.signature, p.position),
+            PoolEvent::DbcBalanceUpdate(_) | PoolEvent::DbcPoolState(_) => return None,
+        };
+        Some((signature, position))
+    }
+}
+
+/// An event as published: the decoded event of one watched pool.
 #[derive(Serialize, Debug)]
-pub struct StreamedEvent<'a> {
-    pub pool_id: &'a str,
-    pub variant: &'a str,
+pub struct StreamedEvent {
+    pub pool_id: String,
+    pub variant: &'static str,
+    /// Replayed from RPC after an ingest gap rather than streamed live.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub backfilled: bool,
     #[serde(flatten)]
     pub event: PoolEvent,
-} 
\ No newline at end of file
+}
+
+impl StreamedEvent {
+    /// Event JSON tagged with its `event_id`, and that id.
+    pub fn to_json(&self) -> serde_json::Result<(Value, String)> {
+        let mut json = serde_json::to_value(self)?;
+        let id = self.event_id(json["event_type"].as_str().unwrap_or_default());
+        json["event_id"] = Value::String(id.clone());
+        Ok((json, id))
+    }
+
+    /// Deterministic id of the event, identical on every ingest replica and on replays.
+    ///
+    /// Transaction events use `{signature}:{instruction_index}:{inner_index}:{event_type}`
+    /// (`-` for a top-level instruction); account snapshots, which have no signature, use
+    /// `{pool_id}:{event_type}:{slot}:{write_version}` of the account write, so a state
+    /// that returns to an earlier value is still a new event.
+    fn event_id(&self, event_type: &str) -> String {
+        if let Some((signature, position)) = self.event.tx_position() {
+            return format!(
+                "{}:{}:{}:{}",
+                signature,
+                position.instruction_index,
+                position
+                    .inner_index
+                    .map_or_else(|| "-".to_string(), |i| i.to_string()),
+                event_type
+            );
+        }
+        let version = match &self.event {
+            PoolEvent::DbcBalanceUpdate(p) => p.version,
+            PoolEvent::DbcPoolState(p) => p.version,
+            _ => unreachable!("every event without a transaction position is a snapshot"),
+        };
+        format!(
+            "{}:{}:{}:{}",
+            self.pool_id, event_type, version.slot, version.write_version
+        )
+    }
+}
+
+pub const POOL_EVENTS_STREAM: &str = "heimdall:pool_events";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balance_update(slot: u64, write_version: u64, raw_balance: u64) -> StreamedEvent {
+        StreamedEvent {
+            pool_id: "pool".to_string(),
+            variant: "dbc",
+            backfilled: false,
+            event: PoolEvent::DbcBalanceUpdate(DbcBalanceUpdatePayload {
+                version: AccountVersion {
+                    slot,
+                    write_version,
+                },
+                quote_vault_address: "vault".to_string(),
+                mint: "mint".to_string(),
+                raw_balance,
+                new_balance: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn snapshot_returning_to_an_earlier_state_gets_a_new_id() {
+        let (_, a) = balance_update(100, 1, 5).to_json().unwrap();
+        let (_, b) = balance_update(100, 2, 7).to_json().unwrap();
+        let (_, a_again) = balance_update(101, 3, 5).to_json().unwrap();
+        assert_eq!(a, "pool:dbc_balance_update:100:1");
+        assert_ne!(a, b);
+        assert_ne!(a, a_again);
+        // The same write seen twice (replay, second replica) keeps its id.
+        assert_eq!(a_again, balance_update(101, 3, 5).to_json().unwrap().1);
+    }
+
+    #[test]
+    fn u128_fields_serialize_as_decimal_strings() {
+        let point = DbcCurvePoint {
+            sqrt_price: 1 << 70,
+            liquidity: u128::MAX,
+        };
+        assert_eq!(
+            serde_json::to_value(&point).unwrap(),
+            serde_json::json!({
+                "sqrt_price": "1180591620717411303424",
+                "liquidity": "340282366920938463463374607431768211455",
+            })
+        );
+    }
+}
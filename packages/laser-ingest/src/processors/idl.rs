@@ -0,0 +1,177 @@
+use helius_laserstream::grpc::{
+    subscribe_update::UpdateOneof, SubscribeRequestFilterTransactions, SubscribeUpdate,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::events::{
+    EventPosition, NamedAccount, PoolEvent, ProgramEventPayload, ProgramInstructionPayload,
+    StreamedEvent,
+};
+use super::{account_keys, FilterContribution, Processor};
+use crate::backfill::is_backfill;
+use crate::idl::{IdlDecoder, ANCHOR_CPI_LOG_DISCRIMINATOR};
+use crate::types::PoolMeta;
+
+/// Watches any Anchor program listed in the watchlist with `variant: "idl"`.
+///
+/// Events emitted through `emit_cpi!` and calls into the program (top-level and CPI)
+/// are decoded with the program's IDL into `program_event` /
+/// `program_instruction` pool events. Failed transactions are skipped: their instructions
+/// didn't take effect.
+#[derive(Default)]
+pub struct IdlProcessor {
+    /// Decoders keyed by IDL path, loaded on first use. `None` if the IDL failed to load,
+    /// so it's reported once rather than on every update.
+    decoders: Mutex<HashMap<String, Option<Arc<IdlDecoder>>>>,
+}
+
+impl IdlProcessor {
+    fn decoder(&self, pool: &PoolMeta) -> Option<Arc<IdlDecoder>> {
+        let path = pool.idl_path.as_ref()?;
+        let mut decoders = self.decoders.lock().unwrap();
+        if let Some(decoder) = decoders.get(path) {
+            return decoder.clone();
+        }
+        let decoder = match IdlDecoder::from_file(path) {
+            Ok(decoder) => {
+                if let (Some(configured), Some(in_idl)) = (&pool.program_id, decoder.program_id()) {
+                    if configured != in_idl {
+                        tracing::warn!(pool_id = %pool.pool_id, configured, in_idl, "IDL address differs from configured program id");
+                    }
+                }
+                Some(Arc::new(decoder))
+            }
+            Err(e) => {
+                tracing::error!(pool_id = %pool.pool_id, error = %e, "Failed to load IDL, ignoring its updates until restart");
+                None
+            }
+        };
+        decoders.insert(path.clone(), decoder.clone());
+        decoder
+    }
+}
+
+impl Processor for IdlProcessor {
+    fn build_filters(&self, pool: &PoolMeta) -> FilterContribution {
+        let mut txs = HashMap::new();
+        match &pool.program_id {
+            Some(program_id) => {
+                txs.insert(
+                    format!("{}_idl", pool.pool_id),
+                    SubscribeRequestFilterTransactions {
+                        account_include: vec![program_id.clone()],
+                        failed: Some(false),
+                        ..Default::default()
+                    },
+                );
+            }
+            None => {
+                tracing::warn!(pool_id = %pool.pool_id, "IDL watch entry has no program id, skipping");
+            }
+        }
+        FilterContribution {
+            accounts: HashMap::new(),
+            transactions: txs,
+        }
+    }
+
+    fn handle_update(&self, pool: &PoolMeta, update: &SubscribeUpdate) -> Vec<StreamedEvent> {
+        let Some(UpdateOneof::Transaction(tx_update)) = &update.update_oneof else {
+            return Vec::new();
+        };
+        let Some(tx_info) = &tx_update.transaction else {
+            return Vec::new();
+        };
+        let (Some(tx), Some(meta)) = (&tx_info.transaction, &tx_info.meta) else {
+            return Vec::new();
+        };
+        // Failed transactions are filtered upstream, but backfill fetches every signature.
+        if meta.err.is_some() {
+            return Vec::new();
+        }
+        let Some(message) = &tx.message else {
+            return Vec::new();
+        };
+        let (Some(decoder), Some(program_id)) = (self.decoder(pool), &pool.program_id) else {
+            return Vec::new();
+        };
+        let Ok(program_bytes) = bs58::decode(program_id).into_vec() else {
+            return Vec::new();
+        };
+
+        // Static keys followed by addresses loaded from lookup tables, which is how
+        // instruction account indexes are resolved.
+        let keys = account_keys(message, meta);
+        let resolve = |indexes: &[u8]| -> Vec<Vec<u8>> {
+            indexes
+                .iter()
+                .filter_map(|i| keys.get(*i as usize).map(|k| (*k).clone()))
+                .collect()
+        };
+        let signature = bs58::encode(&tx_info.signature).into_string();
+
+        let mut events = Vec::new();
+        let top_level = message.instructions.iter().enumerate().map(|(i, ix)| {
+            let position = EventPosition {
+                instruction_index: i as u32,
+                inner_index: None,
+            };
+            (ix.program_id_index, ix.accounts.as_slice(), ix.data.as_slice(), position)
+        });
+        let inner = meta.inner_instructions.iter().flat_map(|list| {
+            list.instructions.iter().enumerate().map(|(j, ix)| {
+                let position = EventPosition {
+                    instruction_index: list.index,
+                    inner_index: Some(j as u32),
+                };
+                (ix.program_id_index, ix.accounts.as_slice(), ix.data.as_slice(), position)
+            })
+        });
+
+        for (program_idx, accounts, data, position) in top_level.chain(inner) {
+            if keys.get(program_idx as usize).map(|k| k.as_slice()) != Some(program_bytes.as_slice()) {
+                continue;
+            }
+
+            if data.starts_with(&ANCHOR_CPI_LOG_DISCRIMINATOR) {
+                if let Some(evt) = decoder.decode_cpi_event(data) {
+                    events.push(PoolEvent::ProgramEvent(ProgramEventPayload {
+                        signature: signature.clone(),
+                        position,
+                        program_id: program_id.clone(),
+                        name: evt.name,
+                        data: evt.data,
+                    }));
+                }
+                continue;
+            }
+
+            if let Some(ix) = decoder.decode_instruction(data, &resolve(accounts)) {
+                events.push(PoolEvent::ProgramInstruction(ProgramInstructionPayload {
+                    signature: signature.clone(),
+                    position,
+                    program_id: program_id.clone(),
+                    name: ix.name,
+                    args: ix.args,
+                    accounts: ix
+                        .accounts
+                        .into_iter()
+                        .map(|(name, pubkey)| NamedAccount { name, pubkey })
+                        .collect(),
+                    inner: position.inner_index.is_some(),
+                }));
+            }
+        }
+
+        events
+            .into_iter()
+            .map(|event| StreamedEvent {
+                pool_id: pool.pool_id.clone(),
+                variant: "idl",
+                backfilled: is_backfill(update),
+                event,
+            })
+            .collect()
+    }
+}
@@ -1,15 +1,20 @@
-use async_trait::async_trait;
-use helius_laserstream::grpc::{SubscribeUpdate, SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions};
-use redis::aio::MultiplexedConnection as RedisConn;
+use helius_laserstream::grpc::{
+    Message, SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions, SubscribeUpdate,
+    TransactionStatusMeta,
+};
 
 use std::collections::HashMap;
 
+use crate::price::SolUsdPrice;
+use crate::processors::events::StreamedEvent;
+use crate::tokens::TokenCache;
 use crate::types::{PoolMeta, PoolVariant};
 
 pub mod events;
 pub mod dbc;
 pub mod amm;
 pub mod damm;
+pub mod idl;
 
 /// Contribution of filters from a processor for a particular pool.
 pub struct FilterContribution {
@@ -17,38 +22,51 @@ pub struct FilterContribution {
     pub transactions: HashMap<String, SubscribeRequestFilterTransactions>,
 }
 
-#[async_trait]
 pub trait Processor: Send + Sync {
     /// Build Helius filters required to receive updates for this pool.
     fn build_filters(&self, pool: &PoolMeta) -> FilterContribution;
 
-    /// Process an incoming update. Implementations must publish to Redis.
-    async fn handle_update(
-        &self,
-        pool: &PoolMeta,
-        update: &SubscribeUpdate,
-        conn: &mut RedisConn,
-    );
+    /// Decode an incoming update into the events to publish for this pool, in order.
+    /// Publishing is left to the dispatcher's `EventSink`.
+    fn handle_update(&self, pool: &PoolMeta, update: &SubscribeUpdate) -> Vec<StreamedEvent>;
+}
+
+/// Full account key list of a transaction: the static keys followed by the writable and
+/// readonly addresses loaded from lookup tables. Instruction `program_id_index` and account
+/// indexes of v0 transactions point into this list, not just `message.account_keys`.
+pub fn account_keys<'a>(message: &'a Message, meta: &'a TransactionStatusMeta) -> Vec<&'a Vec<u8>> {
+    message
+        .account_keys
+        .iter()
+        .chain(meta.loaded_writable_addresses.iter())
+        .chain(meta.loaded_readonly_addresses.iter())
+        .collect()
 }
 
 /// Registry helper
-pub fn default_registry() -> HashMap<PoolVariant, Box<dyn Processor>> {
+pub fn default_registry(
+    tokens: TokenCache,
+    sol_usd: SolUsdPrice,
+    publish_failed_swaps: bool,
+) -> HashMap<PoolVariant, Box<dyn Processor>> {
     use std::iter::FromIterator;
     HashMap::from_iter([
-        (PoolVariant::Dbc, Box::new(dbc::DbcProcessor) as Box<dyn Processor>),
+        (PoolVariant::Dbc, Box::new(dbc::DbcProcessor::new(tokens, sol_usd, publish_failed_swaps)) as Box<dyn Processor>),
         (PoolVariant::Amm, Box::new(amm::AmmProcessor) as Box<dyn Processor>),
         (PoolVariant::Damm, Box::new(damm::DammProcessor) as Box<dyn Processor>),
+        (PoolVariant::Idl, Box::new(idl::IdlProcessor::default()) as Box<dyn Processor>),
     ])
 }
 
 /// A no-op processor which can be used as a placeholder.
 pub struct NoopProcessor;
 
-#[async_trait]
 impl Processor for NoopProcessor {
     fn build_filters(&self, _pool: &PoolMeta) -> FilterContribution {
         FilterContribution { accounts: HashMap::new(), transactions: HashMap::new() }
     }
 
-    async fn handle_update(&self, _pool: &PoolMeta, _update: &SubscribeUpdate, _conn: &mut RedisConn) {}
+    fn handle_update(&self, _pool: &PoolMeta, _update: &SubscribeUpdate) -> Vec<StreamedEvent> {
+        Vec::new()
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,44 @@
+//! Recording of raw upstream updates, for replay with `UPSTREAM_SOURCE=replay`.
+//!
+//! With `RECORD_UPDATES=<path>` every update received from upstream is appended to `path` as
+//! a length-delimited `SubscribeUpdate` protobuf (a varint length, then the message), before
+//! it is processed.
+
+use anyhow::{Context, Result};
+use helius_laserstream::grpc::SubscribeUpdate;
+use prost::Message as _;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+pub struct Recorder {
+    file: File,
+    path: String,
+}
+
+impl Recorder {
+    /// The recorder `RECORD_UPDATES` asks for, if any.
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var("RECORD_UPDATES") {
+            Ok(path) => Self::open(path).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Append to `path`, creating it if needed.
+    pub fn open(path: String) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("opening {}", path))?;
+        Ok(Self { file, path })
+    }
+
+    /// Append `update`. Each record is written with a single `write`, so a crash leaves at
+    /// most the last record truncated.
+    pub fn record(&mut self, update: &SubscribeUpdate) {
+        if let Err(e) = self.file.write_all(&update.encode_length_delimited_to_vec()) {
+            tracing::warn!(error = %e, path = %self.path, "Failed to record update");
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Minimal Solana JSON-RPC client for backfilling.
+//!
+//! Transactions are fetched with `json` encoding and converted into the same
+//! `SubscribeUpdate` shape LaserStream delivers, so processors handle them unchanged.
+
+use anyhow::{anyhow, bail, Context, Result};
+use helius_laserstream::grpc::{
+    subscribe_update::UpdateOneof, CompiledInstruction, InnerInstruction, InnerInstructions,
+    Message, MessageAddressTableLookup, MessageHeader, SubscribeUpdate,
+    SubscribeUpdateTransaction, SubscribeUpdateTransactionInfo, TokenBalance, Transaction,
+    TransactionError, TransactionStatusMeta, UiTokenAmount,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::tx_error;
+
+/// `getSignaturesForAddress` page size (the RPC maximum).
+pub const MAX_SIGNATURES_PAGE: usize = 1000;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+    #[serde(rename = "blockTime")]
+    pub block_time: Option<i64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RpcClient {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl RpcClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut response: Value = self
+            .http
+            .post(&self.url)
+            .json(&body)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(err) = response.get("error") {
+            bail!("{} failed: {}", method, err);
+        }
+        Ok(response["result"].take())
+    }
+
+    /// One page of signatures for `address`, newest first, strictly older than `before` and
+    /// newer than `until`.
+    pub async fn signatures_for_address(
+        &self,
+        address: &str,
+        before: Option<&str>,
+        until: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut config = json!({ "limit": limit.min(MAX_SIGNATURES_PAGE), "commitment": "confirmed" });
+        if let Some(before) = before {
+            config["before"] = json!(before);
+        }
+        if let Some(until) = until {
+            config["until"] = json!(until);
+        }
+        let result = self
+            .call("getSignaturesForAddress", json!([address, config]))
+            .await?;
+        serde_json::from_value(result).context("decoding getSignaturesForAddress result")
+    }
+
+    /// A confirmed transaction as a `SubscribeUpdate`, `None` if the node doesn't have it.
+    pub async fn transaction(&self, signature: &str) -> Result<Option<SubscribeUpdate>> {
+        let config = json!({
+            "encoding": "json",
+            "commitment": "confirmed",
+            "maxSupportedTransactionVersion": 0,
+        });
+        let result = self.call("getTransaction", json!([signature, config])).await?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        transaction_update(&result)
+            .with_context(|| format!("converting transaction {}", signature))
+            .map(Some)
+    }
+}
+
+/// Convert a `json`-encoded `getTransaction` result into a transaction `SubscribeUpdate`.
+pub fn transaction_update(result: &Value) -> Result<SubscribeUpdate> {
+    let tx = &result["transaction"];
+    let message = &tx["message"];
+    let meta = &result["meta"];
+
+    let signatures = tx["signatures"]
+        .as_array()
+        .ok_or_else(|| anyhow!("missing signatures"))?
+        .iter()
+        .map(decode_b58)
+        .collect::<Result<Vec<_>>>()?;
+    let signature = signatures
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow!("transaction without signatures"))?;
+
+    let header = &message["header"];
+    let address_table_lookups = array(&message["addressTableLookups"])
+        .iter()
+        .map(|l| {
+            Ok(MessageAddressTableLookup {
+                account_key: decode_b58(&l["accountKey"])?,
+                writable_indexes: bytes(&l["writableIndexes"]),
+                readonly_indexes: bytes(&l["readonlyIndexes"]),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let message = Message {
+        header: Some(MessageHeader {
+            num_required_signatures: u32_of(&header["numRequiredSignatures"]),
+            num_readonly_signed_accounts: u32_of(&header["numReadonlySignedAccounts"]),
+            num_readonly_unsigned_accounts: u32_of(&header["numReadonlyUnsignedAccounts"]),
+        }),
+        account_keys: array(&message["accountKeys"])
+            .iter()
+            .map(decode_b58)
+            .collect::<Result<_>>()?,
+        recent_blockhash: decode_b58(&message["recentBlockhash"])?,
+        instructions: array(&message["instructions"])
+            .iter()
+            .map(|ix| {
+                Ok(CompiledInstruction {
+                    program_id_index: u32_of(&ix["programIdIndex"]),
+                    accounts: bytes(&ix["accounts"]),
+                    data: decode_b58(&ix["data"])?,
+                })
+            })
+            .collect::<Result<_>>()?,
+        versioned: !result["version"].is_null() && result["version"] != json!("legacy"),
+        address_table_lookups,
+    };
+
+    let token_balances = |v: &Value| -> Vec<TokenBalance> {
+        array(v)
+            .iter()
+            .map(|b| TokenBalance {
+                account_index: u32_of(&b["accountIndex"]),
+                mint: str_of(&b["mint"]),
+                ui_token_amount: Some(UiTokenAmount {
+                    ui_amount: b["uiTokenAmount"]["uiAmount"].as_f64().unwrap_or_default(),
+                    decimals: u32_of(&b["uiTokenAmount"]["decimals"]),
+                    amount: str_of(&b["uiTokenAmount"]["amount"]),
+                    ui_amount_string: str_of(&b["uiTokenAmount"]["uiAmountString"]),
+                }),
+                owner: str_of(&b["owner"]),
+                program_id: str_of(&b["programId"]),
+            })
+            .collect()
+    };
+    let loaded = |kind: &str| -> Result<Vec<Vec<u8>>> {
+        array(&meta["loadedAddresses"][kind])
+            .iter()
+            .map(decode_b58)
+            .collect()
+    };
+    let meta = TransactionStatusMeta {
+        err: match &meta["err"] {
+            Value::Null => None,
+            err => Some(TransactionError {
+                err: tx_error::encode_json(err),
+            }),
+        },
+        fee: meta["fee"].as_u64().unwrap_or_default(),
+        pre_balances: u64s(&meta["preBalances"]),
+        post_balances: u64s(&meta["postBalances"]),
+        inner_instructions: array(&meta["innerInstructions"])
+            .iter()
+            .map(|list| {
+                Ok(InnerInstructions {
+                    index: u32_of(&list["index"]),
+                    instructions: array(&list["instructions"])
+                        .iter()
+                        .map(|ix| {
+                            Ok(InnerInstruction {
+                                program_id_index: u32_of(&ix["programIdIndex"]),
+                                accounts: bytes(&ix["accounts"]),
+                                data: decode_b58(&ix["data"])?,
+                                stack_height: ix["stackHeight"].as_u64().map(|h| h as u32),
+                            })
+                        })
+                        .collect::<Result<_>>()?,
+                })
+            })
+            .collect::<Result<_>>()?,
+        inner_instructions_none: meta["innerInstructions"].is_null(),
+        log_messages: array(&meta["logMessages"]).iter().map(str_of).collect(),
+        log_messages_none: meta["logMessages"].is_null(),
+        pre_token_balances: token_balances(&meta["preTokenBalances"]),
+        post_token_balances: token_balances(&meta["postTokenBalances"]),
+        loaded_writable_addresses: loaded("writable")?,
+        loaded_readonly_addresses: loaded("readonly")?,
+        compute_units_consumed: meta["computeUnitsConsumed"].as_u64(),
+        ..Default::default()
+    };
+
+    Ok(SubscribeUpdate {
+        update_oneof: Some(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature,
+                is_vote: false,
+                transaction: Some(Transaction {
+                    signatures,
+                    message: Some(message),
+                }),
+                meta: Some(meta),
+                index: 0,
+            }),
+            slot: result["slot"].as_u64().unwrap_or_default(),
+        })),
+        ..Default::default()
+    })
+}
+
+fn decode_b58(v: &Value) -> Result<Vec<u8>> {
+    let s = v.as_str().ok_or_else(|| anyhow!("expected base58 string, got {}", v))?;
+    bs58::decode(s)
+        .into_vec()
+        .with_context(|| format!("invalid base58 {}", s))
+}
+
+fn array(v: &Value) -> &[Value] {
+    v.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn bytes(v: &Value) -> Vec<u8> {
+    array(v).iter().filter_map(|b| b.as_u64()).map(|b| b as u8).collect()
+}
+
+fn u64s(v: &Value) -> Vec<u64> {
+    array(v).iter().filter_map(Value::as_u64).collect()
+}
+
+fn u32_of(v: &Value) -> u32 {
+    v.as_u64().unwrap_or_default() as u32
+}
+
+fn str_of(v: &Value) -> String {
+    v.as_str().unwrap_or_default().to_owned()
+}
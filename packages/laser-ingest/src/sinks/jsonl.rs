@@ -0,0 +1,48 @@
+//! Newline-delimited JSON, on stdout or appended to a file.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+
+use super::{EventSink, SinkEvent};
+
+pub struct JsonlSink {
+    out: BufWriter<Box<dyn Write + Send>>,
+}
+
+impl JsonlSink {
+    pub fn stdout() -> Self {
+        Self {
+            out: BufWriter::new(Box::new(std::io::stdout())),
+        }
+    }
+
+    /// Append to `EVENT_SINK_PATH`.
+    pub fn from_env() -> Result<Self> {
+        let path = std::env::var("EVENT_SINK_PATH").context("EVENT_SINK_PATH")?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("opening {}", path))?;
+        Ok(Self {
+            out: BufWriter::new(Box::new(file)),
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for JsonlSink {
+    fn name(&self) -> &'static str {
+        "JSON lines"
+    }
+
+    async fn publish(&mut self, events: &[SinkEvent]) -> Result<()> {
+        for event in events {
+            writeln!(self.out, "{}", event.data)?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+//! A Kafka topic, `heimdall.pool_events` by default.
+//!
+//! Events are keyed by pool id, so each pool's events stay ordered within a partition, and
+//! carry their `event_id` as a header. All events of a batch are queued before waiting for
+//! any delivery. The producer is idempotent, so its own retries don't duplicate.
+//! `KAFKA_BROKERS` lists the bootstrap servers.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rdkafka::config::ClientConfig;
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+
+use super::{dotted, EventSink, SinkEvent};
+
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaSink {
+    pub fn from_env(stream: &str) -> Result<Self> {
+        let brokers = std::env::var("KAFKA_BROKERS").context("KAFKA_BROKERS")?;
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .set("message.timeout.ms", "30000")
+            .create()?;
+        Ok(Self {
+            producer,
+            topic: dotted(stream),
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaSink {
+    fn name(&self) -> &'static str {
+        "Kafka"
+    }
+
+    async fn publish(&mut self, events: &[SinkEvent]) -> Result<()> {
+        let deliveries = events.iter().map(|event| {
+            let record = FutureRecord::to(&self.topic)
+                .key(&event.pool_id)
+                .payload(&event.data)
+                .headers(OwnedHeaders::new().insert(Header {
+                    key: "event_id",
+                    value: Some(&event.event_id),
+                }));
+            self.producer.send(record, Timeout::Never)
+        });
+        for delivery in futures_util::future::join_all(deliveries).await {
+            delivery.map_err(|(e, _)| e)?;
+        }
+        Ok(())
+    }
+}
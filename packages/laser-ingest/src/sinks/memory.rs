@@ -0,0 +1,35 @@
+//! Events kept in memory, for tests.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+use super::{EventSink, SinkEvent};
+
+/// Clones share the published events, so a test keeps one and hands the other to the
+/// `Publisher`.
+#[derive(Clone, Default)]
+#[cfg_attr(not(test), allow(dead_code))]
+pub struct MemorySink {
+    events: Arc<Mutex<Vec<SinkEvent>>>,
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+impl MemorySink {
+    /// Everything published so far, in order.
+    pub fn events(&self) -> Vec<SinkEvent> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl EventSink for MemorySink {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn publish(&mut self, events: &[SinkEvent]) -> Result<()> {
+        self.events.lock().unwrap().extend_from_slice(events);
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+//! Destinations for pool events.
+//!
+//! Processors only decode updates into events; the dispatcher's `Publisher` hands them to an
+//! `EventSink`, chosen with `EVENT_SINK`:
+//!
+//! * `redis` (default) – XADD to a Redis stream on `REDIS_URL`, deduplicated by `event_id`
+//!   for `EVENT_DEDUP_TTL_SECS`.
+//! * `stdout` – one JSON event per line on stdout; logs go to stderr.
+//! * `jsonl` – the same, appended to `EVENT_SINK_PATH`.
+//! * `nats` – NATS subjects on `NATS_URL` (cargo feature `nats`).
+//! * `kafka` – a Kafka topic on `KAFKA_BROKERS` (cargo feature `kafka`).
+//!
+//! [`memory::MemorySink`] keeps events in memory, for tests.
+//!
+//! Every sink is given the logical stream name (`heimdall:pool_events`, or the backfill
+//! stream); NATS and Kafka derive their subject and topic from it by replacing `:` with `.`.
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+
+use crate::processors::events::StreamedEvent;
+
+pub mod jsonl;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod memory;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod redis;
+
+/// An event ready to publish.
+#[derive(Debug, Clone)]
+pub struct SinkEvent {
+    pub pool_id: String,
+    /// Deterministic id (see `StreamedEvent::to_json`), for sinks that deduplicate.
+    pub event_id: String,
+    /// Event JSON, including `event_id`.
+    pub data: String,
+}
+
+impl TryFrom<&StreamedEvent> for SinkEvent {
+    type Error = serde_json::Error;
+
+    fn try_from(event: &StreamedEvent) -> Result<Self, Self::Error> {
+        let (json, event_id) = event.to_json()?;
+        Ok(Self {
+            pool_id: event.pool_id.clone(),
+            event_id,
+            data: json.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+pub trait EventSink: Send {
+    /// Human readable name, for logs.
+    fn name(&self) -> &'static str;
+
+    /// Publish `events`, in order. After an error the whole batch is offered again, so
+    /// events published before the error may be published twice unless the sink drops
+    /// repeated `event_id`s.
+    async fn publish(&mut self, events: &[SinkEvent]) -> Result<()>;
+}
+
+/// The sink selected by `EVENT_SINK`, publishing to `stream`.
+pub fn from_env(stream: &str) -> Result<Box<dyn EventSink>> {
+    let kind = std::env::var("EVENT_SINK").unwrap_or_else(|_| "redis".to_string());
+    Ok(match kind.as_str() {
+        "redis" => Box::new(redis::RedisStreamSink::from_env(stream)?),
+        "stdout" => Box::new(jsonl::JsonlSink::stdout()),
+        "jsonl" => Box::new(jsonl::JsonlSink::from_env()?),
+        #[cfg(feature = "nats")]
+        "nats" => Box::new(nats::NatsSink::from_env(stream)),
+        #[cfg(feature = "kafka")]
+        "kafka" => Box::new(kafka::KafkaSink::from_env(stream)?),
+        #[cfg(not(feature = "nats"))]
+        "nats" => bail!("EVENT_SINK=nats needs laser-ingest built with --features nats"),
+        #[cfg(not(feature = "kafka"))]
+        "kafka" => bail!("EVENT_SINK=kafka needs laser-ingest built with --features kafka"),
+        other => bail!("unknown EVENT_SINK {}", other),
+    })
+}
+
+/// `stream` as a NATS subject or Kafka topic name.
+#[cfg(any(feature = "nats", feature = "kafka"))]
+fn dotted(stream: &str) -> String {
+    stream.replace(':', ".")
+}
@@ -0,0 +1,55 @@
+//! NATS core publishes, one subject per pool: `heimdall.pool_events.<pool_id>`.
+//!
+//! Each event carries its `event_id` as the `Nats-Msg-Id` header, so a JetStream stream
+//! capturing the subjects drops duplicates within its duplicate window. A batch is flushed
+//! once, after all its events have been written.
+
+use anyhow::Result;
+use async_nats::HeaderMap;
+use async_trait::async_trait;
+
+use super::{dotted, EventSink, SinkEvent};
+
+pub struct NatsSink {
+    url: String,
+    subject: String,
+    /// Connected on first use; the client reconnects by itself afterwards.
+    client: Option<async_nats::Client>,
+}
+
+impl NatsSink {
+    pub fn from_env(stream: &str) -> Self {
+        Self {
+            url: std::env::var("NATS_URL").unwrap_or_else(|_| "nats://127.0.0.1:4222".to_string()),
+            subject: dotted(stream),
+            client: None,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsSink {
+    fn name(&self) -> &'static str {
+        "NATS"
+    }
+
+    async fn publish(&mut self, events: &[SinkEvent]) -> Result<()> {
+        if self.client.is_none() {
+            self.client = Some(async_nats::connect(self.url.as_str()).await?);
+        }
+        let client = self.client.as_ref().unwrap();
+        for event in events {
+            let mut headers = HeaderMap::new();
+            headers.insert("Nats-Msg-Id", event.event_id.as_str());
+            client
+                .publish_with_headers(
+                    format!("{}.{}", self.subject, event.pool_id),
+                    headers,
+                    event.data.clone().into(),
+                )
+                .await?;
+        }
+        client.flush().await?;
+        Ok(())
+    }
+}
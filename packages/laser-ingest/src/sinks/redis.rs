@@ -0,0 +1,142 @@
+//! A Redis stream, `heimdall:pool_events` by default.
+//!
+//! Each batch goes out as one pipeline. Every event's JSON is XADDed as the `data` field of
+//! an entry. An event whose id was already published for the same pool and stream within
+//! `EVENT_DEDUP_TTL_SECS` (default 600, `0` disables deduplication) is dropped, so reconnect
+//! replays, gap backfills, retried batches and redundant ingest replicas publish each event
+//! once.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use redis::aio::MultiplexedConnection as RedisConn;
+use std::sync::LazyLock;
+
+use super::{EventSink, SinkEvent};
+
+/// XADD unless the dedup key is already set. `KEYS[1]` is the stream, `KEYS[2]` the dedup
+/// key; `ARGV[1]` is the TTL in seconds and `ARGV[2]` the event JSON.
+const XADD_IF_ABSENT_LUA: &str = r"
+    if redis.call('SET', KEYS[2], 1, 'NX', 'EX', ARGV[1]) then
+        return redis.call('XADD', KEYS[1], '*', 'data', ARGV[2])
+    end
+    return false
+";
+
+static XADD_IF_ABSENT: LazyLock<redis::Script> =
+    LazyLock::new(|| redis::Script::new(XADD_IF_ABSENT_LUA));
+
+pub struct RedisStreamSink {
+    client: redis::Client,
+    /// Connected on first use, and again after an error.
+    conn: Option<RedisConn>,
+    stream: String,
+    dedup_ttl_secs: u64,
+}
+
+impl RedisStreamSink {
+    pub fn new(client: redis::Client, stream: &str, dedup_ttl_secs: u64) -> Self {
+        Self {
+            client,
+            conn: None,
+            stream: stream.to_owned(),
+            dedup_ttl_secs,
+        }
+    }
+
+    pub fn from_env(stream: &str) -> Result<Self> {
+        let url =
+            std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+        let dedup_ttl_secs = std::env::var("EVENT_DEDUP_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(600);
+        Ok(Self::new(redis::Client::open(url)?, stream, dedup_ttl_secs))
+    }
+
+    async fn connection(&mut self) -> Result<&mut RedisConn> {
+        if self.conn.is_none() {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            if self.dedup_ttl_secs > 0 {
+                // Pipelined EVALSHAs can't fall back to EVAL, so make sure the script is
+                // cached.
+                redis::cmd("SCRIPT")
+                    .arg("LOAD")
+                    .arg(XADD_IF_ABSENT_LUA)
+                    .query_async::<_, ()>(&mut conn)
+                    .await?;
+            }
+            self.conn = Some(conn);
+        }
+        Ok(self.conn.as_mut().unwrap())
+    }
+}
+
+#[async_trait]
+impl EventSink for RedisStreamSink {
+    fn name(&self) -> &'static str {
+        "Redis stream"
+    }
+
+    async fn publish(&mut self, events: &[SinkEvent]) -> Result<()> {
+        let mut pipe = redis::pipe();
+        for event in events {
+            if self.dedup_ttl_secs == 0 {
+                pipe.cmd("XADD")
+                    .arg(&self.stream)
+                    .arg("*")
+                    .arg("data")
+                    .arg(&event.data);
+            } else {
+                let dedup_key = format!(
+                    "heimdall:dedup:{}:{}:{}",
+                    self.stream, event.pool_id, event.event_id
+                );
+                pipe.cmd("EVALSHA")
+                    .arg(XADD_IF_ABSENT.get_hash())
+                    .arg(2)
+                    .arg(&self.stream)
+                    .arg(dedup_key)
+                    .arg(self.dedup_ttl_secs)
+                    .arg(&event.data);
+            }
+        }
+        let conn = self.connection().await?;
+        match pipe.query_async::<_, Vec<Option<String>>>(conn).await {
+            Ok(ids) => {
+                let duplicates = ids.iter().filter(|id| id.is_none()).count();
+                if duplicates > 0 {
+                    tracing::debug!(duplicates, "Dropped duplicate pool events");
+                }
+                Ok(())
+            }
+            Err(e) => {
+                // Reconnect, and reload the script, on the next attempt.
+                self.conn = None;
+                Err(e.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeRedis;
+
+    fn event(n: u8) -> SinkEvent {
+        SinkEvent {
+            pool_id: "pool".to_owned(),
+            event_id: format!("sig:{}:-:dbc_swap", n),
+            data: format!(r#"{{"n":{}}}"#, n),
+        }
+    }
+
+    #[tokio::test]
+    async fn drops_repeated_event_ids() {
+        let redis = FakeRedis::start().await;
+        let mut sink = RedisStreamSink::new(redis.client.clone(), "heimdall:pool_events", 600);
+        sink.publish(&[event(1), event(2)]).await.unwrap();
+        sink.publish(&[event(2), event(3)]).await.unwrap();
+        assert_eq!(redis.published(), [r#"{"n":1}"#, r#"{"n":2}"#, r#"{"n":3}"#]);
+    }
+}
@@ -0,0 +1,327 @@
+//! The protobuf Redis streams written by the Geyser-to-Redis plugin (`config/config.json`).
+//!
+//! Entries of `heimdall:accounts`, `heimdall:slots` and `heimdall:transactions` carry an
+//! unwrapped `heimdall.types` message in their `data` field. They are converted to
+//! `SubscribeUpdate`s and filtered locally, since the plugin applies its own filters. The
+//! position in each stream survives resubscribes; `from_slot` is not supported.
+
+use anyhow::Result;
+use helius_laserstream::grpc::{
+    subscribe_update::UpdateOneof, CompiledInstruction, InnerInstruction, InnerInstructions,
+    Message, MessageAddressTableLookup, MessageHeader, SlotStatus, SubscribeRequest,
+    SubscribeUpdate, SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdateSlot,
+    SubscribeUpdateTransaction, SubscribeUpdateTransactionInfo, TokenBalance, Transaction,
+    TransactionError, TransactionStatusMeta, UiTokenAmount,
+};
+use prost::Message as _;
+use redis::streams::{StreamRangeReply, StreamReadOptions, StreamReadReply};
+use redis::AsyncCommands;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+use super::{matches, UpdateStream, UpstreamSource};
+use crate::tx_error;
+
+/// `heimdall.types`, as generated into the `proto-types` package.
+#[allow(clippy::all, dead_code)]
+mod proto {
+    include!("../../../proto-types/src/heimdall.types.rs");
+}
+
+const ACCOUNTS_STREAM: &str = "heimdall:accounts";
+const SLOTS_STREAM: &str = "heimdall:slots";
+const TRANSACTIONS_STREAM: &str = "heimdall:transactions";
+const STREAMS: [&str; 3] = [ACCOUNTS_STREAM, SLOTS_STREAM, TRANSACTIONS_STREAM];
+
+pub struct GeyserRedisSource {
+    client: redis::Client,
+    /// Last entry id read from each of `STREAMS`; `None` until the first subscribe.
+    positions: Arc<Mutex<Option<Vec<String>>>>,
+}
+
+impl GeyserRedisSource {
+    pub fn from_env() -> Result<Self> {
+        let url = std::env::var("GEYSER_REDIS_URL")
+            .or_else(|_| std::env::var("REDIS_URL"))
+            .unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            positions: Arc::default(),
+        })
+    }
+}
+
+impl UpstreamSource for GeyserRedisSource {
+    fn name(&self) -> &'static str {
+        "Geyser Redis streams"
+    }
+
+    fn subscribe(&self, request: SubscribeRequest) -> UpdateStream {
+        let client = self.client.clone();
+        let positions = self.positions.clone();
+        Box::pin(async_stream::stream! {
+            let mut conn = match client.get_multiplexed_async_connection().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    yield Err(e.into());
+                    return;
+                }
+            };
+            let saved = positions.lock().unwrap().clone();
+            let mut ids = match saved {
+                Some(ids) => ids,
+                // Start at the current end of each stream.
+                None => {
+                    let mut ids = Vec::new();
+                    for stream in STREAMS {
+                        let last: redis::RedisResult<StreamRangeReply> =
+                            conn.xrevrange_count(stream, "+", "-", 1).await;
+                        match last {
+                            Ok(reply) => ids.push(
+                                reply.ids.first().map_or_else(|| "0-0".to_string(), |e| e.id.clone()),
+                            ),
+                            Err(e) => {
+                                yield Err(e.into());
+                                return;
+                            }
+                        }
+                    }
+                    ids
+                }
+            };
+
+            let opts = StreamReadOptions::default().count(500).block(5000);
+            loop {
+                let reply: StreamReadReply = match conn.xread_options(&STREAMS, &ids, &opts).await {
+                    Ok(reply) => reply,
+                    Err(e) => {
+                        yield Err(e.into());
+                        return;
+                    }
+                };
+                for key in reply.keys {
+                    let Some(i) = STREAMS.iter().position(|s| *s == key.key) else {
+                        continue;
+                    };
+                    for entry in key.ids {
+                        ids[i] = entry.id.clone();
+                        let Some(redis::Value::Data(data)) = entry.map.get("data") else {
+                            continue;
+                        };
+                        match decode(STREAMS[i], data) {
+                            Ok(mut update) => {
+                                if matches(&request, &mut update) {
+                                    yield Ok(update);
+                                }
+                            }
+                            Err(e) => tracing::warn!(error = %e, id = %entry.id, stream = STREAMS[i], "Skipping undecodable geyser message"),
+                        }
+                    }
+                }
+                *positions.lock().unwrap() = Some(ids.clone());
+            }
+        })
+    }
+}
+
+fn decode(stream: &str, data: &[u8]) -> Result<SubscribeUpdate> {
+    let update_oneof = match stream {
+        ACCOUNTS_STREAM => {
+            UpdateOneof::Account(account_update(proto::UpdateAccountEvent::decode(data)?))
+        }
+        SLOTS_STREAM => UpdateOneof::Slot(slot_update(proto::SlotStatusEvent::decode(data)?)),
+        _ => UpdateOneof::Transaction(transaction_update(proto::TransactionEvent::decode(data)?)),
+    };
+    Ok(SubscribeUpdate {
+        update_oneof: Some(update_oneof),
+        ..Default::default()
+    })
+}
+
+fn account_update(event: proto::UpdateAccountEvent) -> SubscribeUpdateAccount {
+    SubscribeUpdateAccount {
+        account: Some(SubscribeUpdateAccountInfo {
+            pubkey: event.pubkey,
+            lamports: event.lamports,
+            owner: event.owner,
+            executable: event.executable,
+            rent_epoch: event.rent_epoch,
+            data: event.data,
+            write_version: event.write_version,
+            txn_signature: event.txn_signature,
+        }),
+        slot: event.slot,
+        is_startup: false,
+    }
+}
+
+fn slot_update(event: proto::SlotStatusEvent) -> SubscribeUpdateSlot {
+    let status = match proto::SlotStatus::try_from(event.status) {
+        Ok(proto::SlotStatus::Processed) => SlotStatus::SlotProcessed,
+        Ok(proto::SlotStatus::Confirmed) => SlotStatus::SlotConfirmed,
+        Ok(proto::SlotStatus::Rooted) => SlotStatus::SlotFinalized,
+        Ok(proto::SlotStatus::FirstShredReceived) => SlotStatus::SlotFirstShredReceived,
+        Ok(proto::SlotStatus::Completed) => SlotStatus::SlotCompleted,
+        Ok(proto::SlotStatus::CreatedBank) => SlotStatus::SlotCreatedBank,
+        Ok(proto::SlotStatus::Dead) | Err(_) => SlotStatus::SlotDead,
+    };
+    SubscribeUpdateSlot {
+        slot: event.slot,
+        parent: Some(event.parent),
+        status: status as i32,
+        dead_error: None,
+    }
+}
+
+fn transaction_update(event: proto::TransactionEvent) -> SubscribeUpdateTransaction {
+    let tx = event.transaction.unwrap_or_default();
+    let (message, loaded) = match tx.message.and_then(|m| m.message_payload) {
+        Some(proto::sanitized_message::MessagePayload::Legacy(m)) => {
+            let m = m.message.unwrap_or_default();
+            let message = Message {
+                header: m.header.map(header),
+                account_keys: m.account_keys,
+                recent_blockhash: m.recent_block_hash,
+                instructions: m.instructions.into_iter().map(instruction).collect(),
+                versioned: false,
+                address_table_lookups: Vec::new(),
+            };
+            (Some(message), proto::LoadedAddresses::default())
+        }
+        Some(proto::sanitized_message::MessagePayload::V0(m)) => {
+            let loaded = m.loaded_adresses.unwrap_or_default();
+            let m = m.message.unwrap_or_default();
+            let message = Message {
+                header: m.header.map(header),
+                account_keys: m.account_keys,
+                recent_blockhash: m.recent_block_hash,
+                instructions: m.instructions.into_iter().map(instruction).collect(),
+                versioned: true,
+                address_table_lookups: m
+                    .address_table_lookup
+                    .into_iter()
+                    .map(|l| MessageAddressTableLookup {
+                        account_key: l.account_key,
+                        writable_indexes: l.writable_indexes.into_iter().map(|i| i as u8).collect(),
+                        readonly_indexes: l.readonly_indexes.into_iter().map(|i| i as u8).collect(),
+                    })
+                    .collect(),
+            };
+            (Some(message), loaded)
+        }
+        None => (None, proto::LoadedAddresses::default()),
+    };
+
+    let meta = event.transaction_status_meta.unwrap_or_default();
+    let token_balances = |balances: Vec<proto::TransactionTokenBalance>| -> Vec<TokenBalance> {
+        balances
+            .into_iter()
+            .map(|b| TokenBalance {
+                account_index: b.account_index,
+                mint: b.mint,
+                ui_token_amount: b.ui_token_account.map(|a| UiTokenAmount {
+                    ui_amount: a.ui_amount.unwrap_or_default(),
+                    decimals: a.decimals,
+                    amount: a.amount,
+                    ui_amount_string: a.ui_amount_string,
+                }),
+                owner: b.owner,
+                program_id: String::new(),
+            })
+            .collect()
+    };
+    let meta = TransactionStatusMeta {
+        err: meta.is_status_err.then(|| TransactionError {
+            err: tx_error::encode_json(&debug_error_json(&meta.error_info)),
+        }),
+        fee: meta.fee,
+        pre_balances: meta.pre_balances,
+        post_balances: meta.post_balances,
+        inner_instructions: meta
+            .inner_instructions
+            .into_iter()
+            .map(|list| InnerInstructions {
+                index: list.index,
+                instructions: list
+                    .instructions
+                    .into_iter()
+                    .map(|ix| {
+                        let compiled = instruction(ix.instruction.unwrap_or_default());
+                        InnerInstruction {
+                            program_id_index: compiled.program_id_index,
+                            accounts: compiled.accounts,
+                            data: compiled.data,
+                            stack_height: ix.stack_height,
+                        }
+                    })
+                    .collect(),
+            })
+            .collect(),
+        log_messages: meta.log_messages,
+        pre_token_balances: token_balances(meta.pre_token_balances),
+        post_token_balances: token_balances(meta.post_token_balances),
+        loaded_writable_addresses: loaded.writable,
+        loaded_readonly_addresses: loaded.readonly,
+        ..Default::default()
+    };
+
+    SubscribeUpdateTransaction {
+        transaction: Some(SubscribeUpdateTransactionInfo {
+            signature: event.signature,
+            is_vote: event.is_vote,
+            transaction: Some(Transaction {
+                signatures: tx.signatures,
+                message,
+            }),
+            meta: Some(meta),
+            index: event.index,
+        }),
+        slot: event.slot,
+    }
+}
+
+fn header(h: proto::MessageHeader) -> MessageHeader {
+    MessageHeader {
+        num_required_signatures: h.num_required_signatures,
+        num_readonly_signed_accounts: h.num_readonly_signed_accounts,
+        num_readonly_unsigned_accounts: h.num_readonly_unsigned_accounts,
+    }
+}
+
+fn instruction(ix: proto::CompiledInstruction) -> CompiledInstruction {
+    CompiledInstruction {
+        program_id_index: ix.program_id_index,
+        accounts: ix.accounts.into_iter().map(|a| a as u8).collect(),
+        data: ix.data,
+    }
+}
+
+/// The plugin reports errors in `Debug` form, e.g. `InstructionError(2, Custom(6001))`;
+/// rebuild the RPC JSON shape `tx_error::encode_json` understands.
+fn debug_error_json(info: &str) -> Value {
+    let Some(args) = info
+        .strip_prefix("InstructionError(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    else {
+        return json!(info
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .unwrap_or_default());
+    };
+    let Some((index, ix_err)) = args.split_once(',') else {
+        return json!("InstructionError");
+    };
+    let index: u64 = index.trim().parse().unwrap_or_default();
+    let ix_err = ix_err.trim();
+    let ix_err = match ix_err
+        .strip_prefix("Custom(")
+        .and_then(|code| code.strip_suffix(')'))
+    {
+        Some(code) => json!({ "Custom": code.parse::<u64>().unwrap_or_default() }),
+        None => json!(ix_err
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .unwrap_or_default()),
+    };
+    json!({ "InstructionError": [index, ix_err] })
+}
@@ -0,0 +1,38 @@
+//! Helius LaserStream, which reconnects by itself and resumes from its tracked slot.
+
+use anyhow::Result;
+use futures_util::StreamExt;
+use helius_laserstream::{grpc::SubscribeRequest, LaserstreamConfig};
+
+use super::{UpdateStream, UpstreamSource};
+
+pub struct LaserStreamSource {
+    config: LaserstreamConfig,
+}
+
+impl LaserStreamSource {
+    pub fn from_env() -> Result<Self> {
+        let api_key = std::env::var("HELIUS_API_KEY").unwrap_or_else(|_| "demo".to_string());
+        let endpoint_url = std::env::var("HELIUS_ENDPOINT")
+            .unwrap_or_else(|_| "https://laserstream-devnet.helius-rpc.com".to_string());
+        Ok(Self {
+            config: LaserstreamConfig {
+                api_key,
+                endpoint: endpoint_url.parse()?,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+impl UpstreamSource for LaserStreamSource {
+    fn name(&self) -> &'static str {
+        "Helius LaserStream"
+    }
+
+    fn subscribe(&self, request: SubscribeRequest) -> UpdateStream {
+        helius_laserstream::subscribe(self.config.clone(), request)
+            .map(|update| update.map_err(Into::into))
+            .boxed()
+    }
+}
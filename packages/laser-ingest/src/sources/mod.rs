@@ -0,0 +1,125 @@
+//! Upstream feeds of `SubscribeUpdate`s.
+//!
+//! Processors only ever see Yellowstone `SubscribeUpdate`s, so any feed that can be turned
+//! into them drives the same processors. `UPSTREAM_SOURCE` picks one:
+//!
+//! * `laserstream` (default) – Helius LaserStream (`HELIUS_API_KEY`, `HELIUS_ENDPOINT`).
+//! * `yellowstone` – any Yellowstone Dragon's Mouth gRPC endpoint (`YELLOWSTONE_ENDPOINT`,
+//!   optional `YELLOWSTONE_X_TOKEN`).
+//! * `geyser-redis` – the `heimdall:accounts/slots/transactions` protobuf streams written by
+//!   the Geyser-to-Redis plugin (`config/config.json`), on `GEYSER_REDIS_URL` (default
+//!   `REDIS_URL`).
+//! * `websocket` – Solana RPC `logsSubscribe`/`accountSubscribe` on `WS_RPC_URL`, with
+//!   transactions fetched from `RPC_URL`.
+//! * `replay` – updates recorded with `RECORD_UPDATES`, read from `REPLAY_FILE`.
+//!
+//! Sources that can't filter upstream apply the request's filters locally, see [`matches`].
+
+use anyhow::{bail, Result};
+use futures_util::Stream;
+use helius_laserstream::grpc::{
+    subscribe_update::UpdateOneof, SubscribeRequest, SubscribeRequestFilterTransactions,
+    SubscribeUpdate, SubscribeUpdateTransactionInfo,
+};
+use std::pin::Pin;
+
+use crate::processors::account_keys;
+
+pub mod geyser_redis;
+pub mod laserstream;
+pub mod replay;
+pub mod websocket;
+pub mod yellowstone;
+
+pub type UpdateStream = Pin<Box<dyn Stream<Item = Result<SubscribeUpdate>> + Send>>;
+
+pub trait UpstreamSource: Send + Sync {
+    /// Human readable name, for logs.
+    fn name(&self) -> &'static str;
+
+    /// Stream the updates selected by `request`. An `Err` item is a recoverable error the
+    /// source handles itself; the stream ends when the source gives up, after which the
+    /// caller backs off and subscribes again.
+    fn subscribe(&self, request: SubscribeRequest) -> UpdateStream;
+}
+
+/// The source selected by `UPSTREAM_SOURCE`.
+pub fn from_env() -> Result<Box<dyn UpstreamSource>> {
+    let kind = std::env::var("UPSTREAM_SOURCE").unwrap_or_else(|_| "laserstream".to_string());
+    Ok(match kind.as_str() {
+        "laserstream" => Box::new(laserstream::LaserStreamSource::from_env()?),
+        "yellowstone" => Box::new(yellowstone::YellowstoneSource::from_env()?),
+        "geyser-redis" => Box::new(geyser_redis::GeyserRedisSource::from_env()?),
+        "websocket" => Box::new(websocket::WebsocketSource::from_env()?),
+        "replay" => Box::new(replay::ReplaySource::from_env()?),
+        other => bail!("unknown UPSTREAM_SOURCE {}", other),
+    })
+}
+
+/// Apply `request`'s account and transaction filters to `update` the way a Yellowstone
+/// server would, setting `update.filters` to the names of the matching filters. Returns
+/// whether any matched. Account data filters (memcmp, size) are not evaluated.
+pub fn matches(request: &SubscribeRequest, update: &mut SubscribeUpdate) -> bool {
+    let filters: Vec<String> = match &update.update_oneof {
+        Some(UpdateOneof::Account(a)) => {
+            let Some(account) = &a.account else {
+                return false;
+            };
+            let pubkey = bs58::encode(&account.pubkey).into_string();
+            let owner = bs58::encode(&account.owner).into_string();
+            request
+                .accounts
+                .iter()
+                .filter(|(_, f)| {
+                    (f.account.is_empty() || f.account.contains(&pubkey))
+                        && (f.owner.is_empty() || f.owner.contains(&owner))
+                })
+                .map(|(name, _)| name.clone())
+                .collect()
+        }
+        Some(UpdateOneof::Transaction(t)) => {
+            let Some(info) = &t.transaction else {
+                return false;
+            };
+            request
+                .transactions
+                .iter()
+                .filter(|(_, f)| transaction_matches(f, info))
+                .map(|(name, _)| name.clone())
+                .collect()
+        }
+        Some(UpdateOneof::Slot(_)) => request.slots.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+    if filters.is_empty() {
+        return false;
+    }
+    update.filters = filters;
+    true
+}
+
+fn transaction_matches(
+    filter: &SubscribeRequestFilterTransactions,
+    info: &SubscribeUpdateTransactionInfo,
+) -> bool {
+    let (Some(tx), Some(meta)) = (&info.transaction, &info.meta) else {
+        return false;
+    };
+    let Some(message) = &tx.message else {
+        return false;
+    };
+    if filter.vote.is_some_and(|vote| vote != info.is_vote)
+        || filter
+            .failed
+            .is_some_and(|failed| failed != meta.err.is_some())
+    {
+        return false;
+    }
+    let keys: Vec<String> = account_keys(message, meta)
+        .into_iter()
+        .map(|k| bs58::encode(k).into_string())
+        .collect();
+    (filter.account_include.is_empty() || filter.account_include.iter().any(|a| keys.contains(a)))
+        && !filter.account_exclude.iter().any(|a| keys.contains(a))
+        && filter.account_required.iter().all(|a| keys.contains(a))
+}
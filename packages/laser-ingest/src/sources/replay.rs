@@ -0,0 +1,100 @@
+//! Updates recorded with `RECORD_UPDATES` (see `recorder`), read back from `REPLAY_FILE`.
+//!
+//! The file is replayed once, as fast as the processors keep up, with the request's filters
+//! applied locally; the stream then stays open without further updates, so the worker keeps
+//! running instead of replaying it again. A truncated last record is ignored.
+
+use anyhow::{Context, Result};
+use helius_laserstream::grpc::{SubscribeRequest, SubscribeUpdate};
+use prost::Message as _;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+use super::{matches, UpdateStream, UpstreamSource};
+
+pub struct ReplaySource {
+    path: String,
+    /// Keep the stream open once the file is exhausted, instead of ending it.
+    idle_at_end: bool,
+}
+
+impl ReplaySource {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            path: std::env::var("REPLAY_FILE").context("REPLAY_FILE")?,
+            idle_at_end: true,
+        })
+    }
+
+    /// Replay `path` once; the stream ends with the file.
+    #[cfg(test)]
+    pub fn once(path: String) -> Self {
+        Self {
+            path,
+            idle_at_end: false,
+        }
+    }
+}
+
+impl UpstreamSource for ReplaySource {
+    fn name(&self) -> &'static str {
+        "recorded updates"
+    }
+
+    fn subscribe(&self, request: SubscribeRequest) -> UpdateStream {
+        let path = self.path.clone();
+        let idle_at_end = self.idle_at_end;
+        Box::pin(async_stream::stream! {
+            let file = match tokio::fs::File::open(&path).await {
+                Ok(file) => file,
+                Err(e) => {
+                    yield Err(anyhow::Error::new(e).context(format!("opening {}", path)));
+                    return;
+                }
+            };
+            let mut reader = BufReader::new(file);
+            let mut replayed = 0usize;
+            loop {
+                match read_update(&mut reader).await {
+                    Ok(Some(mut update)) => {
+                        if matches(&request, &mut update) {
+                            replayed += 1;
+                            yield Ok(update);
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        yield Err(e.context(format!("reading {}", path)));
+                        break;
+                    }
+                }
+            }
+            tracing::info!(path = %path, replayed, "Replay finished");
+            if idle_at_end {
+                std::future::pending::<()>().await;
+            }
+        })
+    }
+}
+
+/// Next length-delimited update of `reader`; `None` at the end of the file, including
+/// inside a truncated record.
+async fn read_update<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<SubscribeUpdate>> {
+    let mut len = 0usize;
+    for shift in (0..64).step_by(7) {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        len |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut buf = vec![0; len];
+    match reader.read_exact(&mut buf).await {
+        Ok(_) => Ok(Some(SubscribeUpdate::decode(buf.as_slice())?)),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
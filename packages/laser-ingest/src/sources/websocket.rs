@@ -0,0 +1,372 @@
+//! Solana RPC websocket subscriptions, for when no gRPC feed is available.
+//!
+//! Every account of the request's account filters gets an `accountSubscribe`, and every
+//! address of its transaction filters a `logsSubscribe` (`mentions`). Log notifications only
+//! carry the signature, so each transaction is then fetched with `getTransaction` from
+//! `RPC_URL` (default: `WS_RPC_URL` with `ws` replaced by `http`). Everything runs at
+//! `confirmed` commitment; owner-only account filters and `from_slot` are not supported.
+//!
+//! Up to `WS_FETCH_CONCURRENCY` (default 8) transactions are fetched at once while the
+//! socket keeps being read; updates are still yielded in notification order.
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine as _;
+use futures_util::stream::FuturesOrdered;
+use futures_util::{SinkExt, StreamExt};
+use helius_laserstream::grpc::{
+    subscribe_update::UpdateOneof, SubscribeRequest, SubscribeUpdate, SubscribeUpdateAccount,
+    SubscribeUpdateAccountInfo,
+};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use super::{matches, UpdateStream, UpstreamSource};
+use crate::rpc::RpcClient;
+
+/// Signatures remembered to fetch a transaction mentioning several addresses only once.
+const RECENT_SIGNATURES: usize = 10_000;
+
+/// An update in notification order: ready for account notifications, a `getTransaction`
+/// for log notifications. `None` when the transaction could not be fetched.
+type PendingUpdate = Pin<Box<dyn Future<Output = Result<Option<SubscribeUpdate>>> + Send>>;
+
+pub struct WebsocketSource {
+    ws_url: String,
+    rpc: RpcClient,
+    fetch_concurrency: usize,
+}
+
+impl WebsocketSource {
+    pub fn from_env() -> Result<Self> {
+        let ws_url = std::env::var("WS_RPC_URL").context("WS_RPC_URL")?;
+        let rpc_url = std::env::var("RPC_URL").unwrap_or_else(|_| ws_url.replacen("ws", "http", 1));
+        let fetch_concurrency = match std::env::var("WS_FETCH_CONCURRENCY") {
+            Ok(v) => v.parse().context("WS_FETCH_CONCURRENCY")?,
+            Err(_) => 8,
+        };
+        Ok(Self {
+            ws_url,
+            rpc: RpcClient::new(rpc_url),
+            fetch_concurrency: usize::max(fetch_concurrency, 1),
+        })
+    }
+}
+
+impl UpstreamSource for WebsocketSource {
+    fn name(&self) -> &'static str {
+        "RPC websocket"
+    }
+
+    fn subscribe(&self, request: SubscribeRequest) -> UpdateStream {
+        let ws_url = self.ws_url.clone();
+        let rpc = self.rpc.clone();
+        let fetch_concurrency = self.fetch_concurrency;
+        let accounts: HashSet<String> = request
+            .accounts
+            .values()
+            .flat_map(|f| f.account.iter().cloned())
+            .collect();
+        let mentions: HashSet<String> = request
+            .transactions
+            .values()
+            .flat_map(|f| f.account_include.iter().chain(&f.account_required).cloned())
+            .collect();
+        if request.accounts.values().any(|f| f.account.is_empty()) {
+            tracing::warn!("Owner-only account filters are not supported over websockets");
+        }
+
+        Box::pin(async_stream::stream! {
+            let (ws, _) = match connect_async(ws_url.as_str()).await {
+                Ok(ws) => ws,
+                Err(e) => {
+                    yield Err(anyhow!(e).context("connecting to RPC websocket"));
+                    return;
+                }
+            };
+            let (mut write, mut read) = ws.split();
+
+            // Request id -> subscribed account, then subscription id -> account.
+            let mut pending_accounts = HashMap::new();
+            let mut subscribed_accounts = HashMap::new();
+            let mut id = 0u64;
+            let mut requests = Vec::new();
+            for account in &accounts {
+                id += 1;
+                pending_accounts.insert(id, account.clone());
+                requests.push(json!({
+                    "jsonrpc": "2.0", "id": id, "method": "accountSubscribe",
+                    "params": [account, { "encoding": "base64", "commitment": "confirmed" }],
+                }));
+            }
+            for address in &mentions {
+                id += 1;
+                requests.push(json!({
+                    "jsonrpc": "2.0", "id": id, "method": "logsSubscribe",
+                    "params": [{ "mentions": [address] }, { "commitment": "confirmed" }],
+                }));
+            }
+            for request in requests {
+                if let Err(e) = write.send(Message::Text(request.to_string())).await {
+                    yield Err(e.into());
+                    return;
+                }
+            }
+
+            let mut recent = VecDeque::new();
+            let mut seen = HashSet::new();
+            let mut pending: FuturesOrdered<PendingUpdate> = FuturesOrdered::new();
+            let mut reading = true;
+            loop {
+                // Stop reading while `fetch_concurrency` updates are pending; drain what is
+                // left once the socket closes.
+                let message = tokio::select! {
+                    Some(fetched) = pending.next() => {
+                        match fetched {
+                            Ok(Some(mut update)) => {
+                                if matches(&request, &mut update) {
+                                    yield Ok(update);
+                                }
+                            }
+                            Ok(None) => {}
+                            Err(e) => yield Err(e),
+                        }
+                        continue;
+                    }
+                    message = read.next(), if reading && pending.len() < fetch_concurrency => message,
+                    else => break,
+                };
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    None | Some(Ok(Message::Close(_))) => {
+                        reading = false;
+                        continue;
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        yield Err(e.into());
+                        return;
+                    }
+                };
+                let Ok(msg) = serde_json::from_str::<Value>(&text) else {
+                    continue;
+                };
+                if let Some(err) = msg.get("error") {
+                    yield Err(anyhow!("subscription failed: {}", err));
+                    continue;
+                }
+                if let (Some(request_id), Some(subscription)) = (msg["id"].as_u64(), msg["result"].as_u64()) {
+                    if let Some(account) = pending_accounts.remove(&request_id) {
+                        subscribed_accounts.insert(subscription, account);
+                    }
+                    continue;
+                }
+                let params = &msg["params"];
+                match msg["method"].as_str() {
+                    Some("accountNotification") => {
+                        let Some(pubkey) = params["subscription"].as_u64().and_then(|s| subscribed_accounts.get(&s)) else {
+                            continue;
+                        };
+                        let update = account_update(pubkey, &params["result"]).map(Some);
+                        pending.push_back(Box::pin(std::future::ready(update)));
+                    }
+                    Some("logsNotification") => {
+                        let Some(signature) = params["result"]["value"]["signature"].as_str() else {
+                            continue;
+                        };
+                        if !seen.insert(signature.to_owned()) {
+                            continue;
+                        }
+                        recent.push_back(signature.to_owned());
+                        if recent.len() > RECENT_SIGNATURES {
+                            if let Some(old) = recent.pop_front() {
+                                seen.remove(&old);
+                            }
+                        }
+                        let rpc = rpc.clone();
+                        let signature = signature.to_owned();
+                        pending.push_back(Box::pin(async move {
+                            let fetched = fetch_transaction(&rpc, &signature).await;
+                            if let Ok(None) = fetched {
+                                tracing::warn!(signature, "Transaction not available over RPC, skipping");
+                            }
+                            fetched
+                        }));
+                    }
+                    _ => {}
+                }
+            }
+        })
+    }
+}
+
+/// `getTransaction` right after a `confirmed` notification can miss; retry briefly.
+async fn fetch_transaction(rpc: &RpcClient, signature: &str) -> Result<Option<SubscribeUpdate>> {
+    for _ in 0..3 {
+        if let Some(update) = rpc.transaction(signature).await? {
+            return Ok(Some(update));
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    Ok(None)
+}
+
+fn account_update(pubkey: &str, result: &Value) -> Result<SubscribeUpdate> {
+    let value = &result["value"];
+    let data = value["data"][0]
+        .as_str()
+        .ok_or_else(|| anyhow!("account notification without base64 data"))?;
+    let account = SubscribeUpdateAccountInfo {
+        pubkey: bs58::decode(pubkey).into_vec()?,
+        lamports: value["lamports"].as_u64().unwrap_or_default(),
+        owner: bs58::decode(value["owner"].as_str().unwrap_or_default()).into_vec()?,
+        executable: value["executable"].as_bool().unwrap_or_default(),
+        rent_epoch: value["rentEpoch"].as_u64().unwrap_or_default(),
+        data: base64::engine::general_purpose::STANDARD.decode(data)?,
+        write_version: 0,
+        txn_signature: None,
+    };
+    Ok(SubscribeUpdate {
+        update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+            account: Some(account),
+            slot: result["context"]["slot"].as_u64().unwrap_or_default(),
+            is_startup: false,
+        })),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helius_laserstream::grpc::SubscribeRequestFilterTransactions;
+    use poem::{handler, listener::TcpAcceptor, post, web::Json, Route, Server};
+    use std::time::Instant;
+    use tokio::net::TcpListener;
+
+    const RPC_DELAY: Duration = Duration::from_millis(400);
+
+    fn sig(n: u8) -> String {
+        bs58::encode([n; 64]).into_string()
+    }
+
+    fn key(n: u8) -> String {
+        bs58::encode([n; 32]).into_string()
+    }
+
+    /// Every transaction takes `RPC_DELAY` to fetch.
+    #[handler]
+    async fn mock_rpc(Json(req): Json<Value>) -> Json<Value> {
+        assert_eq!(req["method"], "getTransaction");
+        tokio::time::sleep(RPC_DELAY).await;
+        let signature = req["params"][0].as_str().unwrap();
+        let n = (1..=3).find(|n| sig(*n) == signature).unwrap();
+        let result = json!({
+            "slot": 100 + n as u64,
+            "version": 0,
+            "transaction": {
+                "signatures": [signature],
+                "message": {
+                    "header": {
+                        "numRequiredSignatures": 1,
+                        "numReadonlySignedAccounts": 0,
+                        "numReadonlyUnsignedAccounts": 1
+                    },
+                    "accountKeys": [key(1), key(2)],
+                    "recentBlockhash": key(9),
+                    "instructions": [],
+                    "addressTableLookups": []
+                }
+            },
+            "meta": {
+                "err": null,
+                "fee": 5000,
+                "preBalances": [10, 1],
+                "postBalances": [5, 1],
+                "innerInstructions": [],
+                "logMessages": [],
+                "preTokenBalances": [],
+                "postTokenBalances": [],
+                "loadedAddresses": { "writable": [], "readonly": [] }
+            }
+        });
+        Json(json!({ "jsonrpc": "2.0", "id": req["id"], "result": result }))
+    }
+
+    /// Accepts one connection, answers its `logsSubscribe`, sends three log
+    /// notifications back to back and closes.
+    async fn mock_websocket(listener: TcpListener) {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        let Some(Ok(Message::Text(subscribe))) = ws.next().await else {
+            panic!("expected logsSubscribe");
+        };
+        let subscribe: Value = serde_json::from_str(&subscribe).unwrap();
+        let reply = json!({ "jsonrpc": "2.0", "id": subscribe["id"], "result": 7 });
+        ws.send(Message::Text(reply.to_string())).await.unwrap();
+        for n in 1..=3 {
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "logsNotification",
+                "params": {
+                    "subscription": 7,
+                    "result": { "value": { "signature": sig(n), "err": null, "logs": [] } }
+                }
+            });
+            ws.send(Message::Text(notification.to_string()))
+                .await
+                .unwrap();
+        }
+        ws.close(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn fetches_transactions_concurrently_in_notification_order() {
+        let rpc_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rpc_addr = rpc_listener.local_addr().unwrap();
+        let acceptor = TcpAcceptor::from_tokio(rpc_listener).unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(Route::new().at("/", post(mock_rpc))));
+        let ws_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_addr = ws_listener.local_addr().unwrap();
+        tokio::spawn(mock_websocket(ws_listener));
+
+        let source = WebsocketSource {
+            ws_url: format!("ws://{}", ws_addr),
+            rpc: RpcClient::new(format!("http://{}", rpc_addr)),
+            fetch_concurrency: 8,
+        };
+        let request = SubscribeRequest {
+            transactions: HashMap::from([(
+                "pool".to_owned(),
+                SubscribeRequestFilterTransactions {
+                    account_include: vec![key(1)],
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+        let updates: Vec<SubscribeUpdate> = source
+            .subscribe(request)
+            .map(|update| update.unwrap())
+            .collect()
+            .await;
+        let elapsed = started.elapsed();
+
+        let slots: Vec<u64> = updates
+            .iter()
+            .map(|u| match &u.update_oneof {
+                Some(UpdateOneof::Transaction(tx)) => tx.slot,
+                other => panic!("expected transaction, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(slots, [101, 102, 103]);
+        assert!(updates.iter().all(|u| u.filters == ["pool"]));
+        // Fetched one after another this would take at least 3 * RPC_DELAY.
+        assert!(elapsed < RPC_DELAY * 2, "took {:?}", elapsed);
+    }
+}
@@ -0,0 +1,79 @@
+//! Any Yellowstone Dragon's Mouth gRPC endpoint.
+//!
+//! Unlike LaserStream there is no client-side reconnect: the stream ends on the first error
+//! and the worker resubscribes, resuming `from_slot` where the provider supports it.
+
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use helius_laserstream::grpc::{
+    subscribe_update::UpdateOneof, SubscribeRequest, SubscribeRequestPing,
+};
+use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
+
+use super::{UpdateStream, UpstreamSource};
+
+pub struct YellowstoneSource {
+    endpoint: String,
+    x_token: Option<String>,
+}
+
+impl YellowstoneSource {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            endpoint: std::env::var("YELLOWSTONE_ENDPOINT").context("YELLOWSTONE_ENDPOINT")?,
+            x_token: std::env::var("YELLOWSTONE_X_TOKEN").ok(),
+        })
+    }
+}
+
+impl UpstreamSource for YellowstoneSource {
+    fn name(&self) -> &'static str {
+        "Yellowstone gRPC"
+    }
+
+    fn subscribe(&self, request: SubscribeRequest) -> UpdateStream {
+        let endpoint = self.endpoint.clone();
+        let x_token = self.x_token.clone();
+        Box::pin(async_stream::stream! {
+            let connected = async {
+                let mut builder = GeyserGrpcClient::build_from_shared(endpoint.clone())?
+                    .x_token(x_token)?;
+                if endpoint.starts_with("https") {
+                    builder = builder.tls_config(ClientTlsConfig::new().with_enabled_roots())?;
+                }
+                let mut client = builder.connect().await?;
+                let subscription = client.subscribe_with_request(Some(request)).await?;
+                anyhow::Ok(subscription)
+            };
+            let (mut sink, updates) = match connected.await {
+                Ok(subscription) => subscription,
+                Err(e) => {
+                    yield Err(e.context("connecting to Yellowstone"));
+                    return;
+                }
+            };
+            futures_util::pin_mut!(updates);
+            while let Some(update) = updates.next().await {
+                match update {
+                    // Answer server pings so load balancers keep the stream open.
+                    Ok(update) if matches!(update.update_oneof, Some(UpdateOneof::Ping(_))) => {
+                        let ping = SubscribeRequest {
+                            ping: Some(SubscribeRequestPing { id: 1 }),
+                            ..Default::default()
+                        };
+                        if let Err(e) = sink.send(ping).await {
+                            yield Err(e.into());
+                            return;
+                        }
+                    }
+                    Ok(update) if matches!(update.update_oneof, Some(UpdateOneof::Pong(_))) => {}
+                    Ok(update) => yield Ok(update),
+                    Err(status) => {
+                        yield Err(status.into());
+                        return;
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,139 @@
+//! Test support: an in-process stand-in for the Redis commands processors publish with.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Speaks just enough RESP for `RedisStreamSink`: `XADD <stream> * data <json>` and the
+/// `XADD_IF_ABSENT` script (`EVALSHA <sha> 2 <stream> <dedup key> <ttl> <json>`), whose
+/// semantics it imitates. It also keeps `GET`/`SET` values and imitates the leader lease
+/// script (`EVALSHA <sha> 1 <key> <id> <ttl>`), without expiry. Everything else is
+/// answered with `OK`.
+pub struct FakeRedis {
+    pub client: redis::Client,
+    published: Arc<Mutex<Vec<String>>>,
+}
+
+#[derive(Default)]
+struct State {
+    published: Arc<Mutex<Vec<String>>>,
+    dedup_keys: Mutex<HashSet<Vec<u8>>>,
+    values: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl FakeRedis {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(State::default());
+        let published = state.published.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve(socket, state.clone()));
+            }
+        });
+        Self {
+            client: redis::Client::open(format!("redis://{}", addr)).unwrap(),
+            published,
+        }
+    }
+
+    /// Event JSON added to any stream, in order, exactly as it was sent.
+    pub fn published(&self) -> Vec<String> {
+        self.published.lock().unwrap().clone()
+    }
+}
+
+async fn serve(socket: TcpStream, state: Arc<State>) {
+    let (read, mut write) = socket.into_split();
+    let mut read = BufReader::new(read);
+    while let Some(args) = read_command(&mut read).await {
+        let command = String::from_utf8_lossy(&args[0]).to_ascii_uppercase();
+        let data = match command.as_str() {
+            "XADD" => args.get(4),
+            "GET" => {
+                let reply = match state.values.lock().unwrap().get(&args[1]) {
+                    Some(value) => bulk(value),
+                    None => b"$-1\r\n".to_vec(),
+                };
+                write.write_all(&reply).await.unwrap();
+                continue;
+            }
+            "SET" => {
+                state
+                    .values
+                    .lock()
+                    .unwrap()
+                    .insert(args[1].clone(), args[2].clone());
+                write.write_all(b"+OK\r\n").await.unwrap();
+                continue;
+            }
+            "EVALSHA" | "EVAL" if args[2] == b"1" => {
+                let acquired = {
+                    let mut values = state.values.lock().unwrap();
+                    let held_by_other = values
+                        .get(&args[3])
+                        .is_some_and(|holder| *holder != args[4]);
+                    if !held_by_other {
+                        values.insert(args[3].clone(), args[4].clone());
+                    }
+                    !held_by_other
+                };
+                let reply: &[u8] = if acquired { b":1\r\n" } else { b":0\r\n" };
+                write.write_all(reply).await.unwrap();
+                continue;
+            }
+            "EVALSHA" | "EVAL" => {
+                let dedup_key = args[4].clone();
+                if state.dedup_keys.lock().unwrap().insert(dedup_key) {
+                    args.get(6)
+                } else {
+                    write.write_all(b"$-1\r\n").await.unwrap();
+                    continue;
+                }
+            }
+            _ => {
+                write.write_all(b"+OK\r\n").await.unwrap();
+                continue;
+            }
+        };
+        let data = data.expect("XADD without data");
+        state
+            .published
+            .lock()
+            .unwrap()
+            .push(String::from_utf8(data.clone()).unwrap());
+        write.write_all(b"$3\r\n1-0\r\n").await.unwrap();
+    }
+}
+
+fn bulk(value: &[u8]) -> Vec<u8> {
+    let mut reply = format!("${}\r\n", value.len()).into_bytes();
+    reply.extend_from_slice(value);
+    reply.extend_from_slice(b"\r\n");
+    reply
+}
+
+/// One command (an array of bulk strings); `None` once the client disconnects.
+async fn read_command<R: AsyncBufReadExt + Unpin>(read: &mut R) -> Option<Vec<Vec<u8>>> {
+    let count = read_header(read, b'*').await?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = read_header(read, b'$').await?;
+        let mut arg = vec![0; len + 2];
+        read.read_exact(&mut arg).await.ok()?;
+        arg.truncate(len);
+        args.push(arg);
+    }
+    Some(args)
+}
+
+async fn read_header<R: AsyncBufReadExt + Unpin>(read: &mut R, kind: u8) -> Option<usize> {
+    let mut line = String::new();
+    if read.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let line = line.trim_end().strip_prefix(kind as char)?;
+    line.parse().ok()
+}
@@ -0,0 +1,420 @@
+//! Token metadata cache.
+//!
+//! Learns mint decimals, symbol and name from what flows through the stream:
+//! * `pre/post_token_balances` of every transaction (mint + decimals),
+//! * SPL Token / Token-2022 mint accounts (decimals),
+//! * Metaplex token metadata accounts (name + symbol),
+//! * processors that see the metadata in instruction arguments (e.g. DBC pool creation),
+//!
+//! and can be seeded from a JSON snapshot (`TOKEN_SNAPSHOT_JSON`) so amounts are
+//! resolvable straight after a restart. Mint and metadata accounts only arrive for pools
+//! whose processor subscribes to them via [`TokenCache::account_filters`].
+//!
+//! The cache holds at most `TOKEN_CACHE_CAPACITY` mints (default 100 000); the least
+//! recently used ones are evicted first and relearned when they show up again.
+
+use anyhow::{Context, Result};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use helius_laserstream::grpc::{
+    subscribe_update::UpdateOneof, SubscribeRequestFilterAccounts, SubscribeUpdate,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+const METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+/// Size of the base SPL mint layout; Token-2022 mints with extensions are longer.
+const MINT_LEN: usize = 82;
+/// Offset of the account-type byte Token-2022 appends after the base account layout.
+const TOKEN_2022_ACCOUNT_TYPE_OFFSET: usize = 165;
+const TOKEN_2022_ACCOUNT_TYPE_MINT: u8 = 1;
+/// `Key::MetadataV1`
+const METADATA_V1_KEY: u8 = 4;
+const DEFAULT_CAPACITY: usize = 100_000;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TokenInfo {
+    pub mint: String,
+    #[serde(default)]
+    pub decimals: Option<u8>,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Shared, cheaply clonable token metadata cache.
+#[derive(Clone, Debug)]
+pub struct TokenCache {
+    inner: Arc<Mutex<Lru>>,
+}
+
+/// Mints with the tick of their last use.
+#[derive(Debug)]
+struct Lru {
+    tokens: HashMap<String, (TokenInfo, u64)>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl Default for TokenCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl TokenCache {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Lru {
+                tokens: HashMap::new(),
+                tick: 0,
+                capacity: capacity.max(1),
+            })),
+        }
+    }
+
+    /// Cache seeded with wrapped SOL and, if `TOKEN_SNAPSHOT_JSON` is set, the snapshot.
+    pub fn from_env() -> Result<Self> {
+        let cache = match std::env::var("TOKEN_CACHE_CAPACITY") {
+            Ok(v) => Self::with_capacity(v.parse().context("TOKEN_CACHE_CAPACITY")?),
+            Err(_) => Self::default(),
+        };
+        cache.learn(TokenInfo {
+            mint: WSOL_MINT.to_owned(),
+            decimals: Some(9),
+            symbol: Some("SOL".to_owned()),
+            name: Some("Wrapped SOL".to_owned()),
+        });
+        if let Ok(path) = std::env::var("TOKEN_SNAPSHOT_JSON") {
+            let data = std::fs::read_to_string(&path)
+                .with_context(|| format!("reading token snapshot {}", path))?;
+            let tokens: Vec<TokenInfo> = serde_json::from_str(&data)
+                .with_context(|| format!("parsing token snapshot {}", path))?;
+            tracing::info!(tokens = tokens.len(), path, "Loaded token snapshot");
+            for token in tokens {
+                cache.learn(token);
+            }
+        }
+        Ok(cache)
+    }
+
+    pub fn get(&self, mint: &str) -> Option<TokenInfo> {
+        self.inner.lock().unwrap().touch(mint).cloned()
+    }
+
+    pub fn decimals(&self, mint: &str) -> Option<u8> {
+        self.inner.lock().unwrap().touch(mint)?.decimals
+    }
+
+    /// `raw / 10^decimals`, if the mint's decimals are known.
+    pub fn ui_amount(&self, mint: &str, raw: u64) -> Option<f64> {
+        self.decimals(mint)
+            .map(|d| raw as f64 / 10f64.powi(d as i32))
+    }
+
+    /// Merge what we know about a mint; fields already known are only overwritten by
+    /// new non-empty values.
+    pub fn learn(&self, info: TokenInfo) {
+        let mut lru = self.inner.lock().unwrap();
+        if !lru.tokens.contains_key(&info.mint) && lru.tokens.len() >= lru.capacity {
+            lru.evict();
+        }
+        lru.tick += 1;
+        let tick = lru.tick;
+        let (entry, used) = lru.tokens.entry(info.mint.clone()).or_insert_with(|| {
+            let entry = TokenInfo {
+                mint: info.mint.clone(),
+                ..Default::default()
+            };
+            (entry, tick)
+        });
+        *used = tick;
+        if info.decimals.is_some() {
+            entry.decimals = info.decimals;
+        }
+        if info.symbol.is_some() {
+            entry.symbol = info.symbol;
+        }
+        if info.name.is_some() {
+            entry.name = info.name;
+        }
+    }
+
+    fn learn_decimals(&self, mint: String, decimals: u8) {
+        if self.decimals(&mint) == Some(decimals) {
+            return;
+        }
+        self.learn(TokenInfo {
+            mint,
+            decimals: Some(decimals),
+            ..Default::default()
+        });
+    }
+
+    /// Account filters for `mints` and their Metaplex metadata accounts, so their
+    /// decimals, name and symbol are learned once the pool is subscribed. Mints whose
+    /// metadata is already complete are skipped.
+    pub fn account_filters(
+        &self,
+        pool_id: &str,
+        mints: &[&String],
+    ) -> HashMap<String, SubscribeRequestFilterAccounts> {
+        let mut accounts = Vec::new();
+        for &mint in mints {
+            if self
+                .get(mint)
+                .is_some_and(|t| t.decimals.is_some() && t.symbol.is_some() && t.name.is_some())
+            {
+                continue;
+            }
+            accounts.push(mint.clone());
+            accounts.extend(metadata_address(mint));
+        }
+        if accounts.is_empty() {
+            return HashMap::new();
+        }
+        HashMap::from([(
+            format!("{}_token_metadata", pool_id),
+            SubscribeRequestFilterAccounts {
+                account: accounts,
+                ..Default::default()
+            },
+        )])
+    }
+
+    /// Learn from any update: token balances of transactions, mint and metadata accounts.
+    pub fn observe(&self, update: &SubscribeUpdate) {
+        match &update.update_oneof {
+            Some(UpdateOneof::Transaction(tx_update)) => {
+                let Some(meta) = tx_update.transaction.as_ref().and_then(|t| t.meta.as_ref())
+                else {
+                    return;
+                };
+                for balance in meta.pre_token_balances.iter().chain(&meta.post_token_balances) {
+                    if let Some(amount) = &balance.ui_token_amount {
+                        self.learn_decimals(balance.mint.clone(), amount.decimals as u8);
+                    }
+                }
+            }
+            Some(UpdateOneof::Account(acc_update)) => {
+                if let Some(acc) = &acc_update.account {
+                    self.observe_account(&acc.owner, &acc.pubkey, &acc.data);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn observe_account(&self, owner: &[u8], pubkey: &[u8], data: &[u8]) {
+        let owner = bs58::encode(owner).into_string();
+        if owner == TOKEN_PROGRAM_ID || owner == TOKEN_2022_PROGRAM_ID {
+            if let Some(decimals) = parse_mint_decimals(&owner, data) {
+                self.learn_decimals(bs58::encode(pubkey).into_string(), decimals);
+            }
+        } else if owner == METADATA_PROGRAM_ID {
+            if let Some(info) = parse_metadata(data) {
+                self.learn(info);
+            }
+        }
+    }
+}
+
+impl Lru {
+    fn touch(&mut self, mint: &str) -> Option<&TokenInfo> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (info, used) = self.tokens.get_mut(mint)?;
+        *used = tick;
+        Some(info)
+    }
+
+    /// Drop the least recently used eighth of the cache, so a full cache does not pay a
+    /// scan for every new mint.
+    fn evict(&mut self) {
+        let mut ticks: Vec<u64> = self.tokens.values().map(|(_, used)| *used).collect();
+        let n = (ticks.len() / 8).max(1);
+        let (_, cutoff, _) = ticks.select_nth_unstable(n - 1);
+        let cutoff = *cutoff;
+        self.tokens.retain(|_, (_, used)| *used > cutoff);
+    }
+}
+
+/// Metaplex metadata PDA of `mint`: `["metadata", program id, mint]`.
+pub fn metadata_address(mint: &str) -> Option<String> {
+    let mint = bs58::decode(mint).into_vec().ok()?;
+    let program = bs58::decode(METADATA_PROGRAM_ID).into_vec().ok()?;
+    (0..=u8::MAX).rev().find_map(|bump| {
+        let hash: [u8; 32] = Sha256::new()
+            .chain_update(b"metadata")
+            .chain_update(&program)
+            .chain_update(&mint)
+            .chain_update([bump])
+            .chain_update(&program)
+            .chain_update(b"ProgramDerivedAddress")
+            .finalize()
+            .into();
+        // A program address must not be a valid ed25519 public key.
+        CompressedEdwardsY(hash)
+            .decompress()
+            .is_none()
+            .then(|| bs58::encode(hash).into_string())
+    })
+}
+
+/// Decimals of an initialized SPL Token or Token-2022 mint account owned by `owner`.
+fn parse_mint_decimals(owner: &str, data: &[u8]) -> Option<u8> {
+    let is_mint = data.len() == MINT_LEN
+        || (owner == TOKEN_2022_PROGRAM_ID
+            && data.get(TOKEN_2022_ACCOUNT_TYPE_OFFSET) == Some(&TOKEN_2022_ACCOUNT_TYPE_MINT));
+    // is_initialized
+    if is_mint && data.get(45) == Some(&1) {
+        Some(data[44])
+    } else {
+        None
+    }
+}
+
+/// Parse name and symbol out of a Metaplex `MetadataV1` account.
+fn parse_metadata(data: &[u8]) -> Option<TokenInfo> {
+    if *data.first()? != METADATA_V1_KEY {
+        return None;
+    }
+    // key (1) + update_authority (32)
+    let mint: [u8; 32] = data.get(33..65)?.try_into().ok()?;
+    let mut rest = data.get(65..)?;
+    let name = read_string(&mut rest)?;
+    let symbol = read_string(&mut rest)?;
+    Some(TokenInfo {
+        mint: bs58::encode(mint).into_string(),
+        decimals: None,
+        symbol: Some(symbol).filter(|s| !s.is_empty()),
+        name: Some(name).filter(|s| !s.is_empty()),
+    })
+}
+
+/// Borsh string, with Metaplex's trailing NUL padding stripped.
+fn read_string(buf: &mut &[u8]) -> Option<String> {
+    let len = u32::from_le_bytes(buf.get(..4)?.try_into().ok()?) as usize;
+    let bytes = buf.get(4..4 + len)?;
+    *buf = &buf[4 + len..];
+    Some(
+        String::from_utf8_lossy(bytes)
+            .trim_end_matches('\0')
+            .trim()
+            .to_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn mint_account(len: usize, decimals: u8, initialized: bool) -> Vec<u8> {
+        let mut data = vec![0u8; len];
+        data[44] = decimals;
+        data[45] = initialized as u8;
+        data
+    }
+
+    fn borsh_string(s: &str, padded_to: usize) -> Vec<u8> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize(padded_to, 0);
+        let mut out = (bytes.len() as u32).to_le_bytes().to_vec();
+        out.extend(bytes);
+        out
+    }
+
+    #[test]
+    fn metadata_address_matches_the_onchain_pda() {
+        assert_eq!(
+            metadata_address(USDC).as_deref(),
+            Some("5x38Kp4hvdomTCnCrAny4UtMUt5rQBdB6px2K1Ui45Wq")
+        );
+        assert_eq!(metadata_address("not base58!"), None);
+    }
+
+    #[test]
+    fn parses_spl_mint_decimals() {
+        let mint = mint_account(MINT_LEN, 6, true);
+        assert_eq!(parse_mint_decimals(TOKEN_PROGRAM_ID, &mint), Some(6));
+        let uninitialized = mint_account(MINT_LEN, 6, false);
+        assert_eq!(parse_mint_decimals(TOKEN_PROGRAM_ID, &uninitialized), None);
+        // A token account is not a mint.
+        let token_account = mint_account(165, 6, true);
+        assert_eq!(parse_mint_decimals(TOKEN_PROGRAM_ID, &token_account), None);
+    }
+
+    #[test]
+    fn parses_token_2022_mint_with_extensions() {
+        let mut mint = mint_account(250, 9, true);
+        mint[TOKEN_2022_ACCOUNT_TYPE_OFFSET] = TOKEN_2022_ACCOUNT_TYPE_MINT;
+        assert_eq!(parse_mint_decimals(TOKEN_2022_PROGRAM_ID, &mint), Some(9));
+        // The account-type byte only means something for Token-2022.
+        assert_eq!(parse_mint_decimals(TOKEN_PROGRAM_ID, &mint), None);
+        mint[TOKEN_2022_ACCOUNT_TYPE_OFFSET] = 2;
+        assert_eq!(parse_mint_decimals(TOKEN_2022_PROGRAM_ID, &mint), None);
+    }
+
+    #[test]
+    fn parses_metadata_name_and_symbol() {
+        let mint = bs58::decode(USDC).into_vec().unwrap();
+        let mut data = vec![METADATA_V1_KEY];
+        data.extend([7u8; 32]);
+        data.extend(&mint);
+        data.extend(borsh_string("USD Coin", 32));
+        data.extend(borsh_string("USDC", 10));
+        data.extend(borsh_string("https://example.com", 200));
+
+        let info = parse_metadata(&data).unwrap();
+        assert_eq!(info.mint, USDC);
+        assert_eq!(info.name.as_deref(), Some("USD Coin"));
+        assert_eq!(info.symbol.as_deref(), Some("USDC"));
+        assert_eq!(info.decimals, None);
+
+        data[0] = 1;
+        assert!(parse_metadata(&data).is_none());
+        assert!(parse_metadata(&[METADATA_V1_KEY; 70]).is_none());
+    }
+
+    #[test]
+    fn account_filters_cover_mint_and_metadata_until_known() {
+        let cache = TokenCache::default();
+        let mint = USDC.to_owned();
+        let filters = cache.account_filters("pool", &[&mint]);
+        assert_eq!(
+            filters["pool_token_metadata"].account,
+            [USDC, "5x38Kp4hvdomTCnCrAny4UtMUt5rQBdB6px2K1Ui45Wq"]
+        );
+
+        cache.learn(TokenInfo {
+            mint: mint.clone(),
+            decimals: Some(6),
+            symbol: Some("USDC".to_owned()),
+            name: Some("USD Coin".to_owned()),
+        });
+        assert!(cache.account_filters("pool", &[&mint]).is_empty());
+    }
+
+    #[test]
+    fn evicts_least_recently_used_mints() {
+        let cache = TokenCache::with_capacity(8);
+        for i in 0..8 {
+            cache.learn_decimals(format!("mint{}", i), 6);
+        }
+        assert_eq!(cache.decimals("mint0"), Some(6));
+        cache.learn_decimals("mint8".to_owned(), 6);
+
+        assert_eq!(cache.decimals("mint1"), None);
+        for i in [0, 2, 7, 8] {
+            assert_eq!(cache.decimals(&format!("mint{}", i)), Some(6));
+        }
+    }
+}
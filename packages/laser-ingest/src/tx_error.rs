@@ -0,0 +1,198 @@
+//! Decoding of `TransactionStatusMeta.err`.
+//!
+//! Geyser ships the transaction error as the bincode encoding of Solana's `TransactionError`:
+//! a `u32` variant index, followed for `InstructionError` by the failing instruction index
+//! (`u8`) and the `InstructionError` (again a `u32` variant index, then a `u32` code for
+//! `Custom`).
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// `TransactionError` variants, by bincode index.
+const TRANSACTION_ERRORS: &[&str] = &[
+    "AccountInUse",
+    "AccountLoadedTwice",
+    "AccountNotFound",
+    "ProgramAccountNotFound",
+    "InsufficientFundsForFee",
+    "InvalidAccountForFee",
+    "AlreadyProcessed",
+    "BlockhashNotFound",
+    "InstructionError",
+    "CallChainTooDeep",
+    "MissingSignatureForFee",
+    "InvalidAccountIndex",
+    "SignatureFailure",
+    "InvalidProgramForExecution",
+    "SanitizeFailure",
+    "ClusterMaintenance",
+    "AccountBorrowOutstanding",
+    "WouldExceedMaxBlockCostLimit",
+    "UnsupportedVersion",
+    "InvalidWritableAccount",
+    "WouldExceedMaxAccountCostLimit",
+    "WouldExceedAccountDataBlockLimit",
+    "TooManyAccountLocks",
+    "AddressLookupTableNotFound",
+    "InvalidAddressLookupTableOwner",
+    "InvalidAddressLookupTableData",
+    "InvalidAddressLookupTableIndex",
+    "InvalidRentPayingAccount",
+    "WouldExceedMaxVoteCostLimit",
+    "WouldExceedAccountDataTotalLimit",
+    "DuplicateInstruction",
+    "InsufficientFundsForRent",
+    "MaxLoadedAccountsDataSizeExceeded",
+    "InvalidLoadedAccountsDataSizeLimit",
+    "ResanitizationNeeded",
+    "ProgramExecutionTemporarilyRestricted",
+    "UnbalancedTransaction",
+    "ProgramCacheHitMaxLimit",
+    "CommitCancelled",
+];
+const INSTRUCTION_ERROR: u32 = 8;
+
+/// `InstructionError` variants, by bincode index.
+const INSTRUCTION_ERRORS: &[&str] = &[
+    "GenericError",
+    "InvalidArgument",
+    "InvalidInstructionData",
+    "InvalidAccountData",
+    "AccountDataTooSmall",
+    "InsufficientFunds",
+    "IncorrectProgramId",
+    "MissingRequiredSignature",
+    "AccountAlreadyInitialized",
+    "UninitializedAccount",
+    "UnbalancedInstruction",
+    "ModifiedProgramId",
+    "ExternalAccountLamportSpend",
+    "ExternalAccountDataModified",
+    "ReadonlyLamportChange",
+    "ReadonlyDataModified",
+    "DuplicateAccountIndex",
+    "ExecutableModified",
+    "RentEpochModified",
+    "NotEnoughAccountKeys",
+    "AccountDataSizeChanged",
+    "AccountNotExecutable",
+    "AccountBorrowFailed",
+    "AccountBorrowOutstanding",
+    "DuplicateAccountOutOfSync",
+    "Custom",
+    "InvalidError",
+    "ExecutableDataModified",
+    "ExecutableLamportChange",
+    "ExecutableAccountNotRentExempt",
+    "UnsupportedProgramId",
+    "CallDepth",
+    "MissingAccount",
+    "ReentrancyNotAllowed",
+    "MaxSeedLengthExceeded",
+    "InvalidSeeds",
+    "InvalidRealloc",
+    "ComputationalBudgetExceeded",
+    "PrivilegeEscalation",
+    "ProgramEnvironmentSetupFailure",
+    "ProgramFailedToComplete",
+    "ProgramFailedToCompile",
+    "Immutable",
+    "IncorrectAuthority",
+    "BorshIoError",
+    "AccountNotRentExempt",
+    "InvalidAccountOwner",
+    "ArithmeticOverflow",
+    "UnsupportedSysvar",
+    "IllegalOwner",
+    "MaxAccountsDataAllocationsExceeded",
+    "MaxAccountsExceeded",
+    "MaxInstructionTraceLengthExceeded",
+    "BuiltinProgramsMustConsumeComputeUnits",
+];
+const CUSTOM: u32 = 25;
+
+/// A decoded transaction error.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TxError {
+    /// `TransactionError` variant, e.g. `InstructionError`.
+    pub kind: String,
+    /// Top-level instruction that failed, for `InstructionError`.
+    pub instruction_index: Option<u8>,
+    /// `InstructionError` variant, e.g. `Custom`.
+    pub instruction_error: Option<String>,
+    /// Program-defined error code, for `InstructionError(_, Custom(code))`.
+    pub custom_code: Option<u32>,
+}
+
+impl TxError {
+    pub fn decode(err: &[u8]) -> Option<Self> {
+        let kind = read_u32(err, 0)?;
+        let mut decoded = Self {
+            kind: variant_name(TRANSACTION_ERRORS, kind),
+            instruction_index: None,
+            instruction_error: None,
+            custom_code: None,
+        };
+        if kind == INSTRUCTION_ERROR {
+            decoded.instruction_index = err.get(4).copied();
+            if let Some(ix_err) = read_u32(err, 5) {
+                decoded.instruction_error = Some(variant_name(INSTRUCTION_ERRORS, ix_err));
+                if ix_err == CUSTOM {
+                    decoded.custom_code = read_u32(err, 9);
+                }
+            }
+        }
+        Some(decoded)
+    }
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(buf.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn variant_name(names: &[&str], index: u32) -> String {
+    names
+        .get(index as usize)
+        .map(|n| n.to_string())
+        .unwrap_or_else(|| format!("Unknown({})", index))
+}
+
+/// Encode an RPC (`json`) transaction error in the bincode layout geyser uses, so errors of
+/// transactions fetched over RPC decode like streamed ones. Variant payloads other than
+/// `InstructionError`'s are dropped.
+pub fn encode_json(err: &Value) -> Vec<u8> {
+    let (name, payload) = match err {
+        Value::String(name) => (name.as_str(), &Value::Null),
+        Value::Object(map) => match map.iter().next() {
+            Some((name, payload)) => (name.as_str(), payload),
+            None => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+    let mut out = variant_index(TRANSACTION_ERRORS, name).to_le_bytes().to_vec();
+    if name == "InstructionError" {
+        out.push(payload[0].as_u64().unwrap_or_default() as u8);
+        match &payload[1] {
+            Value::String(ix_err) => {
+                out.extend(variant_index(INSTRUCTION_ERRORS, ix_err).to_le_bytes());
+            }
+            Value::Object(map) => {
+                if let Some((ix_err, ix_payload)) = map.iter().next() {
+                    out.extend(variant_index(INSTRUCTION_ERRORS, ix_err).to_le_bytes());
+                    if ix_err == "Custom" {
+                        out.extend((ix_payload.as_u64().unwrap_or_default() as u32).to_le_bytes());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn variant_index(names: &[&str], name: &str) -> u32 {
+    names
+        .iter()
+        .position(|n| *n == name)
+        .unwrap_or(names.len()) as u32
+}
@@ -6,6 +6,8 @@ pub enum PoolVariant {
     Dbc,
     Amm,
     Damm,
+    /// Any Anchor program, decoded at runtime from `idl_path`.
+    Idl,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -16,4 +18,16 @@ pub struct PoolMeta {
     pub quote_vault: Option<String>,
     #[serde(default)]
     pub config_pda: Option<String>,
+    /// Base and quote mints; their mint and metadata accounts are subscribed to so the
+    /// token cache learns decimals, name and symbol.
+    #[serde(default)]
+    pub base_mint: Option<String>,
+    #[serde(default)]
+    pub quote_mint: Option<String>,
+    /// Program to watch for `idl` entries.
+    #[serde(default)]
+    pub program_id: Option<String>,
+    /// Path to the program's Anchor IDL JSON for `idl` entries.
+    #[serde(default)]
+    pub idl_path: Option<String>,
 } 
\ No newline at end of file
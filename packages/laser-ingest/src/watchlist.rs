@@ -7,4 +7,13 @@ pub fn load_from_json<P: AsRef<Path>>(path: P) -> Result<Vec<PoolMeta>> {
     let data = fs::read_to_string(path)?;
     let pools: Vec<PoolMeta> = serde_json::from_str(&data)?;
     Ok(pools)
-} 
\ No newline at end of file
+} 
+
+/// Atomically rewrite the watchlist file (write to a sibling temp file, then rename).
+pub fn save_to_json<P: AsRef<Path>>(path: P, pools: &[PoolMeta]) -> Result<()> {
+    let path = path.as_ref();
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(pools)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE webhook_deliveries DROP CONSTRAINT IF EXISTS webhook_deliveries_event_attempt_key;
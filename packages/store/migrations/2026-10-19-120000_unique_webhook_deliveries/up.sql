@@ -0,0 +1,12 @@
+-- Deliveries are keyed by the deterministic pool event id, so an event published twice
+-- (reconnect replays, redundant ingest replicas) is only logged once per attempt.
+-- Rows logged twice before this constraint existed are dropped, keeping the first.
+DELETE FROM webhook_deliveries a
+    USING webhook_deliveries b
+    WHERE a.webhook_id = b.webhook_id
+      AND a.event_id = b.event_id
+      AND a.attempt = b.attempt
+      AND a.id > b.id;
+
+ALTER TABLE webhook_deliveries
+    ADD CONSTRAINT webhook_deliveries_event_attempt_key UNIQUE (webhook_id, event_id, attempt);
@@ -0,0 +1,14 @@
+-- The dispatcher claims a (webhook, event) pair before delivering it, and skips events
+-- another dispatcher or an earlier copy of the event already claimed.
+CREATE TABLE webhook_event_claims (
+    webhook_id INTEGER NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    event_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (webhook_id, event_id)
+);
+
+CREATE INDEX webhook_event_claims_created_at_idx ON webhook_event_claims (created_at);
+
+-- Events delivered before claims existed count as claimed.
+INSERT INTO webhook_event_claims (webhook_id, event_id)
+    SELECT DISTINCT webhook_id, event_id FROM webhook_deliveries WHERE attempt > 0;
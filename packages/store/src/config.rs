@@ -18,3 +18,37 @@ impl Default for Config {
         }
     }
 }
+
+/// `url` with any password replaced by `***`, for logging connection strings.
+pub fn redact_password(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("***"));
+            parsed.to_string()
+        }
+        Ok(_) => url.to_string(),
+        Err(_) => "<invalid url>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_url_password() {
+        assert_eq!(
+            redact_password("redis://:hunter2@redis:6379/0"),
+            "redis://:***@redis:6379/0"
+        );
+        assert_eq!(
+            redact_password("postgres://heimdall:hunter2@db/heimdall"),
+            "postgres://heimdall:***@db/heimdall"
+        );
+        assert_eq!(
+            redact_password("redis://127.0.0.1:6379"),
+            "redis://127.0.0.1:6379"
+        );
+        assert_eq!(redact_password("not a url"), "<invalid url>");
+    }
+}
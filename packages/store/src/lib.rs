@@ -3,4 +3,5 @@ pub mod models;
 mod schema;
 pub mod store;
 
+pub use config::redact_password;
 pub use store::Store;
@@ -1,7 +1,9 @@
 pub mod accounts;
 pub mod slots;
 pub mod transactions;
+pub mod webhooks;
 
 pub use accounts::*;
 pub use slots::*;
 pub use transactions::*;
+pub use webhooks::*;
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::webhooks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    /// HMAC key used to sign deliveries. Only returned once, when the webhook is created.
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub pool_id: Option<String>,
+    pub event_types: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[diesel(table_name = crate::schema::webhooks)]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    pub pool_id: Option<String>,
+    pub event_types: Vec<String>,
+}
+
+impl Webhook {
+    /// Whether an event for `pool_id` of type `event_type` should be sent to this webhook.
+    pub fn matches(&self, pool_id: &str, event_type: &str) -> bool {
+        self.active
+            && self.pool_id.as_deref().is_none_or(|p| p == pool_id)
+            && (self.event_types.is_empty() || self.event_types.iter().any(|t| t == event_type))
+    }
+}
+
+/// Whether webhooks may be delivered to `ip`. Loopback, private, link-local, shared
+/// (carrier-grade NAT), unspecified, broadcast and multicast addresses are refused, so a
+/// registration can't make the dispatcher POST to internal services.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public_v4(v4),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local (fc00::/7) and link-local (fe80::/10).
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::webhook_deliveries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event_id: String,
+    pub event_type: String,
+    pub attempt: i32,
+    pub status_code: Option<i32>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[diesel(table_name = crate::schema::webhook_deliveries)]
+pub struct NewWebhookDelivery {
+    pub webhook_id: i32,
+    pub event_id: String,
+    pub event_type: String,
+    pub attempt: i32,
+    pub status_code: Option<i32>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_addresses_are_refused() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn public_addresses_are_allowed() {
+        for ip in [
+            "93.184.216.34",
+            "100.128.0.1",
+            "2606:4700::1111",
+            "::ffff:1.1.1.1",
+        ] {
+            assert!(is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+}
@@ -141,6 +141,41 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Int4,
+        webhook_id -> Int4,
+        event_id -> Text,
+        event_type -> Text,
+        attempt -> Int4,
+        status_code -> Nullable<Int4>,
+        success -> Bool,
+        error -> Nullable<Text>,
+        duration_ms -> Int8,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    webhook_event_claims (webhook_id, event_id) {
+        webhook_id -> Int4,
+        event_id -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Int4,
+        url -> Text,
+        secret -> Text,
+        pool_id -> Nullable<Text>,
+        event_types -> Array<Text>,
+        active -> Bool,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::joinable!(sanitized_transactions -> transactions (transaction_id));
 diesel::joinable!(transaction_inner_instruction -> transaction_inner_instructions (inner_instructions_id));
 diesel::joinable!(transaction_inner_instructions -> transaction_status_meta (status_meta_id));
@@ -152,6 +187,8 @@ diesel::joinable!(transaction_pre_token_balances -> transaction_status_meta (sta
 diesel::joinable!(transaction_rewards -> transaction_status_meta (status_meta_id));
 diesel::joinable!(transaction_signatures -> sanitized_transactions (sanitized_transaction_id));
 diesel::joinable!(transaction_status_meta -> transactions (transaction_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
+diesel::joinable!(webhook_event_claims -> webhooks (webhook_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     accounts,
@@ -168,4 +205,7 @@ diesel::allow_tables_to_appear_in_same_query!(
     transaction_signatures,
     transaction_status_meta,
     transactions,
+    webhook_deliveries,
+    webhook_event_claims,
+    webhooks,
 );
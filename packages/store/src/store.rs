@@ -5,8 +5,9 @@ use crate::models::{
         NewSanitizedTransaction, NewTransaction, NewTransactionSignature, NewTransactionStatusMeta,
         SanitizedTransaction, Transaction, TransactionSignature, TransactionStatusMeta,
     },
+    webhooks::{NewWebhook, NewWebhookDelivery, Webhook, WebhookDelivery},
 };
-use diesel::{Connection, PgConnection, RunQueryDsl};
+use diesel::{Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
 
 use crate::config::Config;
 
@@ -201,4 +202,112 @@ impl Store {
 
         transaction_status_meta.load(&mut self.conn)
     }
+
+    // Webhook operations
+    pub fn create_webhook(
+        &mut self,
+        url: String,
+        secret: String,
+        pool_id: Option<String>,
+        event_types: Vec<String>,
+    ) -> Result<Webhook, diesel::result::Error> {
+        use crate::schema::webhooks;
+
+        let new_webhook = NewWebhook {
+            url,
+            secret,
+            pool_id,
+            event_types,
+        };
+
+        diesel::insert_into(webhooks::table)
+            .values(&new_webhook)
+            .get_result(&mut self.conn)
+    }
+
+    pub fn get_webhooks(&mut self) -> Result<Vec<Webhook>, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        webhooks.order(id.asc()).load(&mut self.conn)
+    }
+
+    pub fn get_active_webhooks(&mut self) -> Result<Vec<Webhook>, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        webhooks.filter(active.eq(true)).load(&mut self.conn)
+    }
+
+    pub fn set_webhook_active(
+        &mut self,
+        webhook_id: i32,
+        is_active: bool,
+    ) -> Result<Webhook, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        diesel::update(webhooks.find(webhook_id))
+            .set(active.eq(is_active))
+            .get_result(&mut self.conn)
+    }
+
+    pub fn delete_webhook(&mut self, webhook_id: i32) -> Result<usize, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        diesel::delete(webhooks.find(webhook_id)).execute(&mut self.conn)
+    }
+
+    /// Record a delivery attempt. Returns 0 if the attempt was already recorded for the
+    /// same webhook and event.
+    pub fn create_webhook_delivery(
+        &mut self,
+        delivery: NewWebhookDelivery,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::webhook_deliveries;
+
+        diesel::insert_into(webhook_deliveries::table)
+            .values(&delivery)
+            .on_conflict_do_nothing()
+            .execute(&mut self.conn)
+    }
+
+    /// Claim `event_id` for delivery to `webhook_id`. Returns `false` if it was already
+    /// claimed, i.e. the event has been (or is being) delivered to that webhook.
+    pub fn claim_webhook_event(
+        &mut self,
+        for_webhook_id: i32,
+        for_event_id: &str,
+    ) -> Result<bool, diesel::result::Error> {
+        use crate::schema::webhook_event_claims::dsl::*;
+
+        let inserted = diesel::insert_into(webhook_event_claims)
+            .values((webhook_id.eq(for_webhook_id), event_id.eq(for_event_id)))
+            .on_conflict_do_nothing()
+            .execute(&mut self.conn)?;
+        Ok(inserted == 1)
+    }
+
+    /// Forget claims older than `age`. Returns how many were removed.
+    pub fn delete_webhook_event_claims_older_than(
+        &mut self,
+        age: std::time::Duration,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::webhook_event_claims::dsl::*;
+
+        let cutoff =
+            chrono::Utc::now() - chrono::Duration::from_std(age).unwrap_or(chrono::Duration::MAX);
+        diesel::delete(webhook_event_claims.filter(created_at.lt(cutoff))).execute(&mut self.conn)
+    }
+
+    pub fn get_webhook_deliveries(
+        &mut self,
+        for_webhook_id: i32,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, diesel::result::Error> {
+        use crate::schema::webhook_deliveries::dsl::*;
+
+        webhook_deliveries
+            .filter(webhook_id.eq(for_webhook_id))
+            .order(created_at.desc())
+            .limit(limit)
+            .load(&mut self.conn)
+    }
 }
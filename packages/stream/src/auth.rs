@@ -0,0 +1,422 @@
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tonic::{service::Interceptor, Request, Status};
+
+use crate::metrics;
+
+const DEFAULT_MAX_STREAMS: usize = 10;
+const DEFAULT_MAX_POOLS: usize = 10;
+
+/// Per-client limits. A client is identified by its API key or JWT subject.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub max_streams: usize,
+    pub max_pools: usize,
+}
+
+/// Identity attached to every authenticated request by [`AuthInterceptor`].
+#[derive(Clone, Debug)]
+pub struct ClientIdentity {
+    pub key_id: String,
+    pub limits: Limits,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwtClaims {
+    sub: String,
+    #[serde(default)]
+    max_streams: Option<usize>,
+    #[serde(default)]
+    max_pools: Option<usize>,
+}
+
+/// Authentication settings loaded from the environment.
+///
+/// * `STREAM_API_KEYS` – comma separated `key[:max_streams[:max_pools]]` entries.
+/// * `STREAM_JWT_SECRET` – HS256 secret; tokens must carry a `sub` claim and may
+///   override limits with `max_streams` / `max_pools`.
+/// * `STREAM_MAX_STREAMS_PER_KEY` / `STREAM_MAX_POOLS_PER_KEY` – default limits.
+///
+/// If neither keys nor a JWT secret are configured the server runs open and every
+/// remote IP is treated as its own anonymous client with the default limits.
+pub struct AuthConfig {
+    api_keys: HashMap<String, Limits>,
+    jwt_key: Option<DecodingKey>,
+    default_limits: Limits,
+}
+
+impl AuthConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default_limits = Limits {
+            max_streams: env_usize("STREAM_MAX_STREAMS_PER_KEY")?.unwrap_or(DEFAULT_MAX_STREAMS),
+            max_pools: env_usize("STREAM_MAX_POOLS_PER_KEY")?.unwrap_or(DEFAULT_MAX_POOLS),
+        };
+
+        let api_keys = match std::env::var("STREAM_API_KEYS") {
+            Ok(raw) => parse_api_keys(&raw, default_limits)?,
+            Err(_) => HashMap::new(),
+        };
+
+        let jwt_key = std::env::var("STREAM_JWT_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| DecodingKey::from_secret(s.as_bytes()));
+
+        Ok(Self {
+            api_keys,
+            jwt_key,
+            default_limits,
+        })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.api_keys.is_empty() && self.jwt_key.is_none()
+    }
+
+    fn authenticate(
+        &self,
+        token: Option<&str>,
+        remote: Option<SocketAddr>,
+    ) -> Result<ClientIdentity, &'static str> {
+        if self.is_open() {
+            let ip = remote
+                .map(|a| a.ip().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            return Ok(ClientIdentity {
+                key_id: format!("anon:{}", ip),
+                limits: self.default_limits,
+            });
+        }
+
+        let token = token.ok_or("missing credentials")?;
+
+        if let Some(limits) = self.api_keys.get(token) {
+            return Ok(ClientIdentity {
+                key_id: format!("key:{}", fingerprint(token)),
+                limits: *limits,
+            });
+        }
+
+        if let Some(key) = &self.jwt_key {
+            let data = decode::<JwtClaims>(token, key, &Validation::new(Algorithm::HS256))
+                .map_err(|_| "invalid token")?;
+            let claims = data.claims;
+            return Ok(ClientIdentity {
+                key_id: format!("jwt:{}", claims.sub),
+                limits: Limits {
+                    max_streams: claims
+                        .max_streams
+                        .unwrap_or(self.default_limits.max_streams),
+                    max_pools: claims.max_pools.unwrap_or(self.default_limits.max_pools),
+                },
+            });
+        }
+
+        Err("invalid api key")
+    }
+}
+
+/// Tonic interceptor that validates `authorization: Bearer <token>` or `x-api-key`
+/// metadata and attaches a [`ClientIdentity`] to the request extensions.
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    config: Arc<AuthConfig>,
+}
+
+impl AuthInterceptor {
+    pub fn new(config: Arc<AuthConfig>) -> Self {
+        Self { config }
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let metadata = request.metadata();
+        let token = metadata
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .or_else(|| metadata.get("x-api-key").and_then(|v| v.to_str().ok()))
+            .map(str::to_owned);
+
+        match self
+            .config
+            .authenticate(token.as_deref(), request.remote_addr())
+        {
+            Ok(identity) => {
+                request.extensions_mut().insert(identity);
+                Ok(request)
+            }
+            Err(reason) => {
+                metrics::record_rejection("unauthenticated");
+                tracing::warn!(remote = ?request.remote_addr(), reason, "Rejected unauthenticated request");
+                Err(Status::unauthenticated(reason))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    streams: usize,
+    pools: HashMap<String, usize>,
+}
+
+/// Reason a stream was refused by [`QuotaTracker::acquire`].
+#[derive(Debug)]
+pub enum QuotaExceeded {
+    Streams(usize),
+    Pools(usize),
+}
+
+impl From<QuotaExceeded> for Status {
+    fn from(err: QuotaExceeded) -> Self {
+        match err {
+            QuotaExceeded::Streams(limit) => {
+                Status::resource_exhausted(format!("concurrent stream limit of {} reached", limit))
+            }
+            QuotaExceeded::Pools(limit) => {
+                Status::resource_exhausted(format!("subscribed pool limit of {} reached", limit))
+            }
+        }
+    }
+}
+
+/// Tracks concurrent streams and distinct subscribed pools per client.
+#[derive(Clone, Debug, Default)]
+pub struct QuotaTracker {
+    usage: Arc<Mutex<HashMap<String, Usage>>>,
+}
+
+impl QuotaTracker {
+    /// Reserve a stream slot for `pool_id`. The returned permit releases it on drop.
+    pub fn acquire(
+        &self,
+        identity: &ClientIdentity,
+        pool_id: &str,
+    ) -> Result<StreamPermit, QuotaExceeded> {
+        let mut usage = self.usage.lock().unwrap();
+        let entry = usage.entry(identity.key_id.clone()).or_default();
+
+        if entry.streams >= identity.limits.max_streams {
+            metrics::record_rejection("stream_limit");
+            return Err(QuotaExceeded::Streams(identity.limits.max_streams));
+        }
+        if !entry.pools.contains_key(pool_id) && entry.pools.len() >= identity.limits.max_pools {
+            metrics::record_rejection("pool_limit");
+            return Err(QuotaExceeded::Pools(identity.limits.max_pools));
+        }
+
+        entry.streams += 1;
+        *entry.pools.entry(pool_id.to_string()).or_default() += 1;
+
+        Ok(StreamPermit {
+            tracker: self.clone(),
+            key_id: identity.key_id.clone(),
+            pool_id: pool_id.to_string(),
+        })
+    }
+
+    fn release(&self, key_id: &str, pool_id: &str) {
+        let mut usage = self.usage.lock().unwrap();
+        if let Some(entry) = usage.get_mut(key_id) {
+            entry.streams = entry.streams.saturating_sub(1);
+            if let Some(count) = entry.pools.get_mut(pool_id) {
+                *count -= 1;
+                if *count == 0 {
+                    entry.pools.remove(pool_id);
+                }
+            }
+            if entry.streams == 0 {
+                usage.remove(key_id);
+            }
+        }
+    }
+}
+
+/// Held by a stream worker for as long as the client stream is alive.
+pub struct StreamPermit {
+    tracker: QuotaTracker,
+    key_id: String,
+    pool_id: String,
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        self.tracker.release(&self.key_id, &self.pool_id);
+    }
+}
+
+/// Parse comma separated `key[:max_streams[:max_pools]]` entries.
+fn parse_api_keys(raw: &str, default_limits: Limits) -> anyhow::Result<HashMap<String, Limits>> {
+    let mut api_keys = HashMap::new();
+    for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let mut parts = entry.split(':');
+        let key = parts.next().unwrap_or_default().to_string();
+        let max_streams = match parts.next() {
+            Some(v) => v.parse()?,
+            None => default_limits.max_streams,
+        };
+        let max_pools = match parts.next() {
+            Some(v) => v.parse()?,
+            None => default_limits.max_pools,
+        };
+        api_keys.insert(
+            key,
+            Limits {
+                max_streams,
+                max_pools,
+            },
+        );
+    }
+    Ok(api_keys)
+}
+
+fn env_usize(name: &str) -> anyhow::Result<Option<usize>> {
+    match std::env::var(name) {
+        Ok(v) => Ok(Some(v.parse()?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Stable, non-reversible identifier for an API key so raw keys never end up in logs.
+fn fingerprint(key: &str) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    key.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const DEFAULTS: Limits = Limits {
+        max_streams: 10,
+        max_pools: 10,
+    };
+    const SECRET: &[u8] = b"test-secret";
+
+    fn jwt_config() -> AuthConfig {
+        AuthConfig {
+            api_keys: HashMap::new(),
+            jwt_key: Some(DecodingKey::from_secret(SECRET)),
+            default_limits: DEFAULTS,
+        }
+    }
+
+    fn token(secret: &[u8], exp_offset_secs: i64) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let claims = serde_json::json!({
+            "sub": "alice",
+            "max_streams": 3,
+            "exp": now + exp_offset_secs,
+        });
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap()
+    }
+
+    fn identity(max_streams: usize, max_pools: usize) -> ClientIdentity {
+        ClientIdentity {
+            key_id: "key:test".to_string(),
+            limits: Limits {
+                max_streams,
+                max_pools,
+            },
+        }
+    }
+
+    #[test]
+    fn parses_api_keys_with_optional_limits() {
+        let keys = parse_api_keys(" a , b:2 ,c:3:4,,", DEFAULTS).unwrap();
+        assert_eq!(keys.len(), 3);
+        assert_eq!((keys["a"].max_streams, keys["a"].max_pools), (10, 10));
+        assert_eq!((keys["b"].max_streams, keys["b"].max_pools), (2, 10));
+        assert_eq!((keys["c"].max_streams, keys["c"].max_pools), (3, 4));
+
+        assert!(parse_api_keys("a:many", DEFAULTS).is_err());
+        assert!(parse_api_keys("a:1:-1", DEFAULTS).is_err());
+    }
+
+    #[test]
+    fn accepts_valid_jwt_with_limit_overrides() {
+        let identity = jwt_config()
+            .authenticate(Some(&token(SECRET, 300)), None)
+            .unwrap();
+        assert_eq!(identity.key_id, "jwt:alice");
+        assert_eq!(identity.limits.max_streams, 3);
+        assert_eq!(identity.limits.max_pools, DEFAULTS.max_pools);
+    }
+
+    #[test]
+    fn rejects_jwt_with_bad_signature() {
+        let result = jwt_config().authenticate(Some(&token(b"other-secret", 300)), None);
+        assert_eq!(result.unwrap_err(), "invalid token");
+    }
+
+    #[test]
+    fn rejects_expired_jwt() {
+        let result = jwt_config().authenticate(Some(&token(SECRET, -3600)), None);
+        assert_eq!(result.unwrap_err(), "invalid token");
+    }
+
+    #[test]
+    fn rejects_missing_or_unknown_api_key() {
+        let config = AuthConfig {
+            api_keys: parse_api_keys("good", DEFAULTS).unwrap(),
+            jwt_key: None,
+            default_limits: DEFAULTS,
+        };
+        assert!(config.authenticate(Some("good"), None).is_ok());
+        assert_eq!(
+            config.authenticate(Some("bad"), None).unwrap_err(),
+            "invalid api key"
+        );
+        assert_eq!(
+            config.authenticate(None, None).unwrap_err(),
+            "missing credentials"
+        );
+    }
+
+    #[test]
+    fn refuses_streams_and_pools_over_quota() {
+        let tracker = QuotaTracker::default();
+        let client = identity(2, 1);
+
+        let _first = tracker.acquire(&client, "pool-a").unwrap();
+        assert!(matches!(
+            tracker.acquire(&client, "pool-b"),
+            Err(QuotaExceeded::Pools(1))
+        ));
+        let _second = tracker.acquire(&client, "pool-a").unwrap();
+        assert!(matches!(
+            tracker.acquire(&client, "pool-a"),
+            Err(QuotaExceeded::Streams(2))
+        ));
+    }
+
+    #[test]
+    fn dropping_a_permit_releases_its_slot() {
+        let tracker = QuotaTracker::default();
+        let client = identity(1, 1);
+
+        let permit = tracker.acquire(&client, "pool-a").unwrap();
+        assert!(tracker.acquire(&client, "pool-b").is_err());
+        drop(permit);
+
+        assert!(tracker.usage.lock().unwrap().is_empty());
+        let _permit = tracker.acquire(&client, "pool-b").unwrap();
+    }
+}
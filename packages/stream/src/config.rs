@@ -0,0 +1,203 @@
+use crate::subscriber::Policy;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::time::Duration;
+use store::redact_password;
+use tonic::transport::{Identity, ServerTlsConfig};
+
+/// Stream server settings.
+///
+/// Values are read from the JSON file named by `STREAM_CONFIG` (if set) and then
+/// overridden by individual environment variables, so containers can tweak a single
+/// setting without shipping a new file.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// `STREAM_LISTEN_ADDR`
+    pub listen_addr: SocketAddr,
+    /// `REDIS_URL`
+    pub redis_url: String,
+    /// `STREAM_METRICS_ADDR`
+    pub metrics_addr: SocketAddr,
+    pub tls: Option<TlsConfig>,
+    pub keepalive: KeepaliveConfig,
+    pub subscriber: SubscriberConfig,
+    /// `STREAM_SHUTDOWN_GRACE_SECS` – how long clients get to drain before we exit.
+    pub shutdown_grace_secs: u64,
+}
+
+/// Keeps the Redis password out of the startup log.
+impl std::fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("listen_addr", &self.listen_addr)
+            .field("redis_url", &redact_password(&self.redis_url))
+            .field("metrics_addr", &self.metrics_addr)
+            .field("tls", &self.tls)
+            .field("keepalive", &self.keepalive)
+            .field("subscriber", &self.subscriber)
+            .field("shutdown_grace_secs", &self.shutdown_grace_secs)
+            .finish()
+    }
+}
+
+/// Per-subscriber buffering, see [`crate::subscriber::SubscriberQueue`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SubscriberConfig {
+    /// `STREAM_SUBSCRIBER_QUEUE_CAPACITY` – updates buffered per client before the
+    /// overflow policy kicks in.
+    pub queue_capacity: usize,
+    /// `STREAM_SUBSCRIBER_OVERFLOW_POLICY` – used when the client does not pick one
+    /// (`drop_oldest`, `coalesce_latest` or `disconnect`).
+    pub default_policy: Policy,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    /// `STREAM_TLS_CERT` – PEM encoded certificate chain.
+    pub cert_path: String,
+    /// `STREAM_TLS_KEY` – PEM encoded private key.
+    pub key_path: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct KeepaliveConfig {
+    /// `STREAM_HTTP2_KEEPALIVE_INTERVAL_SECS`
+    pub http2_interval_secs: Option<u64>,
+    /// `STREAM_HTTP2_KEEPALIVE_TIMEOUT_SECS`
+    pub http2_timeout_secs: Option<u64>,
+    /// `STREAM_TCP_KEEPALIVE_SECS`
+    pub tcp_secs: Option<u64>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: "0.0.0.0:50051".parse().unwrap(),
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            metrics_addr: "0.0.0.0:9091".parse().unwrap(),
+            tls: None,
+            keepalive: KeepaliveConfig::default(),
+            subscriber: SubscriberConfig::default(),
+            shutdown_grace_secs: 10,
+        }
+    }
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            http2_interval_secs: Some(30),
+            http2_timeout_secs: Some(10),
+            tcp_secs: Some(60),
+        }
+    }
+}
+
+impl Default for SubscriberConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 100,
+            default_policy: Policy::DropOldest,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn load() -> Result<Self> {
+        let mut config = match std::env::var("STREAM_CONFIG") {
+            Ok(path) => {
+                let data = std::fs::read_to_string(&path)
+                    .with_context(|| format!("reading stream config {}", path))?;
+                serde_json::from_str(&data)
+                    .with_context(|| format!("parsing stream config {}", path))?
+            }
+            Err(_) => ServerConfig::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<()> {
+        if let Some(v) = env("STREAM_LISTEN_ADDR") {
+            self.listen_addr = v.parse().context("STREAM_LISTEN_ADDR")?;
+        }
+        if let Some(v) = env("REDIS_URL") {
+            self.redis_url = v;
+        }
+        if let Some(v) = env("STREAM_METRICS_ADDR") {
+            self.metrics_addr = v.parse().context("STREAM_METRICS_ADDR")?;
+        }
+        match (env("STREAM_TLS_CERT"), env("STREAM_TLS_KEY")) {
+            (Some(cert_path), Some(key_path)) => {
+                self.tls = Some(TlsConfig {
+                    cert_path,
+                    key_path,
+                })
+            }
+            (None, None) => {}
+            _ => anyhow::bail!("STREAM_TLS_CERT and STREAM_TLS_KEY must be set together"),
+        }
+        if let Some(v) = env("STREAM_HTTP2_KEEPALIVE_INTERVAL_SECS") {
+            self.keepalive.http2_interval_secs = Some(v.parse()?);
+        }
+        if let Some(v) = env("STREAM_HTTP2_KEEPALIVE_TIMEOUT_SECS") {
+            self.keepalive.http2_timeout_secs = Some(v.parse()?);
+        }
+        if let Some(v) = env("STREAM_TCP_KEEPALIVE_SECS") {
+            self.keepalive.tcp_secs = Some(v.parse()?);
+        }
+        if let Some(v) = env("STREAM_SUBSCRIBER_QUEUE_CAPACITY") {
+            self.subscriber.queue_capacity = v.parse()?;
+        }
+        if let Some(v) = env("STREAM_SUBSCRIBER_OVERFLOW_POLICY") {
+            self.subscriber.default_policy = serde_json::from_value(serde_json::Value::String(v))
+                .context("STREAM_SUBSCRIBER_OVERFLOW_POLICY")?;
+        }
+        if let Some(v) = env("STREAM_SHUTDOWN_GRACE_SECS") {
+            self.shutdown_grace_secs = v.parse()?;
+        }
+        Ok(())
+    }
+
+    pub fn shutdown_grace(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace_secs)
+    }
+}
+
+impl TlsConfig {
+    pub fn load(&self) -> Result<ServerTlsConfig> {
+        let cert = std::fs::read(&self.cert_path)
+            .with_context(|| format!("reading TLS cert {}", self.cert_path))?;
+        let key = std::fs::read(&self.key_path)
+            .with_context(|| format!("reading TLS key {}", self.key_path))?;
+        Ok(ServerTlsConfig::new().identity(Identity::from_pem(cert, key)))
+    }
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_output_redacts_redis_password() {
+        let config = ServerConfig {
+            redis_url: "redis://:hunter2@redis:6379/0".to_string(),
+            ..Default::default()
+        };
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("redis://:***@redis:6379/0"), "{}", debug);
+        assert!(!debug.contains("hunter2"));
+        assert_eq!(
+            redact_password("redis://127.0.0.1:6379"),
+            "redis://127.0.0.1:6379"
+        );
+    }
+}
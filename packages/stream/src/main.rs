@@ -10,37 +10,137 @@ pub mod proto_stream {
 //     tonic::include_proto!("heimdall.types");
 // }
 
+mod auth;
+mod config;
+mod metrics;
 mod service;
+mod subscriber;
 mod worker;
 
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tonic::codegen::http::{self, HeaderValue};
 use tonic::transport::Server;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
-    let addr = "[::1]:50051".parse()?;
-    info!("Starting Heimdall Stream Server on {}", addr);
+    let config = config::ServerConfig::load()?;
+    info!(
+        ?config,
+        "Starting Heimdall Stream Server on {}", config.listen_addr
+    );
+
+    let redis_client = redis::Client::open(config.redis_url.as_str())?;
+
+    metrics::install(config.metrics_addr)?;
+    info!("Serving metrics on {}", config.metrics_addr);
 
-    let redis_url =
-        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
-    let redis_client = redis::Client::open(redis_url)?;
+    let auth_config = Arc::new(auth::AuthConfig::from_env()?);
+    if auth_config.is_open() {
+        tracing::warn!(
+            "No STREAM_API_KEYS or STREAM_JWT_SECRET configured, authentication is disabled"
+        );
+    }
 
-    let stream_service = crate::service::StreamService::new(redis_client);
+    // Flipped to `true` on SIGTERM/SIGINT; the service stops accepting streams and
+    // every worker sends its client a final status before exiting.
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let stream_service = crate::service::StreamService::new(
+        redis_client,
+        shutdown_rx.clone(),
+        config.subscriber.clone(),
+    );
 
     let reflection_service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(proto_stream::FILE_DESCRIPTOR_SET)
         .build()?;
 
-    Server::builder()
+    let mut builder = Server::builder();
+    if let Some(tls) = &config.tls {
+        builder = builder.tls_config(tls.load()?)?;
+        info!("TLS enabled");
+    }
+
+    let keepalive = &config.keepalive;
+    let serve = builder
         .accept_http1(true)
-        .layer(tower_http::cors::CorsLayer::permissive())
+        .http2_keepalive_interval(keepalive.http2_interval_secs.map(Duration::from_secs))
+        .http2_keepalive_timeout(keepalive.http2_timeout_secs.map(Duration::from_secs))
+        .tcp_keepalive(keepalive.tcp_secs.map(Duration::from_secs))
+        .layer(cors_layer())
         .add_service(reflection_service)
         .add_service(tonic_web::enable(
-            crate::proto_stream::heimdall_stream_server::HeimdallStreamServer::new(stream_service),
+            crate::proto_stream::heimdall_stream_server::HeimdallStreamServer::with_interceptor(
+                stream_service,
+                auth::AuthInterceptor::new(auth_config),
+            ),
         ))
-        .serve(addr)
-        .await?;
+        .serve_with_shutdown(config.listen_addr, async move {
+            shutdown_signal().await;
+            info!("Shutdown signal received, draining clients");
+            let _ = shutdown_tx.send(true);
+        });
+
+    let grace = config.shutdown_grace();
+    let mut shutdown_rx = shutdown_rx;
+    tokio::select! {
+        res = serve => res?,
+        _ = async {
+            let _ = shutdown_rx.wait_for(|stopping| *stopping).await;
+            tokio::time::sleep(grace).await;
+        } => {
+            tracing::warn!(?grace, "Clients did not drain within the grace period, exiting");
+        }
+    }
 
+    info!("Stream server stopped");
     Ok(())
 }
+
+async fn shutdown_signal() {
+    let ctrl_c = tokio::signal::ctrl_c();
+    #[cfg(unix)]
+    {
+        let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler");
+        tokio::select! {
+            _ = ctrl_c => {},
+            _ = term.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = ctrl_c.await;
+    }
+}
+
+/// Build the CORS layer from `STREAM_CORS_ORIGINS` (comma separated). Unset or `*` keeps
+/// the permissive policy used for local development.
+fn cors_layer() -> CorsLayer {
+    let origins = std::env::var("STREAM_CORS_ORIGINS").unwrap_or_default();
+    if origins.trim().is_empty() || origins.trim() == "*" {
+        return CorsLayer::permissive();
+    }
+
+    let allowed: Vec<HeaderValue> = origins
+        .split(',')
+        .map(str::trim)
+        .filter(|o| !o.is_empty())
+        .filter_map(|o| HeaderValue::from_str(o).ok())
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(allowed))
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers([
+            http::header::HeaderName::from_static("grpc-status"),
+            http::header::HeaderName::from_static("grpc-message"),
+        ])
+}
@@ -0,0 +1,52 @@
+use std::net::SocketAddr;
+
+use metrics_exporter_prometheus::PrometheusBuilder;
+
+/// Start the Prometheus exporter on `addr` (served at `/metrics`).
+pub fn install(addr: SocketAddr) -> anyhow::Result<()> {
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .install()?;
+    Ok(())
+}
+
+/// Count a request rejected before a stream was opened.
+pub fn record_rejection(reason: &'static str) {
+    metrics::counter!("heimdall_stream_rejected_requests_total", "reason" => reason).increment(1);
+}
+
+// Subscriber metrics are deliberately not labelled by client or pool: both are chosen by
+// callers, so per-subscriber series would grow without bound. Per-subscriber lag is logged
+// by subscriber id instead, with its distribution in `subscriber_max_lag_seconds`.
+
+/// A subscriber was opened (`1`) or ended (`-1`).
+pub fn add_subscribers(delta: i64) {
+    metrics::gauge!("heimdall_stream_subscribers").increment(delta as f64);
+}
+
+/// Change in the number of updates buffered across all subscribers.
+pub fn add_queued_updates(delta: i64) {
+    metrics::gauge!("heimdall_stream_subscriber_queued_updates").increment(delta as f64);
+}
+
+/// Updates discarded because a subscriber's buffer overflowed.
+pub fn record_subscriber_drop(policy: &'static str, count: u64) {
+    metrics::counter!("heimdall_stream_subscriber_dropped_total", "policy" => policy)
+        .increment(count);
+}
+
+/// Subscribers cut off under the `disconnect` overflow policy.
+pub fn record_slow_consumer_disconnect() {
+    metrics::counter!("heimdall_stream_slow_consumer_disconnects_total").increment(1);
+}
+
+/// Time between an event landing in Redis and it being handed to the subscriber.
+pub fn record_subscriber_lag(lag: std::time::Duration) {
+    metrics::histogram!("heimdall_stream_subscriber_lag_seconds").record(lag.as_secs_f64());
+}
+
+/// A subscriber's worst delivery lag over one report window: one sample per subscriber per
+/// window, so slow subscribers stand out from the per-update lag.
+pub fn record_subscriber_max_lag(lag: std::time::Duration) {
+    metrics::histogram!("heimdall_stream_subscriber_max_lag_seconds").record(lag.as_secs_f64());
+}
@@ -1,21 +1,38 @@
+use crate::auth::{ClientIdentity, QuotaTracker};
+use crate::config::SubscriberConfig;
+use crate::metrics;
 use crate::proto_stream::{
     heimdall_stream_server::HeimdallStream, AccountUpdate, EventUpdate, PoolUpdate,
     PoolUpdateRequest, SlotUpdate, StreamRequest, TransactionUpdate,
 };
+use crate::subscriber::{self, Policy, SubscriberQueue};
 use anyhow::Result;
 use redis::Client;
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
 #[derive(Debug)]
 pub struct StreamService {
     pub redis_client: Client,
+    quotas: QuotaTracker,
+    shutdown: watch::Receiver<bool>,
+    subscriber_config: SubscriberConfig,
 }
 
 impl StreamService {
-    pub fn new(redis_client: Client) -> Self {
-        Self { redis_client }
+    pub fn new(
+        redis_client: Client,
+        shutdown: watch::Receiver<bool>,
+        subscriber_config: SubscriberConfig,
+    ) -> Self {
+        Self {
+            redis_client,
+            quotas: QuotaTracker::default(),
+            shutdown,
+            subscriber_config,
+        }
     }
 }
 
@@ -61,19 +78,54 @@ impl HeimdallStream for StreamService {
         &self,
         request: Request<PoolUpdateRequest>,
     ) -> Result<Response<Self::StreamPoolUpdatesStream>, Status> {
-        let pool_id = request.into_inner().pool_id;
+        if *self.shutdown.borrow() {
+            metrics::record_rejection("shutting_down");
+            return Err(Status::unavailable("server is shutting down"));
+        }
+
+        let identity = request
+            .extensions()
+            .get::<ClientIdentity>()
+            .cloned()
+            .ok_or_else(|| Status::unauthenticated("missing client identity"))?;
+        let request = request.into_inner();
+        let pool_id = request.pool_id;
         if pool_id.is_empty() {
+            metrics::record_rejection("invalid_argument");
             return Err(Status::invalid_argument("pool_id cannot be empty"));
         }
 
-        let (tx, rx) = mpsc::channel(100);
+        let permit = self.quotas.acquire(&identity, &pool_id).map_err(|err| {
+            tracing::warn!(client = %identity.key_id, pool_id = %pool_id, ?err, "Rejected stream");
+            Status::from(err)
+        })?;
+
+        let policy = Policy::from_request(
+            request.overflow_policy,
+            self.subscriber_config.default_policy,
+        );
+        let queue = Arc::new(SubscriberQueue::new(
+            self.subscriber_config.queue_capacity,
+            policy,
+            identity.key_id.clone(),
+            pool_id.clone(),
+        ));
+
+        // Small hand-off buffer; the subscriber queue absorbs bursts.
+        let (tx, rx) = mpsc::channel(16);
         let redis_client = self.redis_client.clone();
+        let shutdown = self.shutdown.clone();
+
+        tracing::info!(subscriber = %queue.id(), client = %identity.key_id, pool_id = %pool_id, ?policy, "Client subscribed to pool updates");
 
-        tracing::info!(pool_id = %pool_id, "Client subscribed to pool updates");
+        tokio::spawn(subscriber::forward(queue.clone(), tx));
 
         tokio::spawn(async move {
+            // Released when the worker exits, freeing the client's stream slot.
+            let _permit = permit;
             if let Err(e) =
-                crate::worker::stream_pool_updates_worker(redis_client, pool_id, tx).await
+                crate::worker::stream_pool_updates_worker(redis_client, pool_id, queue, shutdown)
+                    .await
             {
                 tracing::error!("Pool updates stream worker failed: {}", e);
             }
@@ -0,0 +1,325 @@
+use crate::metrics;
+use crate::proto_stream::{OverflowPolicy, PoolUpdate};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, watch, Notify};
+use tonic::Status;
+use uuid::Uuid;
+
+/// How long we keep trying to hand a slow client its final status before giving up.
+const FINAL_STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often each subscriber reports the worst delivery lag it has seen.
+const LAG_REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Server-side counterpart of the proto `OverflowPolicy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Policy {
+    DropOldest,
+    CoalesceLatest,
+    Disconnect,
+}
+
+impl Policy {
+    /// Resolve the policy a client asked for, falling back to the server default.
+    pub fn from_request(requested: i32, default: Policy) -> Policy {
+        match OverflowPolicy::from_i32(requested) {
+            Some(OverflowPolicy::DropOldest) => Policy::DropOldest,
+            Some(OverflowPolicy::CoalesceLatest) => Policy::CoalesceLatest,
+            Some(OverflowPolicy::Disconnect) => Policy::Disconnect,
+            Some(OverflowPolicy::Unspecified) | None => default,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Policy::DropOldest => "drop_oldest",
+            Policy::CoalesceLatest => "coalesce_latest",
+            Policy::Disconnect => "disconnect",
+        }
+    }
+}
+
+struct Queued {
+    update: PoolUpdate,
+    /// Millisecond timestamp of the Redis stream entry, used for lag reporting.
+    produced_at_ms: Option<u64>,
+}
+
+#[derive(Default)]
+struct State {
+    items: VecDeque<Queued>,
+    closed: bool,
+    final_status: Option<Status>,
+    /// This queue's share of the queued-updates gauge.
+    reported_depth: usize,
+}
+
+impl State {
+    fn report_depth(&mut self) {
+        let depth = self.items.len();
+        metrics::add_queued_updates(depth as i64 - self.reported_depth as i64);
+        self.reported_depth = depth;
+    }
+}
+
+/// Bounded per-subscriber buffer between the Redis reader and the client.
+///
+/// The reader never blocks on a slow client: when the buffer is full the subscriber's
+/// [`Policy`] decides which updates are discarded, or whether the stream is ended.
+pub struct SubscriberQueue {
+    id: Uuid,
+    state: Mutex<State>,
+    notify: Notify,
+    closed_tx: watch::Sender<bool>,
+    capacity: usize,
+    policy: Policy,
+    client: String,
+    pool_id: String,
+}
+
+impl SubscriberQueue {
+    pub fn new(capacity: usize, policy: Policy, client: String, pool_id: String) -> Self {
+        metrics::add_subscribers(1);
+        Self {
+            id: Uuid::new_v4(),
+            state: Mutex::new(State::default()),
+            notify: Notify::new(),
+            closed_tx: watch::channel(false).0,
+            capacity: capacity.max(1),
+            policy,
+            client,
+            pool_id,
+        }
+    }
+
+    /// Identifies this subscriber in logs and as its Redis consumer name.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Enqueue an update. Returns `false` once the subscriber has been closed.
+    pub fn push(&self, update: PoolUpdate, produced_at_ms: Option<u64>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return false;
+        }
+
+        if state.items.len() >= self.capacity {
+            match self.policy {
+                Policy::DropOldest => {
+                    state.items.pop_front();
+                    metrics::record_subscriber_drop(self.policy.as_str(), 1);
+                }
+                Policy::CoalesceLatest => {
+                    let before = state.items.len();
+                    state.items.retain(|q| q.update.pool_id != update.pool_id);
+                    let dropped = (before - state.items.len()) as u64;
+                    if dropped == 0 {
+                        // Buffer is full of other pools; fall back to dropping the oldest.
+                        state.items.pop_front();
+                    }
+                    metrics::record_subscriber_drop(self.policy.as_str(), dropped.max(1));
+                }
+                Policy::Disconnect => {
+                    tracing::warn!(client = %self.client, pool_id = %self.pool_id, "Slow consumer, disconnecting");
+                    metrics::record_slow_consumer_disconnect();
+                    state.items.clear();
+                    state.report_depth();
+                    state.closed = true;
+                    state.final_status = Some(Status::resource_exhausted(
+                        "client is not keeping up with the update stream",
+                    ));
+                    drop(state);
+                    self.closed_tx.send_replace(true);
+                    self.notify.notify_one();
+                    return false;
+                }
+            }
+        }
+
+        state.items.push_back(Queued {
+            update,
+            produced_at_ms,
+        });
+        state.report_depth();
+        drop(state);
+        self.notify.notify_one();
+        true
+    }
+
+    /// Stop the subscriber, discarding buffered updates and optionally sending `status`.
+    pub fn close(&self, status: Option<Status>) {
+        let mut state = self.state.lock().unwrap();
+        if !state.closed {
+            state.items.clear();
+            state.report_depth();
+            state.closed = true;
+            state.final_status = status;
+        }
+        drop(state);
+        self.closed_tx.send_replace(true);
+        self.notify.notify_one();
+    }
+
+    /// Resolves once the subscriber has been closed by either side.
+    pub async fn closed(&self) {
+        let mut rx = self.closed_tx.subscribe();
+        let _ = rx.wait_for(|closed| *closed).await;
+    }
+
+    /// Report the worst delivery lag this subscriber saw over one window.
+    fn report_max_lag(&self, max: Duration) {
+        metrics::record_subscriber_max_lag(max);
+        tracing::info!(
+            subscriber = %self.id,
+            client = %self.client,
+            pool_id = %self.pool_id,
+            max_lag_ms = max.as_millis() as u64,
+            "Subscriber lag"
+        );
+    }
+
+    async fn next(&self) -> Result<Queued, Option<Status>> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(item) = state.items.pop_front() {
+                    state.report_depth();
+                    return Ok(item);
+                }
+                if state.closed {
+                    return Err(state.final_status.take());
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+impl Drop for SubscriberQueue {
+    /// Hand back whatever this subscriber still counts in the shared gauges.
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap();
+        state.items.clear();
+        state.report_depth();
+        metrics::add_subscribers(-1);
+    }
+}
+
+/// Drain `queue` into the client's response channel until either side goes away.
+pub async fn forward(queue: Arc<SubscriberQueue>, tx: mpsc::Sender<Result<PoolUpdate, Status>>) {
+    let mut lag = LagWindow::new(Instant::now());
+    loop {
+        let next = tokio::select! {
+            next = queue.next() => next,
+            _ = tx.closed() => {
+                // The client went away; stop the reader straight away.
+                queue.close(None);
+                break;
+            }
+        };
+        match next {
+            Ok(item) => {
+                if let Some(ms) = item.produced_at_ms {
+                    let item_lag = lag_since(ms);
+                    metrics::record_subscriber_lag(item_lag);
+                    if let Some(max) = lag.observe(item_lag, Instant::now()) {
+                        queue.report_max_lag(max);
+                    }
+                }
+                if tx.send(Ok(item.update)).await.is_err() {
+                    queue.close(None);
+                    break;
+                }
+            }
+            Err(status) => {
+                if let Some(status) = status {
+                    let _ = tokio::time::timeout(FINAL_STATUS_TIMEOUT, tx.send(Err(status))).await;
+                }
+                break;
+            }
+        }
+    }
+    // The last, partial window; nothing about this subscriber outlives it.
+    if let Some(max) = lag.finish() {
+        queue.report_max_lag(max);
+    }
+}
+
+/// Worst delivery lag a subscriber has seen since its last report.
+struct LagWindow {
+    max: Option<Duration>,
+    started: Instant,
+}
+
+impl LagWindow {
+    fn new(now: Instant) -> Self {
+        Self {
+            max: None,
+            started: now,
+        }
+    }
+
+    /// Record one delivery. Once [`LAG_REPORT_INTERVAL`] has passed, returns the window's
+    /// worst lag and starts a new window.
+    fn observe(&mut self, lag: Duration, now: Instant) -> Option<Duration> {
+        self.max = self.max.max(Some(lag));
+        if now.duration_since(self.started) < LAG_REPORT_INTERVAL {
+            return None;
+        }
+        self.started = now;
+        self.max.take()
+    }
+
+    fn finish(self) -> Option<Duration> {
+        self.max
+    }
+}
+
+/// Parse the millisecond timestamp out of a Redis stream entry id (`<ms>-<seq>`).
+pub fn entry_timestamp_ms(id: &str) -> Option<u64> {
+    id.split('-').next()?.parse().ok()
+}
+
+fn lag_since(ms: u64) -> Duration {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    Duration::from_millis(now.saturating_sub(ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lag_window_reports_worst_lag_once_per_interval() {
+        let start = Instant::now();
+        let mut window = LagWindow::new(start);
+        let ms = Duration::from_millis;
+
+        assert_eq!(window.observe(ms(40), start), None);
+        assert_eq!(
+            window.observe(ms(900), start + Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(
+            window.observe(ms(10), start + LAG_REPORT_INTERVAL),
+            Some(ms(900))
+        );
+
+        // A new window starts empty.
+        assert_eq!(window.observe(ms(25), start + LAG_REPORT_INTERVAL), None);
+        assert_eq!(window.finish(), Some(ms(25)));
+    }
+
+    #[test]
+    fn lag_window_without_deliveries_reports_nothing() {
+        assert_eq!(LagWindow::new(Instant::now()).finish(), None);
+    }
+}
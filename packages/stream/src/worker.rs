@@ -1,44 +1,50 @@
 use crate::proto_stream::PoolUpdate;
+use crate::subscriber::{entry_timestamp_ms, SubscriberQueue};
 use anyhow::Result;
 use redis::{streams::StreamReadOptions, AsyncCommands, RedisResult};
 use serde_json::Value;
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use tokio::sync::watch;
 use tonic::Status;
-use uuid::Uuid;
 
 pub async fn stream_pool_updates_worker(
     redis_client: redis::Client,
     pool_id_filter: String,
-    tx: mpsc::Sender<Result<PoolUpdate, Status>>,
+    queue: Arc<SubscriberQueue>,
+    mut shutdown: watch::Receiver<bool>,
 ) -> Result<()> {
     let mut connection = redis_client.get_multiplexed_async_connection().await?;
     let consumer_group = "stream-pool-updates-group";
     // Each client gets its own consumer name to avoid competing for messages
-    let consumer_name = format!("stream-pool-updates-{}", Uuid::new_v4());
+    let consumer_name = format!("stream-pool-updates-{}", queue.id());
 
     let stream_name = "heimdall:pool_events";
     let _: RedisResult<String> = connection
         .xgroup_create_mkstream(stream_name, consumer_group, "0")
         .await;
 
-    loop {
-        if tx.is_closed() {
-            // Client has disconnected, so we can clean up and exit.
-            let _: RedisResult<i32> = connection
-                .xgroup_delconsumer(stream_name, consumer_group, &consumer_name)
-                .await;
-            tracing::info!(consumer=%consumer_name, "Client disconnected, closing worker.");
-            break;
-        }
-
+    let streams = [stream_name];
+    let ids = [">"];
+    'read: loop {
         let opts = StreamReadOptions::default()
             .group(consumer_group, &consumer_name)
             .count(10)
             .block(5000); // Block for 5 seconds
 
-        let results: RedisResult<redis::streams::StreamReadReply> = connection
-            .xread_options(&[stream_name], &[">"], &opts)
-            .await;
+        let results: RedisResult<redis::streams::StreamReadReply> = tokio::select! {
+            res = connection.xread_options(&streams, &ids, &opts) => res,
+            _ = queue.closed() => {
+                // Client disconnected or was cut off as a slow consumer.
+                tracing::info!(consumer=%consumer_name, "Subscriber closed, closing worker.");
+                break;
+            }
+            _ = shutdown.wait_for(|stopping| *stopping) => {
+                // Let the client know why the stream is ending so it can reconnect elsewhere.
+                queue.close(Some(Status::unavailable("server is shutting down")));
+                tracing::info!(consumer=%consumer_name, "Server shutting down, closing worker.");
+                break;
+            }
+        };
 
         if let Ok(stream_reply) = results {
             for stream_key in stream_reply.keys {
@@ -64,9 +70,11 @@ pub async fn stream_pool_updates_worker(
                                         payload_json: payload.to_string(),
                                     };
 
-                                    if tx.send(Ok(pool_update)).await.is_err() {
-                                        // Break inner loop if send fails
-                                        break;
+                                    // Never blocks: overflow is handled by the
+                                    // subscriber's policy instead of stalling the reader.
+                                    if !queue.push(pool_update, entry_timestamp_ms(&stream_id.id)) {
+                                        tracing::info!(consumer=%consumer_name, "Subscriber closed, closing worker.");
+                                        break 'read;
                                     }
                                 }
                             }
@@ -81,5 +89,9 @@ pub async fn stream_pool_updates_worker(
             }
         }
     }
+
+    let _: RedisResult<i32> = connection
+        .xgroup_delconsumer(stream_name, consumer_group, &consumer_name)
+        .await;
     Ok(())
 }
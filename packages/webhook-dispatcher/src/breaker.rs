@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// Cooldown elapsed; one trial delivery is in flight.
+    HalfOpen,
+}
+
+/// Per-endpoint circuit breaker.
+///
+/// After `threshold` consecutive events fail (all retries exhausted) the endpoint is
+/// skipped for `cooldown`. The first event after the cooldown is a trial: success closes
+/// the circuit again, failure re-opens it.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    states: Mutex<HashMap<i32, State>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a delivery to `webhook_id` may be attempted now.
+    pub fn allow(&self, webhook_id: i32) -> bool {
+        let mut states = self.states.lock().unwrap();
+        let state = states
+            .entry(webhook_id)
+            .or_insert(State::Closed { failures: 0 });
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } if Instant::now() >= until => {
+                *state = State::HalfOpen;
+                true
+            }
+            State::Open { .. } | State::HalfOpen => false,
+        }
+    }
+
+    pub fn record_success(&self, webhook_id: i32) {
+        let mut states = self.states.lock().unwrap();
+        states.insert(webhook_id, State::Closed { failures: 0 });
+    }
+
+    /// Give back a half-open trial that ended without a delivery attempt (e.g. the event
+    /// was already claimed), so the next event becomes the trial instead.
+    pub fn release_trial(&self, webhook_id: i32) {
+        let mut states = self.states.lock().unwrap();
+        if let Some(state @ State::HalfOpen) = states.get_mut(&webhook_id) {
+            *state = State::Open {
+                until: Instant::now(),
+            };
+        }
+    }
+
+    /// Returns `true` if this failure opened the circuit.
+    pub fn record_failure(&self, webhook_id: i32) -> bool {
+        let mut states = self.states.lock().unwrap();
+        let state = states
+            .entry(webhook_id)
+            .or_insert(State::Closed { failures: 0 });
+        let failures = match *state {
+            State::Closed { failures } => failures + 1,
+            State::HalfOpen | State::Open { .. } => self.threshold,
+        };
+        if failures >= self.threshold {
+            *state = State::Open {
+                until: Instant::now() + self.cooldown,
+            };
+            true
+        } else {
+            *state = State::Closed { failures };
+            false
+        }
+    }
+
+    /// Drop state for endpoints that are no longer registered.
+    pub fn retain(&self, webhook_ids: &[i32]) {
+        let mut states = self.states.lock().unwrap();
+        states.retain(|id, _| webhook_ids.contains(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_threshold_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        assert!(!breaker.record_failure(1));
+        assert!(!breaker.record_failure(1));
+        assert!(breaker.allow(1));
+        assert!(breaker.record_failure(1));
+        assert!(!breaker.allow(1));
+        // Other endpoints are unaffected.
+        assert!(breaker.allow(2));
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        assert!(!breaker.record_failure(1));
+        breaker.record_success(1);
+        assert!(!breaker.record_failure(1));
+        assert!(breaker.allow(1));
+    }
+
+    #[test]
+    fn half_open_allows_one_trial() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        assert!(breaker.record_failure(1));
+        // Cooldown elapsed: the first caller gets the trial, the rest wait for its outcome.
+        assert!(breaker.allow(1));
+        assert!(!breaker.allow(1));
+
+        breaker.record_success(1);
+        assert!(breaker.allow(1));
+        assert!(breaker.allow(1));
+    }
+
+    #[test]
+    fn failed_trial_reopens() {
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(20));
+        for _ in 0..3 {
+            breaker.record_failure(1);
+        }
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.allow(1));
+        // A single failure in half-open re-opens, regardless of the threshold.
+        assert!(breaker.record_failure(1));
+        assert!(!breaker.allow(1));
+    }
+
+    #[test]
+    fn released_trial_lets_the_next_event_try() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        assert!(breaker.record_failure(1));
+        assert!(breaker.allow(1));
+        // The trial event turned out to be claimed already: nothing was delivered.
+        breaker.release_trial(1);
+        assert!(breaker.allow(1));
+        assert!(!breaker.allow(1));
+    }
+
+    #[test]
+    fn release_trial_leaves_other_states_alone() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        breaker.release_trial(1);
+        assert!(!breaker.record_failure(1));
+        breaker.release_trial(1);
+        // Still one failure short of opening.
+        assert!(breaker.allow(1));
+        assert!(breaker.record_failure(1));
+        breaker.release_trial(1);
+        assert!(!breaker.allow(1));
+    }
+
+    #[test]
+    fn retain_forgets_removed_endpoints() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        breaker.record_failure(1);
+        breaker.record_failure(2);
+        breaker.retain(&[2]);
+        assert!(breaker.allow(1));
+        assert!(!breaker.allow(2));
+    }
+}
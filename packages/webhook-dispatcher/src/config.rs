@@ -0,0 +1,94 @@
+use std::time::Duration;
+use store::redact_password;
+
+/// Dispatcher settings, read from the environment.
+#[derive(Clone)]
+pub struct Config {
+    pub redis_url: String,
+    /// `WEBHOOK_MAX_ATTEMPTS` – attempts per event before giving up.
+    pub max_attempts: u32,
+    /// `WEBHOOK_INITIAL_BACKOFF_MS` – delay before the first retry, doubled every attempt.
+    pub initial_backoff: Duration,
+    /// `WEBHOOK_MAX_BACKOFF_SECS`
+    pub max_backoff: Duration,
+    /// `WEBHOOK_TIMEOUT_SECS` – per-request timeout.
+    pub request_timeout: Duration,
+    /// `WEBHOOK_BREAKER_THRESHOLD` – consecutive failed events that open an endpoint's circuit.
+    pub breaker_threshold: u32,
+    /// `WEBHOOK_BREAKER_COOLDOWN_SECS` – how long an open circuit rejects deliveries.
+    pub breaker_cooldown: Duration,
+    /// `WEBHOOK_REFRESH_SECS` – how often registrations are reloaded from Postgres.
+    pub refresh_interval: Duration,
+    /// `WEBHOOK_MAX_IN_FLIGHT` – concurrent deliveries across all endpoints.
+    pub max_in_flight: usize,
+    /// `WEBHOOK_CLAIM_RETENTION_HOURS` – how long delivered event ids are remembered, so a
+    /// repeat of the event within that window isn't delivered again.
+    pub claim_retention: Duration,
+    /// `WEBHOOK_ALLOW_PRIVATE_URLS` – deliver to loopback, private and link-local addresses
+    /// too. Only meant for local setups.
+    pub allow_private_urls: bool,
+}
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            redis_url: std::env::var("REDIS_URL")
+                .unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string()),
+            max_attempts: env_or("WEBHOOK_MAX_ATTEMPTS", 6)?,
+            initial_backoff: Duration::from_millis(env_or("WEBHOOK_INITIAL_BACKOFF_MS", 500)?),
+            max_backoff: Duration::from_secs(env_or("WEBHOOK_MAX_BACKOFF_SECS", 60)?),
+            request_timeout: Duration::from_secs(env_or("WEBHOOK_TIMEOUT_SECS", 10)?),
+            breaker_threshold: env_or("WEBHOOK_BREAKER_THRESHOLD", 5)?,
+            breaker_cooldown: Duration::from_secs(env_or("WEBHOOK_BREAKER_COOLDOWN_SECS", 300)?),
+            refresh_interval: Duration::from_secs(env_or("WEBHOOK_REFRESH_SECS", 30)?),
+            max_in_flight: env_or("WEBHOOK_MAX_IN_FLIGHT", 64)?,
+            claim_retention: Duration::from_secs(
+                env_or::<u64>("WEBHOOK_CLAIM_RETENTION_HOURS", 168)? * 3600,
+            ),
+            allow_private_urls: env_or("WEBHOOK_ALLOW_PRIVATE_URLS", false)?,
+        })
+    }
+}
+
+// Written out so logging the config doesn't leak the Redis password.
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Config")
+            .field("redis_url", &redact_password(&self.redis_url))
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("request_timeout", &self.request_timeout)
+            .field("breaker_threshold", &self.breaker_threshold)
+            .field("breaker_cooldown", &self.breaker_cooldown)
+            .field("refresh_interval", &self.refresh_interval)
+            .field("max_in_flight", &self.max_in_flight)
+            .field("claim_retention", &self.claim_retention)
+            .field("allow_private_urls", &self.allow_private_urls)
+            .finish()
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match std::env::var(name) {
+        Ok(v) => Ok(v.parse()?),
+        Err(_) => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_output_redacts_redis_password() {
+        let mut config = Config::from_env().unwrap();
+        config.redis_url = "redis://:hunter2@redis:6379/0".to_string();
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("redis://:***@redis:6379/0"), "{}", debug);
+        assert!(!debug.contains("hunter2"));
+    }
+}
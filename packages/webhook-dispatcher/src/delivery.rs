@@ -0,0 +1,295 @@
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sha2::Sha256;
+use std::net::SocketAddr;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use store::{
+    models::{is_public_address, NewWebhookDelivery, Webhook},
+    Store,
+};
+use tracing::{error, warn};
+use url::{Host, Url};
+
+use crate::config::Config;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// One event to be POSTed to one webhook.
+pub struct DeliveryJob {
+    pub webhook: Webhook,
+    pub event_id: String,
+    pub event_type: String,
+    pub body: std::sync::Arc<String>,
+}
+
+/// Writes delivery attempts to `webhook_deliveries` from a dedicated thread so the
+/// synchronous diesel connection never blocks the runtime.
+#[derive(Clone)]
+pub struct DeliveryLog {
+    tx: mpsc::Sender<NewWebhookDelivery>,
+}
+
+impl DeliveryLog {
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel::<NewWebhookDelivery>();
+        std::thread::spawn(move || {
+            let mut store = Store::default();
+            for delivery in rx {
+                if let Err(e) = store.create_webhook_delivery(delivery) {
+                    error!("Failed to record webhook delivery: {}", e);
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    fn record(&self, delivery: NewWebhookDelivery) {
+        let _ = self.tx.send(delivery);
+    }
+
+    /// Log an event that was not sent to `webhook_id` at all, as attempt 0 with `reason`
+    /// as its error.
+    pub fn record_skipped(&self, webhook_id: i32, event_id: &str, event_type: &str, reason: &str) {
+        self.record(NewWebhookDelivery {
+            webhook_id,
+            event_id: event_id.to_string(),
+            event_type: event_type.to_string(),
+            attempt: 0,
+            status_code: None,
+            success: false,
+            error: Some(reason.to_string()),
+            duration_ms: 0,
+        });
+    }
+}
+
+type ClaimRequest = (i32, String, tokio::sync::oneshot::Sender<bool>);
+
+/// Claims `(webhook, event)` pairs in `webhook_event_claims` from a dedicated thread, so
+/// an event published more than once is only delivered to each webhook once.
+#[derive(Clone)]
+pub struct EventClaims {
+    tx: mpsc::Sender<ClaimRequest>,
+}
+
+impl EventClaims {
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel::<ClaimRequest>();
+        std::thread::spawn(move || {
+            let mut store = Store::default();
+            for (webhook_id, event_id, reply) in rx {
+                let claimed = store
+                    .claim_webhook_event(webhook_id, &event_id)
+                    .unwrap_or_else(|e| {
+                        // Delivering twice beats not delivering; receivers dedupe on the id.
+                        error!("Failed to claim webhook event, delivering anyway: {}", e);
+                        true
+                    });
+                let _ = reply.send(claimed);
+            }
+        });
+        Self { tx }
+    }
+
+    /// Whether this dispatcher should deliver `event_id` to `webhook_id`: `false` if it
+    /// was claimed before.
+    pub async fn claim(&self, webhook_id: i32, event_id: &str) -> bool {
+        let (reply, claimed) = tokio::sync::oneshot::channel();
+        if self
+            .tx
+            .send((webhook_id, event_id.to_string(), reply))
+            .is_err()
+        {
+            return true;
+        }
+        claimed.await.unwrap_or(true)
+    }
+}
+
+/// `HMAC-SHA256(secret, "{timestamp}.{body}")`, hex encoded.
+pub fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Deliver `job`, retrying with exponential backoff. Every attempt is logged.
+/// Returns whether the endpoint eventually accepted the event.
+pub async fn deliver(
+    http: &reqwest::Client,
+    config: &Config,
+    log: &DeliveryLog,
+    job: &DeliveryJob,
+) -> bool {
+    if !config.allow_private_urls && !is_public_url(&job.webhook.url) {
+        warn!(
+            webhook_id = job.webhook.id,
+            "Webhook URL is not a public address, skipping"
+        );
+        log.record_skipped(
+            job.webhook.id,
+            &job.event_id,
+            &job.event_type,
+            "skipped_private_address",
+        );
+        return false;
+    }
+
+    let mut backoff = config.initial_backoff;
+
+    for attempt in 1..=config.max_attempts {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let signature = sign(&job.webhook.secret, timestamp, &job.body);
+
+        let started = Instant::now();
+        let result = http
+            .post(&job.webhook.url)
+            .timeout(config.request_timeout)
+            .header("Content-Type", "application/json")
+            .header("X-Heimdall-Event-Id", &job.event_id)
+            .header("X-Heimdall-Event-Type", &job.event_type)
+            .header("X-Heimdall-Timestamp", timestamp.to_string())
+            .header("X-Heimdall-Signature", format!("sha256={}", signature))
+            .body(job.body.as_ref().clone())
+            .send()
+            .await;
+        let duration_ms = started.elapsed().as_millis() as i64;
+
+        let (status_code, retryable, error) = match &result {
+            Ok(resp) if resp.status().is_success() => (Some(resp.status().as_u16()), false, None),
+            Ok(resp) => {
+                let status = resp.status();
+                // Client errors are permanent, except timeouts and rate limiting.
+                let retryable = status.is_server_error()
+                    || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                    || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                (
+                    Some(status.as_u16()),
+                    retryable,
+                    Some(format!("HTTP {}", status)),
+                )
+            }
+            Err(e) => (None, true, Some(e.to_string())),
+        };
+        let success = error.is_none();
+
+        log.record(NewWebhookDelivery {
+            webhook_id: job.webhook.id,
+            event_id: job.event_id.clone(),
+            event_type: job.event_type.clone(),
+            attempt: attempt as i32,
+            status_code: status_code.map(i32::from),
+            success,
+            error: error.clone(),
+            duration_ms,
+        });
+
+        if success {
+            return true;
+        }
+
+        warn!(
+            webhook_id = job.webhook.id,
+            event_id = %job.event_id,
+            attempt,
+            error = error.as_deref().unwrap_or_default(),
+            "Webhook delivery failed"
+        );
+        if !retryable || attempt == config.max_attempts {
+            break;
+        }
+
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(config.max_backoff);
+    }
+
+    false
+}
+
+/// Whether `url` is a valid URL whose host, if it's an IP address, is public. Hostnames
+/// are checked when they are resolved, by [`PublicResolver`].
+fn is_public_url(url: &str) -> bool {
+    match Url::parse(url)
+        .ok()
+        .and_then(|u| u.host().map(|h| h.to_owned()))
+    {
+        Some(Host::Ipv4(ip)) => is_public_address(ip.into()),
+        Some(Host::Ipv6(ip)) => is_public_address(ip.into()),
+        Some(Host::Domain(_)) => true,
+        None => false,
+    }
+}
+
+/// System DNS resolution without the addresses webhooks may not reach, so a hostname that
+/// resolves (or later starts resolving) to an internal address can't be delivered to.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_address(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Default HTTP client for deliveries; redirects are not followed so a webhook can't
+/// bounce signed payloads to another host, and unless `allow_private_urls` is set only
+/// public addresses are connected to.
+pub fn http_client(allow_private_urls: bool) -> reqwest::Client {
+    let builder = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .connect_timeout(Duration::from_secs(5));
+    let builder = if allow_private_urls {
+        builder
+    } else {
+        builder.dns_resolver(Arc::new(PublicResolver))
+    };
+    builder.build().expect("failed to build HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        assert_eq!(
+            sign("whsec", 1_700_000_000, r#"{"a":1}"#),
+            "8ad37ba156048ae0e0a5533c75cdf26fee88b07f93cb57ee4c80adb053012032"
+        );
+    }
+
+    #[test]
+    fn signature_covers_every_input() {
+        let signature = sign("whsec", 1_700_000_000, r#"{"a":1}"#);
+        assert_ne!(signature, sign("other", 1_700_000_000, r#"{"a":1}"#));
+        assert_ne!(signature, sign("whsec", 1_700_000_001, r#"{"a":1}"#));
+        assert_ne!(signature, sign("whsec", 1_700_000_000, r#"{"a":2}"#));
+        // The separator keeps the timestamp and body apart.
+        assert_ne!(sign("whsec", 1, "2.x"), sign("whsec", 12, ".x"));
+    }
+
+    #[test]
+    fn private_ip_urls_are_refused() {
+        assert!(!is_public_url("http://127.0.0.1:8080/hook"));
+        assert!(!is_public_url("http://[::1]/hook"));
+        assert!(!is_public_url("http://169.254.169.254/latest/meta-data"));
+        assert!(!is_public_url("not a url"));
+        assert!(is_public_url("https://93.184.216.34/hook"));
+        assert!(is_public_url("https://example.com/hook"));
+    }
+}
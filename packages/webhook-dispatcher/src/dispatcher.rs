@@ -0,0 +1,213 @@
+use anyhow::Result;
+use redis::{aio::MultiplexedConnection, streams::StreamReadOptions, AsyncCommands, RedisResult};
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use store::{models::Webhook, Store};
+use tokio::sync::Semaphore;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, error, info, warn};
+
+use crate::breaker::CircuitBreaker;
+use crate::config::Config;
+use crate::delivery::{self, DeliveryJob, DeliveryLog, EventClaims};
+
+const POOL_EVENTS_STREAM: &str = "heimdall:pool_events";
+
+/// Consumes `heimdall:pool_events` and fans matching events out to registered webhooks.
+///
+/// Dispatchers share one consumer group, so several replicas split the event stream
+/// between them. Events are acknowledged once their deliveries have been scheduled;
+/// retries in progress are lost if the process dies. Each delivery first claims its
+/// `(webhook, event_id)` pair, so repeated copies of an event are delivered once.
+pub struct Dispatcher {
+    connection: MultiplexedConnection,
+    config: Arc<Config>,
+    http: reqwest::Client,
+    webhooks: Arc<RwLock<Vec<Webhook>>>,
+    breaker: Arc<CircuitBreaker>,
+    log: DeliveryLog,
+    claims: EventClaims,
+    in_flight: Arc<Semaphore>,
+    consumer_group: String,
+    consumer_name: String,
+}
+
+impl Dispatcher {
+    pub async fn new(client: redis::Client, config: Arc<Config>) -> Result<Self> {
+        let connection = client.get_multiplexed_async_connection().await?;
+        Ok(Self {
+            connection,
+            http: delivery::http_client(config.allow_private_urls),
+            webhooks: Arc::new(RwLock::new(Vec::new())),
+            breaker: Arc::new(CircuitBreaker::new(
+                config.breaker_threshold,
+                config.breaker_cooldown,
+            )),
+            log: DeliveryLog::spawn(),
+            claims: EventClaims::spawn(),
+            in_flight: Arc::new(Semaphore::new(config.max_in_flight)),
+            consumer_group: "webhook-dispatcher-group".to_string(),
+            consumer_name: format!("webhook-dispatcher-{}", uuid::Uuid::new_v4()),
+            config,
+        })
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        self.spawn_registry_refresh();
+
+        let _: RedisResult<String> = self
+            .connection
+            .xgroup_create_mkstream(POOL_EVENTS_STREAM, &self.consumer_group, "$")
+            .await;
+        info!(consumer = %self.consumer_name, "Consuming {}", POOL_EVENTS_STREAM);
+
+        loop {
+            if let Err(e) = self.consume().await {
+                error!("Error consuming pool events: {}", e);
+                sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+
+    /// Reload registrations from Postgres on a fixed interval, and forget event claims
+    /// older than the retention period.
+    fn spawn_registry_refresh(&self) {
+        let webhooks = self.webhooks.clone();
+        let breaker = self.breaker.clone();
+        let interval = self.config.refresh_interval;
+        let claim_retention = self.config.claim_retention;
+        std::thread::spawn(move || {
+            let mut store = Store::default();
+            loop {
+                match store.get_active_webhooks() {
+                    Ok(active) => {
+                        let ids: Vec<i32> = active.iter().map(|w| w.id).collect();
+                        breaker.retain(&ids);
+                        *webhooks.write().unwrap() = active;
+                    }
+                    Err(e) => error!("Failed to load webhooks: {}", e),
+                }
+                if let Err(e) = store.delete_webhook_event_claims_older_than(claim_retention) {
+                    error!("Failed to prune webhook event claims: {}", e);
+                }
+                std::thread::sleep(interval);
+            }
+        });
+    }
+
+    async fn consume(&mut self) -> Result<()> {
+        let opts = StreamReadOptions::default()
+            .group(&self.consumer_group, &self.consumer_name)
+            .count(100)
+            .block(5000);
+        let reply: redis::streams::StreamReadReply = self
+            .connection
+            .xread_options(&[POOL_EVENTS_STREAM], &[">"], &opts)
+            .await?;
+
+        for key in reply.keys {
+            for entry in key.ids {
+                if let Some(redis::Value::Data(data)) = entry.map.get("data") {
+                    self.dispatch(&entry.id, data).await;
+                }
+                let _: RedisResult<i32> = self
+                    .connection
+                    .xack(&key.key, &self.consumer_group, &[&entry.id])
+                    .await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn dispatch(&self, entry_id: &str, data: &[u8]) {
+        let Ok(mut event) = serde_json::from_slice::<Value>(data) else {
+            warn!(entry_id, "Skipping undecodable pool event");
+            return;
+        };
+        // The deterministic id laser-ingest assigns; older events only have the stream id.
+        let event_id = event
+            .get("event_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or(entry_id)
+            .to_string();
+        let event_id = event_id.as_str();
+        let pool_id = event
+            .get("pool_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let event_type = event
+            .get("event_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        let targets: Vec<Webhook> = self
+            .webhooks
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|w| w.matches(&pool_id, &event_type))
+            .cloned()
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        // Receivers resume on the stream id and dedupe on `event_id`.
+        event["id"] = Value::String(entry_id.to_string());
+        let body = Arc::new(event.to_string());
+
+        for webhook in targets {
+            if !self.breaker.allow(webhook.id) {
+                warn!(
+                    webhook_id = webhook.id,
+                    event_id, "Circuit open, skipping delivery"
+                );
+                self.log
+                    .record_skipped(webhook.id, event_id, &event_type, "skipped_circuit_open");
+                continue;
+            }
+
+            // Bounds concurrent deliveries; waiting here applies backpressure to the reader.
+            let permit = match self.in_flight.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => return,
+            };
+            let job = DeliveryJob {
+                webhook,
+                event_id: event_id.to_string(),
+                event_type: event_type.clone(),
+                body: body.clone(),
+            };
+            let http = self.http.clone();
+            let config = self.config.clone();
+            let log = self.log.clone();
+            let claims = self.claims.clone();
+            let breaker = self.breaker.clone();
+
+            tokio::spawn(async move {
+                let _permit = permit;
+                if !claims.claim(job.webhook.id, &job.event_id).await {
+                    debug!(
+                        webhook_id = job.webhook.id,
+                        event_id = %job.event_id,
+                        "Event already delivered, skipping"
+                    );
+                    // This may have been the half-open trial; without an outcome the
+                    // circuit would stay half-open for good.
+                    breaker.release_trial(job.webhook.id);
+                    return;
+                }
+                if delivery::deliver(&http, &config, &log, &job).await {
+                    breaker.record_success(job.webhook.id);
+                } else if breaker.record_failure(job.webhook.id) {
+                    warn!(
+                        webhook_id = job.webhook.id,
+                        "Circuit opened after repeated failures"
+                    );
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+use tracing::info;
+
+mod breaker;
+mod config;
+mod delivery;
+mod dispatcher;
+
+use config::Config;
+use dispatcher::Dispatcher;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+    info!("Starting Heimdall Webhook Dispatcher");
+
+    let config = Arc::new(Config::from_env()?);
+    info!(?config, "Loaded dispatcher config");
+
+    let client = redis::Client::open(config.redis_url.as_str())?;
+
+    let mut dispatcher = Dispatcher::new(client, config).await?;
+    dispatcher.run().await
+}